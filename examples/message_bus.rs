@@ -0,0 +1,70 @@
+//! Authorizing messages consumed from a bus (NATS, Kafka, ...), where sender roles travel in message headers.
+
+use std::collections::HashMap;
+
+use rbacrab::*;
+
+define_permissions! {
+    pub domain Orders {
+        Order {
+            Read => "View orders",
+            Cancel => "Cancel orders",
+        },
+    }
+}
+
+/// Headers access, as provided by bus clients (`async_nats::HeaderMap`, `rdkafka::message::Headers`, ...)
+trait MessageHeaders {
+    fn get(&self, name: &str) -> Option<&str>;
+}
+
+/// Stand-in for bus message
+struct Message {
+    subject: String,
+    headers: HashMap<String, String>,
+    payload: Vec<u8>,
+}
+
+impl MessageHeaders for Message {
+    fn get(&self, name: &str) -> Option<&str> {
+        self.headers.get(name).map(String::as_str)
+    }
+}
+
+/// Adapter every consumer handler goes through: message without roles header is checked against fallback roles
+fn authorize<P: Permission>(
+    rbac_service: &RbacService,
+    message: &impl MessageHeaders,
+    permission: P,
+) -> Result<(), RbacError> {
+    authorize_message(rbac_service, message.get(ROLES_HEADER).unwrap_or_default(), permission)
+}
+
+fn main() {
+    let mut builder = RbacService::builder();
+    builder
+        .add_role(Role::new("Default", vec!["Orders::Order::Read".to_string()]))
+        .add_role(Role::new("OrderManager", vec!["Orders::Order::*".to_string()]));
+    let rbac_service = builder.build();
+
+    let messages = [
+        Message {
+            subject: "orders.cancel".to_string(),
+            headers: HashMap::from([(ROLES_HEADER.to_string(), "OrderManager".to_string())]),
+            payload: b"42".to_vec(),
+        },
+        Message {
+            subject: "orders.cancel".to_string(),
+            headers: HashMap::new(),
+            payload: b"43".to_vec(),
+        },
+    ];
+
+    for message in &messages {
+        let order = String::from_utf8_lossy(&message.payload);
+        match authorize(&rbac_service, message, Orders::Order::Cancel) {
+            Ok(()) => println!("{}: cancelling order {}", message.subject, order),
+            Err(e) => println!("{}: rejected order {}: {}", message.subject, order, e),
+        }
+    }
+}
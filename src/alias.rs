@@ -0,0 +1,78 @@
+use std::collections::BTreeMap;
+
+use crate::{CompiledPermissions, MACHINE_ONLY, Role, split_pattern};
+
+/// Alternative names of permissions (`"Orders::Order::View"` for `"Orders::Order::Read"`), added by
+/// [.add_permission_alias()][crate::RbacServiceBuilder#method.add_permission_alias]
+#[derive(Debug, Clone, Default)]
+pub(crate) struct PermissionAliases(BTreeMap<String, String>);
+
+impl PermissionAliases {
+    pub(crate) fn insert(&mut self, alias: &str, permission: &str) {
+        self.0.insert(alias.to_string(), permission.to_string());
+    }
+
+    pub(crate) fn is_alias(&self, permission: &str) -> bool {
+        self.0.contains_key(permission)
+    }
+
+    /// Permission alias stands for, or permission itself if it isn't alias
+    pub(crate) fn resolve<'a>(&'a self, permission: &'a str) -> &'a str {
+        self.0.get(permission).map_or(permission, String::as_str)
+    }
+
+    /// Patterns with aliases replaced by permissions they stand for, `None` if none of patterns names alias.
+    /// Action set naming aliases of other object is split into pattern per object.
+    fn resolve_patterns(&self, patterns: &[String]) -> Option<Vec<String>> {
+        let mut changed = false;
+        let mut resolved = Vec::with_capacity(patterns.len());
+        for pattern in patterns {
+            let (machine_only, unmarked, condition) = split_pattern(pattern);
+            let Some((object, actions)) = unmarked.rsplit_once("::").filter(|(object, actions)| object.contains("::") && *actions != "*") else {
+                resolved.push(pattern.clone());
+                continue;
+            };
+            let actions = actions.strip_prefix('{').and_then(|a| a.strip_suffix('}')).unwrap_or(actions);
+            let mut objects: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+            for action in actions.split(',') {
+                let permission = format!("{object}::{action}");
+                let permission = match self.0.get(&permission) {
+                    Some(permission) => permission.as_str(),
+                    None => {
+                        objects.entry(object).or_default().push(action);
+                        continue;
+                    }
+                };
+                changed = true;
+                if let Some((object, action)) = permission.rsplit_once("::") {
+                    objects.entry(object).or_default().push(action);
+                }
+            }
+            for (object, actions) in objects {
+                let actions = match actions[..] {
+                    [action] => action.to_string(),
+                    _ => format!("{{{}}}", actions.join(",")),
+                };
+                let marker = if machine_only { MACHINE_ONLY } else { "" };
+                let condition = condition.map(|condition| format!(" if {condition}")).unwrap_or_default();
+                resolved.push(format!("{marker}{object}::{actions}{condition}"));
+            }
+        }
+        changed.then_some(resolved)
+    }
+
+    /// Recompiles role with aliases resolved, if its patterns name any. Role patterns are kept as written.
+    /// Returns whether role was recompiled, so it has to be interned again.
+    pub(crate) fn compile(&self, role: &mut Role) -> bool {
+        if self.0.is_empty() {
+            return false;
+        }
+        match self.resolve_patterns(&role.permissions) {
+            Some(resolved) => {
+                role.compiled_permissions = CompiledPermissions::compile(&resolved);
+                true
+            }
+            None => false,
+        }
+    }
+}
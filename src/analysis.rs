@@ -0,0 +1,146 @@
+use std::collections::BTreeSet;
+
+use crate::{ConditionInput, RbacDecision, RbacService, Role, RoleStorage, service::PermissionKey};
+
+/// Permission both granted and denied by service roles, found by [.analyze_conflicts()][RbacService#method.analyze_conflicts]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PolicyConflict {
+    pub permission: String,
+    /// Allow roles granting permission, sorted by name
+    pub allowed_by: Vec<String>,
+    /// Deny roles taking permission away, sorted by name
+    pub denied_by: Vec<String>,
+    /// Outcome for subject holding all of these roles, according to [Role::priority]
+    pub denied: bool,
+}
+
+impl<R: RoleStorage> RbacService<R> {
+    /// Reports permissions which are granted by some roles and denied by others, so subject holding both would get contradictory policy.
+    ///
+    /// Permissions checked are registered ones plus ones explicitly named in role patterns,
+    /// so conflicts between wildcards only are found only for registered permissions.
+    pub fn analyze_conflicts(&self) -> Vec<PolicyConflict> {
+        let mut roles = self.get_roles();
+        roles.retain(Role::is_active);
+        roles.sort_by(|a, b| a.name.cmp(&b.name));
+
+        let mut candidates: BTreeSet<(String, String, String)> = self
+            .get_all_permissions()
+            .into_iter()
+            .map(|info| (info.domain, info.object_type, info.action))
+            .collect();
+        candidates.extend(roles.iter().flat_map(explicit_permissions));
+
+        candidates
+            .into_iter()
+            .filter_map(|(domain, object_type, action)| {
+                let matching: Vec<&Role> = roles
+                    .iter()
+                    .filter(|role| role.compiled_permissions.matches(&domain, &object_type, &action))
+                    .collect();
+                let names = |deny: bool| -> Vec<String> {
+                    matching.iter().filter(|role| role.deny == deny).map(|role| role.name.clone()).collect()
+                };
+                let (allowed_by, denied_by) = (names(false), names(true));
+                if allowed_by.is_empty() || denied_by.is_empty() {
+                    return None;
+                }
+
+                Some(PolicyConflict {
+                    permission: format!("{}::{}::{}", domain, object_type, action),
+                    allowed_by,
+                    denied_by,
+                    denied: matching.iter().map(|role| (role.priority, role.deny)).max().is_some_and(|(_, deny)| deny),
+                })
+            })
+            .collect()
+    }
+}
+
+/// Historical checks which would fail without role, found by [.impact_of_removing()][RbacService#method.impact_of_removing]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RoleRemovalImpact {
+    pub role: String,
+    /// Number of allowed decisions checked
+    pub allowed: usize,
+    /// Allowed decisions which would be denied without role, in recorded order
+    pub lost: Vec<RbacDecision>,
+}
+
+impl RoleRemovalImpact {
+    /// Role may be removed without breaking any recorded check
+    pub fn is_safe(&self) -> bool {
+        self.lost.is_empty()
+    }
+
+    /// Subjects losing access, sorted
+    pub fn affected_subjects(&self) -> Vec<&str> {
+        let subjects: BTreeSet<&str> = self.lost.iter().filter_map(|decision| decision.subject.as_deref()).collect();
+        subjects.into_iter().collect()
+    }
+}
+
+impl<R: RoleStorage> RbacService<R> {
+    /// Replays allowed decisions recorded earlier (e.g. by [AuditSink][crate::AuditSink]) against current roles without `role`,
+    /// reporting ones which would become denies, so legacy role may be retired confidently.
+    ///
+    /// Decisions don't carry subject attributes, so remaining conditional grants are evaluated without them
+    /// and checks allowed only by conditions are reported as lost. Only remaining roles are evaluated: feature gates
+    /// and quotas don't depend on roles, so their current state doesn't count as loss.
+    pub fn impact_of_removing(&self, role: &str, recorded_checks: &[RbacDecision]) -> RoleRemovalImpact {
+        let without = |roles: &[String]| -> Vec<String> { roles.iter().filter(|r| *r != role).cloned().collect() };
+        let mut impact = RoleRemovalImpact {
+            role: role.to_string(),
+            allowed: 0,
+            lost: Vec::new(),
+        };
+        let input = ConditionInput::default();
+        self.with_policy(|policy| {
+            for decision in recorded_checks.iter().filter(|decision| decision.allowed) {
+                impact.allowed += 1;
+                if !decision.roles.iter().any(|r| r == role) {
+                    continue;
+                }
+                let Ok(permission) = self.parse_permission(&decision.permission) else {
+                    continue;
+                };
+                let mut remaining = without(&decision.roles);
+                // Subject left without roles falls back to fallback roles, which may not have role either
+                if remaining.is_empty() && !decision.fallback_used {
+                    remaining = without(self.fallback_roles());
+                }
+                let PermissionKey { domain, object_type, action, .. } = permission;
+                if remaining.is_empty() || !self.roles_match(policy.roles, &remaining, &input, domain, object_type, action) {
+                    impact.lost.push(decision.clone());
+                }
+            }
+        });
+        impact
+    }
+}
+
+/// Non-wildcard permissions named in role patterns, with action sets expanded
+pub(crate) fn explicit_permissions(role: &Role) -> Vec<(String, String, String)> {
+    let mut permissions = Vec::new();
+    for pattern in role.compiled_permissions.to_patterns() {
+        // Conditional and machine-only grants aren't considered, as conflict may never happen
+        if let (true, _, _) | (_, _, Some(_)) = crate::split_pattern(&pattern) {
+            continue;
+        }
+        let parts: Vec<&str> = pattern.split("::").collect();
+        let [domain, object_type, actions] = parts[..] else {
+            continue;
+        };
+        let actions = match actions.strip_prefix('{').and_then(|a| a.strip_suffix('}')) {
+            Some(set) => set.split(',').collect(),
+            None if actions != "*" => vec![actions],
+            None => continue,
+        };
+        permissions.extend(
+            actions
+                .into_iter()
+                .map(|action| (domain.to_string(), object_type.to_string(), action.to_string())),
+        );
+    }
+    permissions
+}
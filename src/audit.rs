@@ -0,0 +1,155 @@
+use crate::{DeprecationWarning, RbacDecision};
+
+#[cfg(feature = "json")]
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, Write},
+    path::PathBuf,
+    sync::{Mutex, PoisonError},
+};
+
+/// Receiver of check decisions, registered by [.add_audit_sink()][crate::RbacServiceBuilder#method.add_audit_sink].
+///
+/// Sinks are called synchronously on every check, so they should be cheap or offload work.
+/// Implemented for closures, so `|decision: &RbacDecision| {...}` may be used as sink.
+pub trait AuditSink: Send + Sync {
+    fn on_decision(&self, decision: &RbacDecision);
+
+    /// Called before [.on_decision()][AuditSink::on_decision] for checks of deprecated permissions, if their reporting is enabled
+    fn on_deprecated(&self, _warning: &DeprecationWarning) {}
+}
+
+impl<F: Fn(&RbacDecision) + Send + Sync> AuditSink for F {
+    fn on_decision(&self, decision: &RbacDecision) {
+        self(decision)
+    }
+}
+
+/// Writes decisions to stderr, one line per decision prefixed with UNIX time in milliseconds
+#[derive(Debug, Default, Clone, Copy)]
+pub struct StderrAuditSink;
+
+impl AuditSink for StderrAuditSink {
+    fn on_decision(&self, decision: &RbacDecision) {
+        eprintln!("{} rbac: {}", decision.unix_millis(), decision);
+    }
+
+    fn on_deprecated(&self, warning: &DeprecationWarning) {
+        eprintln!("rbac: {}", warning);
+    }
+}
+
+/// Writes decisions as JSON lines to file, rotating it when it grows over size limit:
+/// `audit.log` is renamed to `audit.log.1`, `audit.log.1` to `audit.log.2` and so on, keeping up to `max_files` rotated files.
+///
+/// Write errors are dropped, as checks can't fail because of audit.
+#[cfg(feature = "json")]
+pub struct JsonLinesAuditSink {
+    path: PathBuf,
+    max_bytes: u64,
+    max_files: usize,
+    file: Mutex<(File, u64)>,
+}
+
+#[cfg(feature = "json")]
+impl JsonLinesAuditSink {
+    pub fn new(path: impl Into<PathBuf>, max_bytes: u64, max_files: usize) -> io::Result<Self> {
+        let path = path.into();
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let written = file.metadata()?.len();
+        Ok(JsonLinesAuditSink {
+            path,
+            max_bytes,
+            max_files,
+            file: Mutex::new((file, written)),
+        })
+    }
+
+    fn rotated(&self, index: usize) -> PathBuf {
+        let mut path = self.path.clone().into_os_string();
+        path.push(format!(".{}", index));
+        path.into()
+    }
+
+    fn rotate(&self) -> io::Result<File> {
+        if self.max_files > 0 {
+            for index in (1..self.max_files).rev() {
+                let from = self.rotated(index);
+                if from.exists() {
+                    fs::rename(from, self.rotated(index + 1))?;
+                }
+            }
+            fs::rename(&self.path, self.rotated(1))?;
+        }
+        File::create(&self.path)
+    }
+
+    fn write(&self, decision: &RbacDecision) -> io::Result<()> {
+        let mut line = serde_json::to_vec(&serde_json::json!({
+            "timestamp_ms": decision.unix_millis() as u64,
+            "allowed": decision.allowed,
+            "permission": decision.permission,
+            "subject": decision.subject,
+            "roles": decision.roles,
+            "matched_role": decision.matched_role,
+            "matched_pattern": decision.matched_pattern,
+            "fallback_used": decision.fallback_used,
+            "generation": decision.generation,
+            "blocked_by": decision.blocked_by,
+            "correlation_id": decision.correlation_id,
+        }))?;
+        line.push(b'\n');
+
+        let mut file = self.file.lock().unwrap_or_else(PoisonError::into_inner);
+        if file.1 > 0 && file.1 + line.len() as u64 > self.max_bytes {
+            *file = (self.rotate()?, 0);
+        }
+        file.0.write_all(&line)?;
+        file.1 += line.len() as u64;
+        Ok(())
+    }
+}
+
+#[cfg(feature = "json")]
+impl AuditSink for JsonLinesAuditSink {
+    fn on_decision(&self, decision: &RbacDecision) {
+        let _ = self.write(decision);
+    }
+}
+
+/// Sends decisions to local syslog daemon (`/dev/log`) with `authpriv` facility: allowed checks as `info`, denied ones as `notice`
+#[cfg(unix)]
+pub struct SyslogAuditSink {
+    socket: std::os::unix::net::UnixDatagram,
+    tag: String,
+}
+
+#[cfg(unix)]
+impl SyslogAuditSink {
+    const AUTHPRIV: u8 = 10;
+    const NOTICE: u8 = 5;
+    const INFO: u8 = 6;
+
+    pub fn new(tag: &str) -> std::io::Result<Self> {
+        Self::with_socket("/dev/log", tag)
+    }
+
+    /// Sends messages to given syslog socket instead of `/dev/log`
+    pub fn with_socket(path: impl AsRef<std::path::Path>, tag: &str) -> std::io::Result<Self> {
+        let socket = std::os::unix::net::UnixDatagram::unbound()?;
+        socket.connect(path)?;
+        Ok(SyslogAuditSink {
+            socket,
+            tag: tag.to_string(),
+        })
+    }
+}
+
+#[cfg(unix)]
+impl AuditSink for SyslogAuditSink {
+    fn on_decision(&self, decision: &RbacDecision) {
+        let severity = if decision.allowed { Self::INFO } else { Self::NOTICE };
+        let message = format!("<{}>{}: {}", Self::AUTHPRIV * 8 + severity, self.tag, decision);
+        let _ = self.socket.send(message.as_bytes());
+    }
+}
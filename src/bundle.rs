@@ -0,0 +1,104 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{MutableRoleStorage, PermissionCatalog, RbacError, RbacService, Role, RoleStorage, Severity, policy::roles_hash};
+
+/// Current version of bundle format ([PolicyBundle])
+pub const BUNDLE_FORMAT_VERSION: u32 = 1;
+
+/// Policy shipped as single versioned artifact: permission catalog it was written for, roles, and metadata to verify them.
+/// Created by [.export_bundle()][crate::RbacService#method.export_bundle] and applied by [.load_bundle()][crate::RbacService#method.load_bundle].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PolicyBundle {
+    /// Format version, see [BUNDLE_FORMAT_VERSION]
+    #[serde(default)]
+    pub version: u32,
+    pub catalog: PermissionCatalog,
+    pub roles: Vec<Role>,
+    /// [Generation][crate::RbacService#method.generation] of exporting service
+    #[serde(default)]
+    pub generation: u64,
+    /// [Policy hash][crate::RbacService#method.policy_hash] of roles, checked on load
+    pub policy_hash: u64,
+    /// Detached signature of [.signing_payload()][PolicyBundle#method.signing_payload], made and verified by deploy pipeline
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<String>,
+}
+
+impl PolicyBundle {
+    /// Canonical JSON of bundle without signature: the bytes signature is made for
+    #[cfg(feature = "json")]
+    pub fn signing_payload(&self) -> Vec<u8> {
+        let unsigned = PolicyBundle {
+            signature: None,
+            ..self.clone()
+        };
+        serde_json::to_vec(&unsigned).unwrap_or_default()
+    }
+
+    /// Fails, if roles don't match [policy_hash][PolicyBundle::policy_hash], e.g. when bundle was edited by hand or corrupted
+    pub fn verify_hash(&self) -> Result<(), RbacError> {
+        let actual = roles_hash(self.roles.clone());
+        match actual == self.policy_hash {
+            true => Ok(()),
+            false => Err(RbacError::InvalidRoleData(format!(
+                "bundle policy hash {:016x} doesn't match its roles ({:016x})",
+                self.policy_hash, actual
+            ))),
+        }
+    }
+}
+
+impl<R: RoleStorage> RbacService<R> {
+    /// Current catalog and roles (sorted by name) as [PolicyBundle], unsigned
+    pub fn export_bundle(&self) -> PolicyBundle {
+        // Roles, their hash and generation are taken from the same roles
+        let (mut roles, generation) = self.with_policy(|policy| (policy.roles.values().cloned().collect::<Vec<Role>>(), policy.generation));
+        roles.sort_by(|a, b| a.name.cmp(&b.name));
+        PolicyBundle {
+            version: BUNDLE_FORMAT_VERSION,
+            catalog: self.catalog(),
+            policy_hash: roles_hash(roles.clone()),
+            roles,
+            generation,
+            signature: None,
+        }
+    }
+}
+
+impl<R: MutableRoleStorage> RbacService<R> {
+    /// Replaces service roles with roles of bundle, keeping their revisions. Bundle is rejected, if its roles don't match its hash.
+    ///
+    /// When permissions are registered, bundle catalog is compared with them, so policy written for different app version is caught:
+    /// mismatch is handled according to [.set_bundle_check()][crate::RbacServiceBuilder#method.set_bundle_check].
+    /// With [Severity::Warn] bundle is loaded and mismatch ([RbacError::IncompatibleBundle]) is returned for caller to report.
+    pub fn load_bundle(&self, bundle: &PolicyBundle) -> Result<Option<RbacError>, RbacError> {
+        bundle.verify_hash()?;
+        let warning = self.check_bundle_catalog(&bundle.catalog)?;
+        let mut updater = self.updater_clean();
+        for role in &bundle.roles {
+            updater.insert_role(role.clone());
+        }
+        updater.update(self);
+        Ok(warning)
+    }
+}
+
+impl<R: RoleStorage> RbacService<R> {
+    /// Compares bundle catalog with registered permissions, returning mismatch as warning unless it's configured to be an error
+    fn check_bundle_catalog(&self, catalog: &PermissionCatalog) -> Result<Option<RbacError>, RbacError> {
+        if self.bundle_check == Severity::Ignore || self.get_all_permissions().is_empty() {
+            return Ok(None);
+        }
+        let diff = PermissionCatalog::diff(catalog, &self.catalog());
+        let renamed = diff.renamed.iter();
+        let error = RbacError::IncompatibleBundle {
+            missing: diff.added.iter().chain(renamed.clone().map(|(_, to)| to)).cloned().collect(),
+            extra: diff.removed.iter().chain(renamed.map(|(from, _)| from)).cloned().collect(),
+        };
+        match (diff.is_empty(), self.bundle_check) {
+            (true, _) => Ok(None),
+            (false, Severity::Error) => Err(error),
+            (false, _) => Ok(Some(error)),
+        }
+    }
+}
@@ -0,0 +1,76 @@
+use std::{
+    collections::HashMap,
+    hash::{BuildHasher, RandomState},
+    sync::{Arc, PoisonError, RwLock},
+};
+
+use smallvec::SmallVec;
+
+use crate::{RbacService, RoleStorage, session::MergedPermissions};
+
+/// Sorted, deduplicated role names of subject; most subjects have just a few roles, so they are kept inline
+type RoleSet<'a> = SmallVec<[&'a str; 4]>;
+
+struct Entry {
+    roles: Box<[String]>,
+    generation: u64,
+    merged: Arc<MergedPermissions>,
+}
+
+/// Merged permissions per distinct role combination, enabled by [.set_combination_cache()][crate::RbacServiceBuilder#method.set_combination_cache].
+///
+/// Entries are tagged with service generation they were merged at, so entries merged before update are never used after it.
+/// Entries are found by hash of role set, so cache hit doesn't allocate.
+pub(crate) struct CombinationCache {
+    max_entries: usize,
+    hasher: RandomState,
+    /// Role set hash → entries with that hash
+    entries: RwLock<HashMap<u64, SmallVec<[Entry; 1]>>>,
+}
+
+impl CombinationCache {
+    pub(crate) fn new(max_entries: usize) -> Self {
+        CombinationCache {
+            max_entries,
+            hasher: RandomState::new(),
+            entries: RwLock::default(),
+        }
+    }
+
+    /// Merged permissions of roles, merging them if combination isn't cached yet
+    pub(crate) fn get<R: RoleStorage, T: AsRef<str>>(&self, rbac_service: &RbacService<R>, roles: &[T]) -> Arc<MergedPermissions> {
+        let mut key: RoleSet = roles.iter().map(AsRef::as_ref).collect();
+        key.sort_unstable();
+        key.dedup();
+        let hash = self.hasher.hash_one(&key);
+        let same_roles = |entry: &Entry| entry.roles.iter().map(String::as_str).eq(key.iter().copied());
+
+        // Generation is read before roles, so concurrent update only makes entry look stale
+        let generation = rbac_service.generation();
+        if let Some(bucket) = self.entries.read().unwrap_or_else(PoisonError::into_inner).get(&hash)
+            && let Some(entry) = bucket.iter().find(|entry| same_roles(entry))
+            && entry.generation == generation
+        {
+            return entry.merged.clone();
+        }
+
+        let merged = Arc::new(rbac_service.merged_permissions(&key));
+        let mut entries = self.entries.write().unwrap_or_else(PoisonError::into_inner);
+        // Subjects may present arbitrary role lists, so cache is bounded and simply starts over when full
+        if entries.len() >= self.max_entries && !entries.contains_key(&hash) {
+            entries.clear();
+        }
+        let bucket = entries.entry(hash).or_default();
+        bucket.retain(|entry| !same_roles(entry));
+        bucket.push(Entry {
+            roles: key.iter().map(|role| role.to_string()).collect(),
+            generation,
+            merged: merged.clone(),
+        });
+        merged
+    }
+
+    pub(crate) fn clear(&self) {
+        self.entries.write().unwrap_or_else(PoisonError::into_inner).clear();
+    }
+}
@@ -0,0 +1,241 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{PermissionInfo, RbacService, Role, RoleStorage, analysis::explicit_permissions, suggest::edit_distance};
+
+/// Registered permissions of a service, taken by [.catalog()][RbacService#method.catalog].
+///
+/// Serializable, so catalog of one deployment may be stored and compared with next one by [PermissionCatalog::diff].
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct PermissionCatalog {
+    permissions: BTreeMap<String, PermissionInfo>,
+}
+
+impl PermissionCatalog {
+    /// Permissions sorted by full name
+    pub fn permissions(&self) -> impl Iterator<Item = &PermissionInfo> {
+        self.permissions.values()
+    }
+
+    pub fn contains(&self, permission: &str) -> bool {
+        self.permissions.contains_key(permission)
+    }
+
+    pub fn len(&self) -> usize {
+        self.permissions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.permissions.is_empty()
+    }
+
+    /// Hash of permission full names, stable across builds and platforms like [policy hash][RbacService#method.policy_hash],
+    /// so consumers of data indexed by catalog position (e.g. claimed permission bitmaps) may verify they have the same catalog
+    pub fn hash(&self) -> u64 {
+        crate::policy::names_hash(self.permissions.keys().map(String::as_str))
+    }
+
+    /// Compares two catalogs.
+    ///
+    /// Permission gone from `old` is reported as renamed, when exactly one added permission of the same domain
+    /// has the same (non-empty) description, as renames rarely touch descriptions.
+    pub fn diff(old: &PermissionCatalog, new: &PermissionCatalog) -> CatalogDiff {
+        let mut removed: Vec<&PermissionInfo> = old.permissions().filter(|info| !new.contains(&info.full_name)).collect();
+        let mut added: Vec<&PermissionInfo> = new.permissions().filter(|info| !old.contains(&info.full_name)).collect();
+
+        let key = |info: &PermissionInfo| (info.domain.clone(), info.description.clone());
+        let count = |infos: &[&PermissionInfo]| {
+            let mut counts: BTreeMap<(String, String), usize> = BTreeMap::new();
+            for info in infos.iter().filter(|info| !info.description.is_empty()) {
+                *counts.entry(key(info)).or_default() += 1;
+            }
+            counts
+        };
+        let (removed_counts, added_counts) = (count(&removed), count(&added));
+        let unique = |counts: &BTreeMap<(String, String), usize>, info: &PermissionInfo| counts.get(&key(info)) == Some(&1);
+
+        let mut renamed = Vec::new();
+        removed.retain(|from| {
+            if !unique(&removed_counts, from) || !unique(&added_counts, from) {
+                return true;
+            }
+            let Some(index) = added.iter().position(|to| key(to) == key(from)) else {
+                return true;
+            };
+            renamed.push((from.full_name.clone(), added.remove(index).full_name.clone()));
+            false
+        });
+
+        CatalogDiff {
+            added: added.into_iter().map(|info| info.full_name.clone()).collect(),
+            removed: removed.into_iter().map(|info| info.full_name.clone()).collect(),
+            renamed,
+        }
+    }
+
+    /// Permissions matching query, best matches first, see [PermissionQuery]
+    pub fn search(&self, query: &PermissionQuery) -> Vec<PermissionInfo> {
+        let mut matches: Vec<(usize, &PermissionInfo)> = self
+            .permissions()
+            .filter(|info| query.domain.as_ref().is_none_or(|domain| *domain == info.domain))
+            .filter(|info| query.object_type.as_ref().is_none_or(|object_type| *object_type == info.object_type))
+            .filter_map(|info| Some((query.score(info)?, info)))
+            .collect();
+        // Sort is stable, so equally good matches stay in full name order
+        matches.sort_by_key(|(score, _)| *score);
+        matches
+            .into_iter()
+            .take(query.limit.unwrap_or(usize::MAX))
+            .map(|(_, info)| info.clone())
+            .collect()
+    }
+}
+
+/// Query of [.search_permissions()][RbacService#method.search_permissions], e.g. for autocomplete in admin tools.
+///
+/// Text is matched case-insensitively: exact full name first, then names and actions starting with text, names containing it,
+/// descriptions containing it and finally names with part (domain, object or action) few typos away from text
+/// (one per four characters of text, at most two).
+/// Empty text matches every permission passing filters.
+#[derive(Debug, Clone, Default)]
+pub struct PermissionQuery {
+    text: String,
+    domain: Option<String>,
+    object_type: Option<String>,
+    limit: Option<usize>,
+}
+
+impl PermissionQuery {
+    pub fn new(text: &str) -> Self {
+        PermissionQuery {
+            text: text.to_lowercase(),
+            ..Default::default()
+        }
+    }
+
+    /// Only permissions of domain
+    pub fn set_domain(&mut self, domain: &str) -> &mut Self {
+        self.domain = Some(domain.to_string());
+        self
+    }
+
+    /// Only permissions of object type (of any domain, unless domain is set too)
+    pub fn set_object_type(&mut self, object_type: &str) -> &mut Self {
+        self.object_type = Some(object_type.to_string());
+        self
+    }
+
+    /// Returns at most `limit` best matches
+    pub fn set_limit(&mut self, limit: usize) -> &mut Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// Rank of match, lower is better, `None` if permission doesn't match
+    fn score(&self, info: &PermissionInfo) -> Option<usize> {
+        let text = self.text.as_str();
+        let name = info.full_name.to_lowercase();
+        let action = info.action.to_lowercase();
+        if name == text {
+            return Some(0);
+        }
+        if name.starts_with(text) || action.starts_with(text) {
+            return Some(1);
+        }
+        if name.contains(text) {
+            return Some(2);
+        }
+        if info.description.to_lowercase().contains(text) {
+            return Some(3);
+        }
+        // Typo per four characters, so short words don't match everything
+        let max_typos = (text.chars().count() / 4).min(2);
+        let typos = name.split("::").map(|part| edit_distance(text, part)).min()?;
+        (typos <= max_typos).then_some(4 + typos)
+    }
+}
+
+impl From<&str> for PermissionQuery {
+    fn from(text: &str) -> Self {
+        PermissionQuery::new(text)
+    }
+}
+
+impl FromIterator<PermissionInfo> for PermissionCatalog {
+    fn from_iter<I: IntoIterator<Item = PermissionInfo>>(iter: I) -> Self {
+        PermissionCatalog {
+            permissions: iter.into_iter().map(|info| (info.full_name.clone(), info)).collect(),
+        }
+    }
+}
+
+/// Changes between two catalogs, made by [PermissionCatalog::diff].
+///
+/// Display gives release notes form: one `+ added`, `- removed` or `~ old -> new` line per change.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CatalogDiff {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    /// Pairs of old and new permission names
+    pub renamed: Vec<(String, String)>,
+}
+
+impl CatalogDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.renamed.is_empty()
+    }
+
+    /// Roles explicitly naming removed or renamed permissions (wildcards aside), mapped to names of these permissions
+    pub fn affected_roles(&self, roles: &[Role]) -> BTreeMap<String, Vec<String>> {
+        let gone: BTreeSet<&str> = self
+            .removed
+            .iter()
+            .chain(self.renamed.iter().map(|(from, _)| from))
+            .map(String::as_str)
+            .collect();
+
+        roles
+            .iter()
+            .filter_map(|role| {
+                let names: BTreeSet<String> = explicit_permissions(role)
+                    .into_iter()
+                    .map(|(domain, object_type, action)| format!("{}::{}::{}", domain, object_type, action))
+                    .filter(|name| gone.contains(name.as_str()))
+                    .collect();
+                (!names.is_empty()).then(|| (role.name.clone(), names.into_iter().collect()))
+            })
+            .collect()
+    }
+}
+
+impl fmt::Display for CatalogDiff {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for name in &self.added {
+            writeln!(f, "+ {}", name)?;
+        }
+        for name in &self.removed {
+            writeln!(f, "- {}", name)?;
+        }
+        for (from, to) in &self.renamed {
+            writeln!(f, "~ {} -> {}", from, to)?;
+        }
+        Ok(())
+    }
+}
+
+impl<R: RoleStorage> RbacService<R> {
+    /// Catalog of registered permissions
+    pub fn catalog(&self) -> PermissionCatalog {
+        self.get_all_permissions().into_iter().collect()
+    }
+
+    /// Registered permissions matching query (`"invoice"`, or [PermissionQuery] with filters), best matches first
+    pub fn search_permissions(&self, query: impl Into<PermissionQuery>) -> Vec<PermissionInfo> {
+        self.catalog().search(&query.into())
+    }
+}
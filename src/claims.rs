@@ -0,0 +1,168 @@
+use std::collections::BTreeSet;
+
+use serde_json::{Value, json};
+
+use crate::{ConditionInput, PermissionCatalog, PermissionCore, RbacError, RbacService, RbacSubject, RoleStorage, service::PermissionKey};
+
+fn invalid(reason: &str) -> RbacError {
+    RbacError::InvalidRoleData(format!("invalid claims: {}", reason))
+}
+
+/// Bits of claimed permission bitmap, made for given catalog of registered permissions
+fn claimed_bits(claims: &Value, bitmap: &str, catalog: &PermissionCatalog) -> Result<Vec<bool>, RbacError> {
+    let permission_count = catalog.len();
+    // Bits are positions in catalog, so catalog of the same size, but other permissions would map them to wrong permissions
+    if claims["permission_count"].as_u64() != Some(permission_count as u64)
+        || claims["permission_catalog_hash"].as_str() != Some(format!("{:016x}", catalog.hash()).as_str())
+    {
+        return Err(invalid("permissions were made for different permission catalog"));
+    }
+    (0..permission_count)
+        .map(|i| {
+            let byte = bitmap.get(i / 8 * 2..i / 8 * 2 + 2).and_then(|byte| u8::from_str_radix(byte, 16).ok());
+            byte.map(|byte| byte & (1 << (i % 8)) != 0).ok_or_else(|| invalid("malformed permissions"))
+        })
+        .collect()
+}
+
+impl<R: RoleStorage> RbacService<R> {
+    /// Compact claims of subject effective permissions to embed into tokens: `{"roles": [...], "policy_hash": "..."}`,
+    /// with `"anonymous": true` or `"machine": true` for [anonymous][RbacSubject::is_anonymous] and [machine][RbacSubject::is_machine] subjects.
+    /// Downstream services sharing role store check them with [.check_claims()][RbacService#method.check_claims].
+    pub fn claims_for(&self, subject: &impl RbacSubject) -> Value {
+        let mut claims = json!({
+            "roles": self.subject_roles(subject),
+            "policy_hash": format!("{:016x}", self.policy_hash()),
+        });
+        if subject.is_anonymous() {
+            claims["anonymous"] = true.into();
+        }
+        if subject.is_machine() {
+            claims["machine"] = true.into();
+        }
+        claims
+    }
+
+    /// [Claims][RbacService#method.claims_for] with bitmap of granted registered permissions (`"permissions"`, hex, bit per permission in full name order),
+    /// so downstream services registering the same permissions may check them without role store at all.
+    /// Claims carry `"permission_catalog_hash"` ([PermissionCatalog::hash]), so bitmap is rejected by services with other permissions.
+    ///
+    /// Bitmap is evaluated against subject roles and attributes only: permissions granted by conditions on check context aren't included,
+    /// and feature gates and quotas aren't applied, as their state changes during token lifetime. They stay the job of service checking claims.
+    pub fn claims_with_permissions_for(&self, subject: &impl RbacSubject) -> Value {
+        let input = ConditionInput::of(subject);
+        let roles = self.checked_roles(self.subject_roles(subject), &input);
+        let all_permissions = self.get_all_permissions();
+        let mut bitmap = vec![0u8; all_permissions.len().div_ceil(8)];
+        self.with_policy(|policy| {
+            for (i, info) in all_permissions.iter().enumerate() {
+                if self.roles_match(policy.roles, &roles.names, &input, &info.domain, &info.object_type, &info.action) {
+                    bitmap[i / 8] |= 1 << (i % 8);
+                }
+            }
+        });
+
+        let mut claims = self.claims_for(subject);
+        claims["permissions"] = bitmap.iter().map(|byte| format!("{:02x}", byte)).collect::<String>().into();
+        claims["permission_count"] = all_permissions.len().into();
+        claims["permission_catalog_hash"] = format!("{:016x}", self.catalog().hash()).into();
+        claims
+    }
+
+    /// Checks permission against claims made by [.claims_for()][RbacService#method.claims_for]: against permission bitmap, if claims have it,
+    /// or against claimed roles otherwise. Bitmap made for different set of registered permissions is rejected.
+    /// Feature gates of this service apply to both.
+    #[track_caller]
+    pub fn check_claims<P: PermissionCore + ?Sized>(&self, claims: &Value, permission: impl AsRef<P>) -> Result<(), RbacError> {
+        let permission = PermissionKey::of(permission.as_ref());
+        let Some(bitmap) = claims["permissions"].as_str() else {
+            let roles: Vec<&str> = claims["roles"]
+                .as_array()
+                .ok_or_else(|| invalid("no roles"))?
+                .iter()
+                .filter_map(Value::as_str)
+                .collect();
+            let input = ConditionInput {
+                anonymous: claims["anonymous"].as_bool().unwrap_or(false),
+                machine: claims["machine"].as_bool().unwrap_or(false),
+                ..Default::default()
+            };
+            return self.check_roles(None, &input, &roles, permission);
+        };
+        self.gates.check(permission)?;
+
+        let catalog = self.catalog();
+        let bits = claimed_bits(claims, bitmap, &catalog)?;
+        let i = catalog.permissions().position(|info| info.full_name == permission.full_name).ok_or_else(|| invalid("permission isn't registered"))?;
+        match bits[i] {
+            true => Ok(()),
+            false => Err(RbacError::PermissionDenied(permission.to_string())),
+        }
+    }
+}
+
+/// Checker of claims made by [.claims_for()][RbacService#method.claims_for] without service or role store,
+/// for stateless enforcement points (gateways, edge functions) knowing only [catalog][PermissionCatalog] of permissions.
+///
+/// Permissions are answered from claimed permission bitmap only, claimed roles may be checked by [.has_role()][RbacVerifier#method.has_role].
+#[derive(Debug, Clone)]
+pub struct RbacVerifier {
+    roles: Vec<String>,
+    policy_hash: Option<String>,
+    /// Granted permissions, `None` when claims have no permission bitmap
+    granted: Option<BTreeSet<String>>,
+}
+
+impl RbacVerifier {
+    /// Decodes claims against catalog, which must be the catalog of service the claims were made by.
+    /// Fails with [RbacError::InvalidRoleData], if claims are malformed or their bitmap was made for different catalog.
+    pub fn from_claims(claims: &Value, catalog: &PermissionCatalog) -> Result<Self, RbacError> {
+        let roles = claims["roles"]
+            .as_array()
+            .ok_or_else(|| invalid("no roles"))?
+            .iter()
+            .filter_map(Value::as_str)
+            .map(str::to_string)
+            .collect();
+        let granted = match claims["permissions"].as_str() {
+            Some(bitmap) => {
+                let bits = claimed_bits(claims, bitmap, catalog)?;
+                Some(catalog.permissions().zip(bits).filter(|(_, granted)| *granted).map(|(info, _)| info.full_name.clone()).collect())
+            }
+            None => None,
+        };
+        Ok(RbacVerifier {
+            roles,
+            policy_hash: claims["policy_hash"].as_str().map(str::to_string),
+            granted,
+        })
+    }
+
+    /// Checks permission against claimed bitmap.
+    /// Claims without bitmap can't grant anything, so every check fails with [RbacError::InvalidRoleData].
+    pub fn has_permission<P: PermissionCore + ?Sized>(&self, permission: impl AsRef<P>) -> Result<(), RbacError> {
+        self.has_permission_str(permission.as_ref().full_name())
+    }
+
+    /// Same as [.has_permission()][RbacVerifier#method.has_permission], but with permission string (`"Orders::Order::Read"`)
+    pub fn has_permission_str(&self, permission: &str) -> Result<(), RbacError> {
+        let granted = self.granted.as_ref().ok_or_else(|| invalid("no permissions"))?;
+        match granted.contains(permission) {
+            true => Ok(()),
+            false => Err(RbacError::PermissionDenied(permission.to_string())),
+        }
+    }
+
+    pub fn has_role(&self, role: &str) -> bool {
+        self.roles.iter().any(|r| r == role)
+    }
+
+    pub fn roles(&self) -> &[String] {
+        &self.roles
+    }
+
+    /// Policy hash of service the claims were made by, so stale claims may be told apart
+    pub fn policy_hash(&self) -> Option<&str> {
+        self.policy_hash.as_deref()
+    }
+}
@@ -0,0 +1,106 @@
+use std::{ffi::OsStr, marker::PhantomData, str::FromStr};
+
+use clap::{
+    Arg, Command,
+    builder::{PossibleValue, TypedValueParser},
+    error::ErrorKind,
+};
+
+use crate::{Permission, PermissionCatalog, RbacError, RoleNameRules, suggest::closest};
+
+/// Clap value parser of permission enum generated by [define_permissions!][crate::define_permissions]:
+/// `Arg::new("permission").value_parser(permission_parser::<Orders::Order>())`.
+/// Every permission of the enum is possible value, so it's listed in help and shell completions. Deprecated permissions are accepted, but not listed.
+pub fn permission_parser<P: Permission + Send + Sync + 'static>() -> PermissionParser<P> {
+    PermissionParser(PhantomData)
+}
+
+/// See [permission_parser]
+#[derive(Debug)]
+pub struct PermissionParser<P>(PhantomData<P>);
+
+impl<P> Clone for PermissionParser<P> {
+    fn clone(&self) -> Self {
+        PermissionParser(PhantomData)
+    }
+}
+
+impl<P: Permission + Send + Sync + 'static> TypedValueParser for PermissionParser<P> {
+    type Value = P;
+
+    fn parse_ref(&self, _cmd: &Command, arg: Option<&Arg>, value: &OsStr) -> Result<P, clap::Error> {
+        let value = value.to_string_lossy();
+        P::from_string(&value).ok_or_else(|| {
+            let names: Vec<&str> = P::all_permissions().iter().map(|permission| permission.full_name()).collect();
+            invalid_value(arg, &value, &closest(&value, names.into_iter()))
+        })
+    }
+
+    fn possible_values(&self) -> Option<Box<dyn Iterator<Item = PossibleValue> + '_>> {
+        Some(Box::new(
+            P::all_permissions()
+                .into_iter()
+                .filter(|permission| permission.deprecated().is_none())
+                .map(|permission| PossibleValue::new(permission.full_name()).help(permission.description())),
+        ))
+    }
+}
+
+/// Clap value parser of permission strings from catalog (e.g. of [PolicyBundle][crate::PolicyBundle]), for CLIs not linking permission enums.
+/// Catalog permissions are possible values, so they are listed in help and shell completions.
+#[derive(Debug, Clone)]
+pub struct CatalogPermissionParser {
+    catalog: PermissionCatalog,
+}
+
+impl CatalogPermissionParser {
+    pub fn new(catalog: PermissionCatalog) -> Self {
+        CatalogPermissionParser { catalog }
+    }
+}
+
+impl TypedValueParser for CatalogPermissionParser {
+    type Value = String;
+
+    fn parse_ref(&self, _cmd: &Command, arg: Option<&Arg>, value: &OsStr) -> Result<String, clap::Error> {
+        let value = value.to_string_lossy();
+        match self.catalog.contains(&value) {
+            true => Ok(value.into_owned()),
+            false => {
+                let suggestions = closest(&value, self.catalog.permissions().map(|info| info.full_name.as_str()));
+                Err(invalid_value(arg, &value, &suggestions))
+            }
+        }
+    }
+
+    fn possible_values(&self) -> Option<Box<dyn Iterator<Item = PossibleValue> + '_>> {
+        Some(Box::new(
+            self.catalog
+                .permissions()
+                .map(|info| PossibleValue::new(info.full_name.clone()).help(info.description.clone())),
+        ))
+    }
+}
+
+fn invalid_value(arg: Option<&Arg>, value: &str, suggestions: &[String]) -> clap::Error {
+    let arg = arg.map(|arg| format!(" for '{}'", arg.get_id())).unwrap_or_default();
+    let mut message = format!("invalid permission '{}'{}", value, arg);
+    if !suggestions.is_empty() {
+        message.push_str(&format!(" (did you mean {}?)", suggestions.join(", ")));
+    }
+    clap::Error::raw(ErrorKind::InvalidValue, message + "\n")
+}
+
+/// Role name command line argument (`--role billing:Admin`), checked against default [RoleNameRules].
+/// Parsed by clap through [FromStr]: `Arg::new("role").value_parser(clap::value_parser!(RoleArg))`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RoleArg(pub String);
+
+impl FromStr for RoleArg {
+    type Err = RbacError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        RoleNameRules::default().validate(s)?;
+        Ok(RoleArg(s.to_string()))
+    }
+}
@@ -0,0 +1,438 @@
+use std::{collections::HashMap, fmt};
+
+use crate::RbacError;
+
+/// Attributes conditions are evaluated against, see [RbacSubject::attributes][crate::RbacSubject::attributes]
+pub type Attributes = HashMap<String, String>;
+
+/// Everything conditions of single check are evaluated against
+#[derive(Debug, Clone, Default)]
+pub struct ConditionInput {
+    /// `subject.*` attributes
+    pub subject: Attributes,
+    /// `context.*` attributes of the check (request origin, resource owner, ...), given to
+    /// [.has_permission_in_context()][crate::RbacService#method.has_permission_in_context] (`expressions` feature)
+    pub context: Attributes,
+    /// Checked subject is [machine][crate::RbacSubject::is_machine] one, so [machine-only patterns][crate::MACHINE_ONLY] apply
+    pub machine: bool,
+    /// Checked subject is [anonymous][crate::RbacSubject::is_anonymous] one, so it isn't checked against fallback roles, when it has no roles
+    pub anonymous: bool,
+    /// Correlation (request) ID of the check, attached to its [decision][crate::RbacDecision::correlation_id]
+    pub correlation_id: Option<String>,
+}
+
+impl ConditionInput {
+    pub fn subject(subject: Attributes) -> Self {
+        ConditionInput {
+            subject,
+            ..Default::default()
+        }
+    }
+
+    /// Input of subject check: its attributes, whether it's machine or anonymous subject and its correlation ID
+    pub fn of(subject: &impl crate::RbacSubject) -> Self {
+        ConditionInput {
+            subject: subject.attributes(),
+            machine: subject.is_machine(),
+            anonymous: subject.is_anonymous(),
+            correlation_id: subject.correlation_id().map(str::to_string),
+            ..Default::default()
+        }
+    }
+}
+
+/// Attribute condition refers to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Attribute {
+    /// `subject.name`
+    Subject(String),
+    /// `context.name`
+    #[cfg(feature = "expressions")]
+    Context(String),
+}
+
+impl Attribute {
+    fn value<'a>(&self, input: &'a ConditionInput) -> Option<&'a String> {
+        match self {
+            Attribute::Subject(name) => input.subject.get(name),
+            #[cfg(feature = "expressions")]
+            Attribute::Context(name) => input.context.get(name),
+        }
+    }
+}
+
+impl fmt::Display for Attribute {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Attribute::Subject(name) => write!(f, "subject.{}", name),
+            #[cfg(feature = "expressions")]
+            Attribute::Context(name) => write!(f, "context.{}", name),
+        }
+    }
+}
+
+/// Condition of permission pattern, written after `if`: `"Orders::Order::Approve if subject.department == 'finance'"`.
+///
+/// Parsed when role is compiled; pattern with malformed condition is dropped, as any other malformed pattern
+/// (use [.try_add_role()][crate::RbacServiceBuilder#method.try_add_role] to reject such roles).
+/// Comparisons of attributes that aren't given are false, so conditional grant (or denial) doesn't apply.
+///
+/// By default conditions are comparisons of subject attributes joined with `&&`. With `expressions` feature they may also use
+/// `||`, `!`, parentheses, `context.*` attributes, `in ['a', 'b']` and integer comparisons (`subject.level >= 3`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Condition {
+    /// `attribute == 'value'`
+    Equals { attribute: Attribute, value: String },
+    /// `attribute != 'value'`
+    NotEquals { attribute: Attribute, value: String },
+    /// Conditions joined with `&&`
+    All(Vec<Condition>),
+    /// Conditions joined with `||`
+    #[cfg(feature = "expressions")]
+    Any(Vec<Condition>),
+    /// `!condition`
+    #[cfg(feature = "expressions")]
+    Not(Box<Condition>),
+    /// `attribute in ['a', 'b']`
+    #[cfg(feature = "expressions")]
+    In { attribute: Attribute, values: Vec<String> },
+    /// `attribute < 10` and others, false if attribute isn't integer
+    #[cfg(feature = "expressions")]
+    Compare { attribute: Attribute, op: CompareOp, value: i64 },
+}
+
+#[cfg(feature = "expressions")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompareOp {
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+}
+
+#[cfg(feature = "expressions")]
+impl fmt::Display for CompareOp {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            CompareOp::Less => "<",
+            CompareOp::LessOrEqual => "<=",
+            CompareOp::Greater => ">",
+            CompareOp::GreaterOrEqual => ">=",
+        })
+    }
+}
+
+/// Deeper nesting is rejected, so evaluation can't overflow stack
+const MAX_DEPTH: usize = 32;
+
+impl Condition {
+    pub fn parse(condition: &str) -> Result<Condition, RbacError> {
+        let invalid = || RbacError::InvalidCondition(condition.to_string());
+        let tokens = tokenize(condition).ok_or_else(invalid)?;
+        let mut parser = Parser { tokens: &tokens, depth: 0 };
+        match parser.any() {
+            Some(condition) if parser.tokens.is_empty() => Ok(condition),
+            _ => Err(invalid()),
+        }
+    }
+
+    /// Whether input satisfies condition. Comparison of missing attribute is neither true nor false, so it doesn't satisfy condition
+    /// even under `!`: `!subject.team == 'a'` agrees with `subject.team != 'a'` for subjects without team.
+    pub fn evaluate(&self, input: &ConditionInput) -> bool {
+        self.truth(input).unwrap_or(false)
+    }
+
+    /// Three-valued evaluation, `None` when outcome depends on missing attribute
+    fn truth(&self, input: &ConditionInput) -> Option<bool> {
+        match self {
+            Condition::Equals { attribute, value } => attribute.value(input).map(|actual| actual == value),
+            Condition::NotEquals { attribute, value } => attribute.value(input).map(|actual| actual != value),
+            // False if any condition is false, otherwise unknown if any is unknown
+            Condition::All(conditions) => {
+                let mut all = Some(true);
+                for condition in conditions {
+                    match condition.truth(input) {
+                        Some(false) => return Some(false),
+                        None => all = None,
+                        Some(true) => {}
+                    }
+                }
+                all
+            }
+            // True if any condition is true, otherwise unknown if any is unknown
+            #[cfg(feature = "expressions")]
+            Condition::Any(conditions) => {
+                let mut any = Some(false);
+                for condition in conditions {
+                    match condition.truth(input) {
+                        Some(true) => return Some(true),
+                        None => any = None,
+                        Some(false) => {}
+                    }
+                }
+                any
+            }
+            #[cfg(feature = "expressions")]
+            Condition::Not(condition) => condition.truth(input).map(|truth| !truth),
+            #[cfg(feature = "expressions")]
+            Condition::In { attribute, values } => attribute.value(input).map(|actual| values.contains(actual)),
+            #[cfg(feature = "expressions")]
+            Condition::Compare { attribute, op, value } => attribute.value(input).map(|actual| {
+                actual.parse::<i64>().is_ok_and(|actual| match op {
+                    CompareOp::Less => actual < *value,
+                    CompareOp::LessOrEqual => actual <= *value,
+                    CompareOp::Greater => actual > *value,
+                    CompareOp::GreaterOrEqual => actual >= *value,
+                })
+            }),
+        }
+    }
+}
+
+/// Canonical form, used when role is serialized
+impl fmt::Display for Condition {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let join = |f: &mut fmt::Formatter, conditions: &[Condition], separator: &str| -> fmt::Result {
+            for (i, condition) in conditions.iter().enumerate() {
+                if i > 0 {
+                    f.write_str(separator)?;
+                }
+                match condition {
+                    #[cfg(feature = "expressions")]
+                    Condition::All(_) | Condition::Any(_) => write!(f, "({})", condition)?,
+                    _ => write!(f, "{}", condition)?,
+                }
+            }
+            Ok(())
+        };
+
+        match self {
+            Condition::Equals { attribute, value } => write!(f, "{} == {}", attribute, quoted(value)),
+            Condition::NotEquals { attribute, value } => write!(f, "{} != {}", attribute, quoted(value)),
+            Condition::All(conditions) => join(f, conditions, " && "),
+            #[cfg(feature = "expressions")]
+            Condition::Any(conditions) => join(f, conditions, " || "),
+            #[cfg(feature = "expressions")]
+            Condition::Not(condition) => match **condition {
+                Condition::All(_) | Condition::Any(_) => write!(f, "!({})", condition),
+                _ => write!(f, "!{}", condition),
+            },
+            #[cfg(feature = "expressions")]
+            Condition::In { attribute, values } => {
+                let values: Vec<String> = values.iter().map(|value| quoted(value)).collect();
+                write!(f, "{} in [{}]", attribute, values.join(", "))
+            }
+            #[cfg(feature = "expressions")]
+            Condition::Compare { attribute, op, value } => write!(f, "{} {} {}", attribute, op, value),
+        }
+    }
+}
+
+/// Single-quoted value, unless it contains single quote itself
+fn quoted(value: &str) -> String {
+    match value.contains('\'') {
+        true => format!("\"{}\"", value),
+        false => format!("'{}'", value),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Attribute(Attribute),
+    /// Quoted string
+    Value(String),
+    Equals,
+    NotEquals,
+    And,
+    #[cfg(feature = "expressions")]
+    Or,
+    #[cfg(feature = "expressions")]
+    Not,
+    #[cfg(feature = "expressions")]
+    Open,
+    #[cfg(feature = "expressions")]
+    Close,
+    #[cfg(feature = "expressions")]
+    OpenList,
+    #[cfg(feature = "expressions")]
+    CloseList,
+    #[cfg(feature = "expressions")]
+    Comma,
+    #[cfg(feature = "expressions")]
+    In,
+    #[cfg(feature = "expressions")]
+    Compare(CompareOp),
+    #[cfg(feature = "expressions")]
+    Number(i64),
+}
+
+/// Operators, longer ones first
+const OPERATORS: &[(&str, Token)] = &[
+    ("==", Token::Equals),
+    ("!=", Token::NotEquals),
+    ("&&", Token::And),
+    #[cfg(feature = "expressions")]
+    ("||", Token::Or),
+    #[cfg(feature = "expressions")]
+    ("<=", Token::Compare(CompareOp::LessOrEqual)),
+    #[cfg(feature = "expressions")]
+    (">=", Token::Compare(CompareOp::GreaterOrEqual)),
+    #[cfg(feature = "expressions")]
+    ("<", Token::Compare(CompareOp::Less)),
+    #[cfg(feature = "expressions")]
+    (">", Token::Compare(CompareOp::Greater)),
+    #[cfg(feature = "expressions")]
+    ("!", Token::Not),
+    #[cfg(feature = "expressions")]
+    ("(", Token::Open),
+    #[cfg(feature = "expressions")]
+    (")", Token::Close),
+    #[cfg(feature = "expressions")]
+    ("[", Token::OpenList),
+    #[cfg(feature = "expressions")]
+    ("]", Token::CloseList),
+    #[cfg(feature = "expressions")]
+    (",", Token::Comma),
+];
+
+fn tokenize(input: &str) -> Option<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut rest = input.trim_start();
+    while !rest.is_empty() {
+        let (token, tail) = if let Some((op, token)) = OPERATORS.iter().find(|(op, _)| rest.starts_with(op)) {
+            (token.clone(), &rest[op.len()..])
+        } else if let Some(quote) = rest.chars().next().filter(|c| *c == '\'' || *c == '"') {
+            let (value, tail) = rest[1..].split_once(quote)?;
+            (Token::Value(value.to_string()), tail)
+        } else {
+            let end = rest
+                .find(|c: char| !(c.is_alphanumeric() || c == '_' || c == '.' || c == '-'))
+                .unwrap_or(rest.len());
+            (word(&rest[..end])?, &rest[end..])
+        };
+        tokens.push(token);
+        rest = tail.trim_start();
+    }
+    Some(tokens)
+}
+
+/// Attribute, keyword or number
+fn word(word: &str) -> Option<Token> {
+    let attribute = |name: &str| (!name.is_empty() && !name.contains('.')).then(|| name.to_string());
+    if let Some(name) = word.strip_prefix("subject.") {
+        return Some(Token::Attribute(Attribute::Subject(attribute(name)?)));
+    }
+    #[cfg(feature = "expressions")]
+    {
+        if let Some(name) = word.strip_prefix("context.") {
+            return Some(Token::Attribute(Attribute::Context(attribute(name)?)));
+        }
+        if word == "in" {
+            return Some(Token::In);
+        }
+        if let Ok(number) = word.parse() {
+            return Some(Token::Number(number));
+        }
+    }
+    None
+}
+
+/// Recursive descent parser over tokens: `any := all ('||' all)*`, `all := unary ('&&' unary)*`,
+/// `unary := '!' unary | '(' any ')' | comparison`
+struct Parser<'t> {
+    tokens: &'t [Token],
+    depth: usize,
+}
+
+impl<'t> Parser<'t> {
+    fn next(&mut self) -> Option<&'t Token> {
+        let (token, rest) = self.tokens.split_first()?;
+        self.tokens = rest;
+        Some(token)
+    }
+
+    fn eat(&mut self, token: &Token) -> bool {
+        let matched = self.tokens.first() == Some(token);
+        if matched {
+            self.tokens = &self.tokens[1..];
+        }
+        matched
+    }
+
+    #[cfg(feature = "expressions")]
+    fn any(&mut self) -> Option<Condition> {
+        let mut conditions = vec![self.all()?];
+        while self.eat(&Token::Or) {
+            conditions.push(self.all()?);
+        }
+        Some(match conditions.len() {
+            1 => conditions.remove(0),
+            _ => Condition::Any(conditions),
+        })
+    }
+
+    #[cfg(not(feature = "expressions"))]
+    fn any(&mut self) -> Option<Condition> {
+        self.all()
+    }
+
+    fn all(&mut self) -> Option<Condition> {
+        let mut conditions = vec![self.unary()?];
+        while self.eat(&Token::And) {
+            conditions.push(self.unary()?);
+        }
+        Some(match conditions.len() {
+            1 => conditions.remove(0),
+            _ => Condition::All(conditions),
+        })
+    }
+
+    fn unary(&mut self) -> Option<Condition> {
+        self.depth += 1;
+        if self.depth > MAX_DEPTH {
+            return None;
+        }
+        #[cfg(feature = "expressions")]
+        let condition = if self.eat(&Token::Not) {
+            self.unary().map(|condition| Condition::Not(Box::new(condition)))
+        } else if self.eat(&Token::Open) {
+            self.any().filter(|_| self.eat(&Token::Close))
+        } else {
+            self.comparison()
+        };
+        #[cfg(not(feature = "expressions"))]
+        let condition = self.comparison();
+        self.depth -= 1;
+        condition
+    }
+
+    fn comparison(&mut self) -> Option<Condition> {
+        let Some(Token::Attribute(attribute)) = self.next() else {
+            return None;
+        };
+        let attribute = attribute.clone();
+        match (self.next()?, self.next()?) {
+            (Token::Equals, Token::Value(value)) => Some(Condition::Equals { attribute, value: value.clone() }),
+            (Token::NotEquals, Token::Value(value)) => Some(Condition::NotEquals { attribute, value: value.clone() }),
+            #[cfg(feature = "expressions")]
+            (Token::Compare(op), Token::Number(value)) => Some(Condition::Compare { attribute, op: *op, value: *value }),
+            #[cfg(feature = "expressions")]
+            (Token::In, Token::OpenList) => {
+                let mut values = Vec::new();
+                loop {
+                    let Some(Token::Value(value)) = self.next() else {
+                        return None;
+                    };
+                    values.push(value.clone());
+                    match self.next()? {
+                        Token::Comma => continue,
+                        Token::CloseList => break,
+                        _ => return None,
+                    }
+                }
+                Some(Condition::In { attribute, values })
+            }
+            _ => None,
+        }
+    }
+}
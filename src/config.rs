@@ -0,0 +1,68 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{Quota, RbacError, RbacService, RbacServiceBuilder, Role};
+
+/// RBAC settings as part of application configuration (e.g. section of layered `config`/`figment` configuration),
+/// turned into builder by [RbacServiceBuilder::from_config]. Every field is optional.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RbacConfig {
+    pub roles: Vec<Role>,
+    /// Fallback roles, `["Default"]` if not set (see [.set_fallback_roles()][RbacServiceBuilder#method.set_fallback_roles])
+    pub fallback: Option<Vec<String>>,
+    pub options: RbacOptions,
+}
+
+/// Builder options settable from configuration, see corresponding [RbacServiceBuilder] methods
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RbacOptions {
+    pub usage_stats: bool,
+    pub report_unknown_roles: bool,
+    pub deprecation_warnings: bool,
+    /// Maximum number of cached role combinations, `0` disables cache
+    pub combination_cache: usize,
+    pub audience: Option<String>,
+    pub anonymous_roles: Option<Vec<String>>,
+    pub protected_roles: Vec<String>,
+    /// Quotas by permission in [Quota::parse] form: `{"Reports::Export::Run": "10/day"}`
+    pub quotas: BTreeMap<String, String>,
+    /// Permissions by their aliases: `{"Orders::Order::View": "Orders::Order::Read"}`
+    pub permission_aliases: BTreeMap<String, String>,
+}
+
+impl RbacServiceBuilder {
+    /// Creates builder from configuration. Roles are added with [.try_add_role()][RbacServiceBuilder#method.try_add_role],
+    /// so duplicates and malformed conditions are rejected, as are unparsable quotas.
+    pub fn from_config(config: &RbacConfig) -> Result<RbacServiceBuilder, RbacError> {
+        let options = &config.options;
+        let mut builder = RbacService::builder();
+        builder
+            .set_usage_stats(options.usage_stats)
+            .set_report_unknown_roles(options.report_unknown_roles)
+            .set_deprecation_warnings(options.deprecation_warnings)
+            .set_combination_cache(options.combination_cache)
+            .try_load_roles(config.roles.clone())?;
+        if let Some(fallback) = &config.fallback {
+            builder.set_fallback_roles(fallback.clone());
+        }
+        if let Some(audience) = &options.audience {
+            builder.set_audience(audience);
+        }
+        if let Some(anonymous_roles) = &options.anonymous_roles {
+            builder.set_anonymous_roles(anonymous_roles.clone());
+        }
+        for role_name in &options.protected_roles {
+            builder.protect_role(role_name);
+        }
+        for (permission, quota) in &options.quotas {
+            builder.add_quota(permission, Quota::parse(quota)?);
+        }
+        for (alias, permission) in &options.permission_aliases {
+            builder.add_permission_alias(alias, permission);
+        }
+        Ok(builder)
+    }
+}
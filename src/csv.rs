@@ -0,0 +1,162 @@
+use crate::{RbacError, RbacService, RbacServiceBuilder, Role, RoleStorage, suggest::closest};
+
+/// Header of role CSV written by [.export_roles_csv()][RbacService#method.export_roles_csv]
+const ROLES_CSV_HEADER: &str = "role,permission";
+
+/// Quotes field, if it contains separators or quotes
+pub(crate) fn csv_field(field: &str) -> String {
+    match field.contains([',', '"', '\n', '\r']) {
+        true => format!("\"{}\"", field.replace('"', "\"\"")),
+        false => field.to_string(),
+    }
+}
+
+/// Splits CSV into rows of fields, unquoting quoted fields. Blank lines are skipped, rows are numbered from 1 for errors.
+pub(crate) fn parse_csv(csv: &str) -> Result<Vec<(usize, Vec<String>)>, RbacError> {
+    let mut rows = Vec::new();
+    let mut row = Vec::new();
+    let mut field = String::new();
+    let (mut line, mut row_line) = (1, 1);
+    let mut quoted = false;
+    let mut chars = csv.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (quoted, c) {
+            (true, '"') if chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            (true, '"') => quoted = false,
+            (false, '"') if field.is_empty() => quoted = true,
+            (false, ',') => row.push(std::mem::take(&mut field)),
+            (false, '\r') if chars.peek() == Some(&'\n') => {}
+            (false, '\n') => {
+                row.push(std::mem::take(&mut field));
+                if !(row.len() == 1 && row[0].is_empty()) {
+                    rows.push((row_line, std::mem::take(&mut row)));
+                }
+                row.clear();
+                line += 1;
+                row_line = line;
+            }
+            (_, c) => {
+                if c == '\n' {
+                    line += 1;
+                }
+                field.push(c);
+            }
+        }
+    }
+    if quoted {
+        return Err(RbacError::InvalidRoleData(format!("csv line {}: unterminated quoted field", row_line)));
+    }
+    row.push(field);
+    if !(row.len() == 1 && row[0].is_empty()) {
+        rows.push((row_line, row));
+    }
+    Ok(rows)
+}
+
+impl<R: RoleStorage> RbacService<R> {
+    /// Current roles as CSV with `role,permission` row per pattern (roles sorted by name, patterns as written),
+    /// loadable by [.load_roles_csv()][RbacServiceBuilder#method.load_roles_csv]. Role without patterns gets row with empty permission.
+    ///
+    /// Only names and patterns fit the format, so deny roles, roles with priority and disabled or deleted roles
+    /// are refused with [RbacError::InvalidRoleData]: loaded back they would grant what they deny or don't grant now.
+    pub fn export_roles_csv(&self) -> Result<String, RbacError> {
+        let mut csv = format!("{}\n", ROLES_CSV_HEADER);
+        for role in self.snapshot().roles {
+            if role.deny || role.priority != 0 || !role.is_active() {
+                return Err(RbacError::InvalidRoleData(format!("role {}: deny, prioritized and inactive roles don't fit csv", role.name)));
+            }
+            let patterns = match role.permissions.is_empty() {
+                true => vec![String::new()],
+                false => role.permissions,
+            };
+            for pattern in patterns {
+                csv.push_str(&format!("{},{}\n", csv_field(&role.name), csv_field(&pattern)));
+            }
+        }
+        Ok(csv)
+    }
+}
+
+impl RbacServiceBuilder {
+    /// Loads roles from CSV with `role,permission` row per pattern (e.g. access matrix maintained in spreadsheet),
+    /// optionally starting with `role,permission` header. Rows of one role are joined into single role, empty permission cells are skipped.
+    ///
+    /// Roles are added with [.try_load_roles()][RbacServiceBuilder#method.try_load_roles] in order of first appearance,
+    /// so malformed CSV or rejected role adds no roles.
+    pub fn load_roles_csv(&mut self, csv: &str) -> Result<&mut Self, RbacError> {
+        let mut roles: Vec<(String, Vec<String>)> = Vec::new();
+        for (i, (line, row)) in parse_csv(csv)?.into_iter().enumerate() {
+            let [role_name, pattern] = <[String; 2]>::try_from(row).map_err(|row| {
+                RbacError::InvalidRoleData(format!("csv line {}: expected 2 fields, got {}", line, row.len()))
+            })?;
+            let (role_name, pattern) = (role_name.trim(), pattern.trim());
+            if i == 0 && format!("{},{}", role_name, pattern).eq_ignore_ascii_case(ROLES_CSV_HEADER) {
+                continue;
+            }
+            if role_name.is_empty() {
+                return Err(RbacError::InvalidRoleData(format!("csv line {}: empty role name", line)));
+            }
+            let patterns = match roles.iter_mut().find(|(name, _)| name == role_name) {
+                Some((_, patterns)) => patterns,
+                None => {
+                    roles.push((role_name.to_string(), Vec::new()));
+                    &mut roles.last_mut().unwrap().1
+                }
+            };
+            if !pattern.is_empty() {
+                patterns.push(pattern.to_string());
+            }
+        }
+
+        self.try_load_roles(roles.into_iter().map(|(role_name, patterns)| Role::new(&role_name, patterns)).collect())
+    }
+    /// Loads roles from wide access matrix, as auditors' spreadsheets usually are: header row of permission strings
+    /// (its first cell, e.g. `role`, is ignored), then row per role with role name and `X` in cells of granted permissions.
+    ///
+    /// When permissions are registered, every column has to be registered permission, otherwise [RbacError::UnknownPermission]
+    /// with closest registered ones is returned and no roles are added. Roles are added with [.try_add_role()][RbacServiceBuilder#method.try_add_role].
+    pub fn load_roles_matrix(&mut self, csv: &str) -> Result<&mut Self, RbacError> {
+        let mut rows = parse_csv(csv)?.into_iter();
+        let Some((_, header)) = rows.next() else {
+            return Ok(self);
+        };
+        let permissions: Vec<String> = header.iter().skip(1).map(|permission| permission.trim().to_string()).collect();
+        for permission in &permissions {
+            let parts: Vec<&str> = permission.split("::").collect();
+            if parts.len() != 3 || parts.iter().any(|part| part.is_empty() || part.contains(['*', '{', '}'])) {
+                return Err(RbacError::InvalidPermission(permission.clone()));
+            }
+            if !self.all_permissions.is_empty() && !self.all_permissions.contains_key(permission) {
+                return Err(RbacError::UnknownPermission {
+                    suggestions: closest(permission, self.all_permissions.keys().map(String::as_str)),
+                    permission: permission.clone(),
+                });
+            }
+        }
+
+        let mut roles = Vec::new();
+        for (line, row) in rows {
+            if row.len() > header.len() {
+                return Err(RbacError::InvalidRoleData(format!("csv line {}: more cells than permissions", line)));
+            }
+            let role_name = row[0].trim();
+            if role_name.is_empty() {
+                return Err(RbacError::InvalidRoleData(format!("csv line {}: empty role name", line)));
+            }
+            let mut granted = Vec::new();
+            for (permission, cell) in permissions.iter().zip(&row[1..]) {
+                match cell.trim() {
+                    "X" | "x" => granted.push(permission.clone()),
+                    "" => {}
+                    cell => return Err(RbacError::InvalidRoleData(format!("csv line {}: unexpected cell {:?} of {}", line, cell, permission))),
+                }
+            }
+            roles.push(Role::new(role_name, granted));
+        }
+
+        self.try_load_roles(roles)
+    }
+}
@@ -0,0 +1,61 @@
+use std::{
+    fmt,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// Outcome of permission check with its reasons, returned by [.explain()][crate::RbacService#method.explain] and passed to [AuditSink][crate::AuditSink]s
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RbacDecision {
+    pub allowed: bool,
+    pub permission: String,
+    /// Name of checked subject, `None` for checks of bare roles
+    pub subject: Option<String>,
+    /// Roles permission was checked against (fallback roles, if subject had none)
+    pub roles: Vec<String>,
+    /// Role which decided the check: granting one, or deny role taking permission away. `None` if no role matched.
+    pub matched_role: Option<String>,
+    /// Canonical pattern of [matched_role][RbacDecision::matched_role] covering permission (e.g. `Orders::*`)
+    pub matched_pattern: Option<String>,
+    /// Subject had no roles, so fallback roles were checked
+    pub fallback_used: bool,
+    /// Service roles [generation][crate::RbacService#method.generation] decision was made on
+    pub generation: u64,
+    pub time: SystemTime,
+    /// Disabled feature flag or used up quota denying check regardless of roles, as error message
+    pub blocked_by: Option<String>,
+    /// Correlation (request) ID of checked subject, see [RbacSubject::correlation_id][crate::RbacSubject::correlation_id]
+    pub correlation_id: Option<String>,
+}
+
+impl RbacDecision {
+    pub(crate) fn unix_millis(&self) -> u128 {
+        self.time.duration_since(UNIX_EPOCH).unwrap_or_default().as_millis()
+    }
+}
+
+/// `allow Orders::Order::Read subject=alice roles=OrderManager,Auditor matched=OrderManager(Orders::*)`,
+/// checks of fallback roles are marked: `allow Orders::Order::Read subject=bob roles=Guest fallback matched=Guest(Orders::Order::Read)`,
+/// gate or quota denial is appended as `blocked=(Feature disabled: Orders::Invoice::Send (flag invoices))`, correlation ID as `correlation=req-42`
+impl fmt::Display for RbacDecision {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let decision = if self.allowed { "allow" } else { "deny" };
+        write!(f, "{} {}", decision, self.permission)?;
+        if let Some(subject) = &self.subject {
+            write!(f, " subject={}", subject)?;
+        }
+        write!(f, " roles={}", self.roles.join(","))?;
+        if self.fallback_used {
+            f.write_str(" fallback")?;
+        }
+        if let Some(role) = &self.matched_role {
+            write!(f, " matched={}({})", role, self.matched_pattern.as_deref().unwrap_or_default())?;
+        }
+        if let Some(blocked_by) = &self.blocked_by {
+            write!(f, " blocked=({})", blocked_by)?;
+        }
+        if let Some(correlation_id) = &self.correlation_id {
+            write!(f, " correlation={}", correlation_id)?;
+        }
+        Ok(())
+    }
+}
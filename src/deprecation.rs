@@ -0,0 +1,25 @@
+use std::{fmt, panic::Location};
+
+/// Check of deprecated permission, reported to [AuditSink::on_deprecated][crate::AuditSink::on_deprecated]
+/// when enabled by [.set_deprecation_warnings()][crate::RbacServiceBuilder#method.set_deprecation_warnings]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeprecationWarning {
+    pub permission: String,
+    /// Deprecation note of permission, e.g. what to use instead
+    pub note: String,
+    /// Name of checked subject, `None` for checks of bare roles
+    pub subject: Option<String>,
+    /// Code which made the check
+    pub caller: &'static Location<'static>,
+}
+
+/// `deprecated permission Orders::Order::Cancel checked by alice at src/orders.rs:42:9: use Orders::Order::Update`
+impl fmt::Display for DeprecationWarning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "deprecated permission {} checked", self.permission)?;
+        if let Some(subject) = &self.subject {
+            write!(f, " by {}", subject)?;
+        }
+        write!(f, " at {}: {}", self.caller, self.note)
+    }
+}
@@ -0,0 +1,119 @@
+use std::time::{Duration, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{PermissionCatalog, PermissionInfo, RbacDecision, Role};
+
+/// Registered permissions, sorted by full name
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CatalogDto {
+    pub permissions: Vec<PermissionInfo>,
+}
+
+impl From<&PermissionCatalog> for CatalogDto {
+    fn from(catalog: &PermissionCatalog) -> Self {
+        CatalogDto {
+            permissions: catalog.permissions().cloned().collect(),
+        }
+    }
+}
+
+impl From<CatalogDto> for PermissionCatalog {
+    fn from(dto: CatalogDto) -> Self {
+        dto.permissions.into_iter().collect()
+    }
+}
+
+/// Role with its permissions in canonical form (see [Role::normalized])
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RoleDto {
+    pub name: String,
+    pub permissions: Vec<String>,
+    pub revision: u64,
+    pub deny: bool,
+    pub priority: i32,
+    pub enabled: bool,
+    /// Tombstone time as UNIX milliseconds
+    pub deleted_at_ms: Option<u64>,
+}
+
+impl From<&Role> for RoleDto {
+    fn from(role: &Role) -> Self {
+        RoleDto {
+            name: role.name.clone(),
+            permissions: role.compiled_permissions.to_patterns(),
+            revision: role.revision,
+            deny: role.deny,
+            priority: role.priority,
+            enabled: role.enabled,
+            deleted_at_ms: role.deleted_at.map(|time| time.duration_since(UNIX_EPOCH).unwrap_or_default().as_millis() as u64),
+        }
+    }
+}
+
+impl From<RoleDto> for Role {
+    fn from(dto: RoleDto) -> Self {
+        Role {
+            revision: dto.revision,
+            deny: dto.deny,
+            priority: dto.priority,
+            enabled: dto.enabled,
+            deleted_at: dto.deleted_at_ms.map(|ms| UNIX_EPOCH + Duration::from_millis(ms)),
+            ..Role::new(&dto.name, dto.permissions)
+        }
+    }
+}
+
+/// [RbacDecision] with time as UNIX milliseconds
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DecisionDto {
+    pub allowed: bool,
+    pub permission: String,
+    pub subject: Option<String>,
+    pub roles: Vec<String>,
+    pub matched_role: Option<String>,
+    pub matched_pattern: Option<String>,
+    pub fallback_used: bool,
+    pub generation: u64,
+    pub timestamp_ms: u64,
+    #[serde(default)]
+    pub blocked_by: Option<String>,
+    #[serde(default)]
+    pub correlation_id: Option<String>,
+}
+
+impl From<&RbacDecision> for DecisionDto {
+    fn from(decision: &RbacDecision) -> Self {
+        DecisionDto {
+            allowed: decision.allowed,
+            permission: decision.permission.clone(),
+            subject: decision.subject.clone(),
+            roles: decision.roles.clone(),
+            matched_role: decision.matched_role.clone(),
+            matched_pattern: decision.matched_pattern.clone(),
+            fallback_used: decision.fallback_used,
+            generation: decision.generation,
+            timestamp_ms: decision.unix_millis() as u64,
+            blocked_by: decision.blocked_by.clone(),
+            correlation_id: decision.correlation_id.clone(),
+        }
+    }
+}
+
+impl From<DecisionDto> for RbacDecision {
+    fn from(dto: DecisionDto) -> Self {
+        RbacDecision {
+            allowed: dto.allowed,
+            permission: dto.permission,
+            subject: dto.subject,
+            roles: dto.roles,
+            matched_role: dto.matched_role,
+            matched_pattern: dto.matched_pattern,
+            fallback_used: dto.fallback_used,
+            generation: dto.generation,
+            time: UNIX_EPOCH + Duration::from_millis(dto.timestamp_ms),
+            blocked_by: dto.blocked_by,
+            correlation_id: dto.correlation_id,
+        }
+    }
+}
@@ -0,0 +1,70 @@
+use aes_gcm::{
+    Aes256Gcm, Key, KeyInit, Nonce,
+    aead::{Aead, AeadCore, OsRng},
+};
+
+use crate::{RbacError, RbacService, RbacServiceBuilder, RoleStorage};
+
+/// Header of encrypted role files, followed by 12 bytes of nonce and AES-256-GCM ciphertext
+const MAGIC: &[u8] = b"RBACENC1";
+const NONCE_LEN: usize = 12;
+
+/// 256-bit key encrypting role files
+pub type RoleFileKey = [u8; 32];
+
+/// Encrypts data with AES-256-GCM under random nonce
+fn encrypt(plaintext: &[u8], key: &RoleFileKey) -> Vec<u8> {
+    let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(key));
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+    // Encryption into Vec fails only when plaintext is beyond AES-GCM limits (64 GiB)
+    let ciphertext = cipher.encrypt(&nonce, plaintext).expect("role file is too large to encrypt");
+    [MAGIC, nonce.as_slice(), &ciphertext].concat()
+}
+
+/// Decrypts data written by [encrypt]. Data without encryption header is rejected, unless plaintext is allowed,
+/// in which case it's passed through as is.
+fn decrypt<'a>(data: &'a [u8], key: &RoleFileKey, allow_plaintext: bool) -> Result<std::borrow::Cow<'a, [u8]>, RbacError> {
+    let Some(encrypted) = data.strip_prefix(MAGIC) else {
+        return match allow_plaintext {
+            true => Ok(data.into()),
+            false => Err(RbacError::InvalidRoleData("role file isn't encrypted".to_string())),
+        };
+    };
+    if encrypted.len() < NONCE_LEN {
+        return Err(RbacError::InvalidRoleData("encrypted role file is truncated".to_string()));
+    }
+    let (nonce, ciphertext) = encrypted.split_at(NONCE_LEN);
+    let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(key));
+    cipher
+        .decrypt(Nonce::from_slice(nonce), ciphertext)
+        .map(Into::into)
+        .map_err(|_| RbacError::InvalidRoleData("role file can't be decrypted with given key".to_string()))
+}
+
+impl<R: RoleStorage> RbacService<R> {
+    /// Current roles as encrypted NDJSON role file, loadable by [.load_encrypted_roles()][RbacServiceBuilder#method.load_encrypted_roles]
+    pub fn export_encrypted_roles(&self, key: &RoleFileKey) -> Result<Vec<u8>, RbacError> {
+        let mut plaintext = Vec::new();
+        for role in self.snapshot().roles {
+            serde_json::to_writer(&mut plaintext, &role).map_err(|e| RbacError::InvalidRoleData(e.to_string()))?;
+            plaintext.push(b'\n');
+        }
+        Ok(encrypt(&plaintext, key))
+    }
+}
+
+impl RbacServiceBuilder {
+    /// Loads role file written by [.export_encrypted_roles()][RbacService#method.export_encrypted_roles] as [.load_roles_from_reader()][RbacServiceBuilder#method.load_roles_from_reader] does.
+    /// Files without encryption header are rejected, as anyone able to write them could replace roles otherwise.
+    pub fn load_encrypted_roles(&mut self, data: &[u8], key: &RoleFileKey) -> Result<&mut Self, RbacError> {
+        let plaintext = decrypt(data, key, false)?;
+        self.load_roles_from_reader(&*plaintext)
+    }
+
+    /// Like [.load_encrypted_roles()][RbacServiceBuilder#method.load_encrypted_roles], but loads files without encryption header as plain NDJSON,
+    /// so role files may be encrypted gradually. Plain files aren't authenticated, so it's meant for the time of migration only.
+    pub fn load_encrypted_or_plain_roles(&mut self, data: &[u8], key: &RoleFileKey) -> Result<&mut Self, RbacError> {
+        let plaintext = decrypt(data, key, true)?;
+        self.load_roles_from_reader(&*plaintext)
+    }
+}
@@ -0,0 +1,74 @@
+use crate::{Role, service::RoleMap};
+
+/// Kind of role mutation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RoleChangeKind {
+    Added,
+    Updated,
+    Removed,
+}
+
+/// Single role mutation applied to [RbacService][crate::RbacService] by [updater.update()][crate::RbacServiceUpdater#method.update]
+#[derive(Debug, Clone)]
+pub struct RoleChangeEvent {
+    pub kind: RoleChangeKind,
+    pub role: String,
+    /// Role definition before change, `None` for added roles
+    pub before: Option<Role>,
+    /// Role definition after change, `None` for removed roles
+    pub after: Option<Role>,
+    /// Who made the change, set by [.set_actor()][crate::RbacServiceUpdater#method.set_actor]
+    pub actor: Option<String>,
+}
+
+/// Receiver of role change events, registered by [.add_role_change_sink()][crate::RbacServiceBuilder#method.add_role_change_sink].
+///
+/// Sinks are called synchronously right after roles swapped, so heavy processing should be offloaded.
+/// Implemented for closures, so `|event: &RoleChangeEvent| {...}` may be used as sink.
+pub trait RoleChangeSink: Send + Sync {
+    fn on_role_change(&self, event: &RoleChangeEvent);
+}
+
+impl<F: Fn(&RoleChangeEvent) + Send + Sync> RoleChangeSink for F {
+    fn on_role_change(&self, event: &RoleChangeEvent) {
+        self(event)
+    }
+}
+
+/// Whether role definition differs in anything that affects checks or is reported to sinks
+pub(crate) fn changed(old: &Role, new: &Role) -> bool {
+    new.revision != old.revision || new.permissions != old.permissions || new.deny != old.deny
+        || new.priority != old.priority || new.enabled != old.enabled || new.deleted_at != old.deleted_at
+}
+
+impl RoleChangeEvent {
+    /// Changes between two role maps, ordered by role name
+    pub(crate) fn diff(before: &RoleMap, after: &RoleMap, actor: Option<&str>) -> Vec<RoleChangeEvent> {
+        let event = |kind, role: &str, before: Option<&Role>, after: Option<&Role>| RoleChangeEvent {
+            kind,
+            role: role.to_string(),
+            before: before.cloned(),
+            after: after.cloned(),
+            actor: actor.map(str::to_string),
+        };
+
+        let mut events: Vec<RoleChangeEvent> = before
+            .iter()
+            .filter_map(|(name, old)| match after.get(name) {
+                None => Some(event(RoleChangeKind::Removed, name, Some(old), None)),
+                Some(new) if changed(old, new) => Some(event(RoleChangeKind::Updated, name, Some(old), Some(new))),
+                Some(_) => None,
+            })
+            .collect();
+
+        events.extend(
+            after
+                .iter()
+                .filter(|(name, _)| !before.contains_key(*name))
+                .map(|(name, new)| event(RoleChangeKind::Added, name, None, Some(new))),
+        );
+
+        events.sort_by(|a, b| a.role.cmp(&b.role));
+        events
+    }
+}
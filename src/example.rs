@@ -80,9 +80,7 @@ pub mod test {
         let mut service = RbacService::builder();
 
         // Register all permissions (just in case we need full list)
-        Users::register_all(&mut service);
-        Templates::register_all(&mut service);
-        Orders::register_all(&mut service);
+        service.register_domains((Users, Templates, Orders));
 
         service.add_role(Role::new(
             "UserManager",
@@ -0,0 +1,57 @@
+use std::sync::Arc;
+
+use crate::{CompiledPermissions, RbacError, service::PermissionKey, split_pattern, validation::is_valid_pattern};
+
+/// Source of feature flag states (LaunchDarkly, Unleash, config...), set by [.set_flag_provider()][crate::RbacServiceBuilder#method.set_flag_provider].
+/// Implemented for closures, so `|flag: &str| -> bool {...}` may be used as provider.
+pub trait FlagProvider: Send + Sync {
+    fn is_enabled(&self, flag: &str) -> bool;
+}
+
+impl<F: Fn(&str) -> bool + Send + Sync> FlagProvider for F {
+    fn is_enabled(&self, flag: &str) -> bool {
+        self(flag)
+    }
+}
+
+/// Permissions gated by feature flags, added by [.gate_permissions()][crate::RbacServiceBuilder#method.gate_permissions]
+#[derive(Clone, Default)]
+pub(crate) struct FeatureGates {
+    /// Gated permissions → flag
+    gates: Vec<(CompiledPermissions, String)>,
+    /// Patterns, which can't gate anything (malformed, conditional or machine-only) → flag
+    malformed: Vec<(String, String)>,
+    provider: Option<Arc<dyn FlagProvider>>,
+}
+
+impl FeatureGates {
+    pub(crate) fn add(&mut self, pattern: &str, flag: &str) {
+        if !is_valid_pattern(pattern) || split_pattern(pattern) != (false, pattern, None) {
+            self.malformed.push((pattern.to_string(), flag.to_string()));
+            return;
+        }
+        self.gates.push((CompiledPermissions::compile(&vec![pattern.to_string()]), flag.to_string()));
+    }
+
+    pub(crate) fn malformed(&self) -> &[(String, String)] {
+        &self.malformed
+    }
+
+    pub(crate) fn set_provider(&mut self, provider: Arc<dyn FlagProvider>) {
+        self.provider = Some(provider);
+    }
+
+    /// Fails, if permission is gated by flag, which is disabled (flags are disabled, until provider is set)
+    pub(crate) fn check(&self, permission: PermissionKey) -> Result<(), RbacError> {
+        let PermissionKey { domain, object_type, action, .. } = permission;
+        for (gated, flag) in &self.gates {
+            if gated.matches(domain, object_type, action) && !self.provider.as_ref().is_some_and(|provider| provider.is_enabled(flag)) {
+                return Err(RbacError::FeatureDisabled {
+                    permission: permission.to_string(),
+                    flag: flag.clone(),
+                });
+            }
+        }
+        Ok(())
+    }
+}
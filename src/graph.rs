@@ -0,0 +1,130 @@
+use std::{collections::HashSet, fmt::Write};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{CompiledPermissions, RbacService, RoleStorage};
+
+/// Policy structure: roles → their canonical patterns → registered permissions patterns expand to.
+/// Built by [.policy_graph()][crate::RbacService#method.policy_graph], rendered by [.to_dot()][PolicyGraph#method.to_dot] or serialized as JSON.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PolicyGraph {
+    pub nodes: Vec<GraphNode>,
+    pub edges: Vec<GraphEdge>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GraphNodeKind {
+    Role,
+    Pattern,
+    Permission,
+}
+
+/// Node with unique id: `role:Name`, `pattern:Orders::*` (`deny-pattern:Orders::*` for patterns of deny roles) or `permission:Orders::Order::Read`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GraphNode {
+    pub id: String,
+    pub kind: GraphNodeKind,
+    pub label: String,
+    /// Deny role, or pattern of deny role
+    #[serde(default)]
+    pub deny: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GraphEdge {
+    pub from: String,
+    pub to: String,
+}
+
+impl PolicyGraph {
+    /// Adds node, unless node with the same id is already `seen`, returning node id.
+    /// Patterns shared by roles are single node, but patterns granting and denying permissions are kept apart.
+    fn add_node(&mut self, seen: &mut HashSet<String>, kind: GraphNodeKind, label: &str, deny: bool) -> String {
+        let prefix = match (kind, deny) {
+            (GraphNodeKind::Role, _) => "role",
+            (GraphNodeKind::Pattern, false) => "pattern",
+            (GraphNodeKind::Pattern, true) => "deny-pattern",
+            (GraphNodeKind::Permission, _) => "permission",
+        };
+        let id = format!("{prefix}:{label}");
+        if seen.insert(id.clone()) {
+            self.nodes.push(GraphNode {
+                id: id.clone(),
+                kind,
+                label: label.to_string(),
+                deny,
+            });
+        }
+        id
+    }
+
+    /// Graphviz DOT rendering: roles are boxes, patterns ellipses and permissions plain text, deny roles and their patterns are red
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph rbac {\n    rankdir=LR;\n");
+        for node in &self.nodes {
+            let shape = match node.kind {
+                GraphNodeKind::Role => "box",
+                GraphNodeKind::Pattern => "ellipse",
+                GraphNodeKind::Permission => "plaintext",
+            };
+            let color = if node.deny { ", color=red" } else { "" };
+            let _ = writeln!(dot, "    {} [label={}, shape={shape}{color}];", dot_id(&node.id), dot_id(&node.label));
+        }
+        for edge in &self.edges {
+            let _ = writeln!(dot, "    {} -> {};", dot_id(&edge.from), dot_id(&edge.to));
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+/// Quoted DOT id: only quotes and backslashes are escaped (so labels aren't read as escape sequences), line breaks are written as `\n`
+fn dot_id(id: &str) -> String {
+    let mut quoted = String::with_capacity(id.len() + 2);
+    quoted.push('"');
+    for c in id.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => {}
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+impl<R: RoleStorage> RbacService<R> {
+    /// Graph of roles, their patterns and registered permissions patterns expand to (conditional patterns expand regardless of conditions)
+    pub fn policy_graph(&self) -> PolicyGraph {
+        let mut roles = self.get_roles();
+        roles.sort_by(|a, b| a.name.cmp(&b.name));
+        let permissions = self.get_all_permissions();
+
+        let mut graph = PolicyGraph::default();
+        let mut seen = HashSet::new();
+        for role in roles {
+            let role_id = graph.add_node(&mut seen, GraphNodeKind::Role, &role.name, role.deny);
+            for pattern in role.compiled_permissions.to_patterns() {
+                let pattern_id = graph.add_node(&mut seen, GraphNodeKind::Pattern, &pattern, role.deny);
+                graph.edges.push(GraphEdge {
+                    from: role_id.clone(),
+                    to: pattern_id.clone(),
+                });
+
+                let (_, unconditional, _) = crate::split_pattern(&pattern);
+                let compiled = CompiledPermissions::compile(&vec![unconditional.to_string()]);
+                for info in permissions.iter().filter(|info| compiled.matches(&info.domain, &info.object_type, &info.action)) {
+                    let permission_id = graph.add_node(&mut seen, GraphNodeKind::Permission, &info.full_name, false);
+                    graph.edges.push(GraphEdge {
+                        from: pattern_id.clone(),
+                        to: permission_id,
+                    });
+                }
+            }
+        }
+        graph
+    }
+}
@@ -0,0 +1,114 @@
+use std::sync::Arc;
+
+use arc_swap::ArcSwap;
+use serde::{Deserialize, Serialize};
+
+/// Rule granting roles to members of directory group.
+///
+/// `group` is DN (e.g. `cn=admins,ou=groups,dc=example,dc=com`), compared case-insensitively and ignoring spaces around `,` and `=`.
+/// It may contain `*` matching any characters, so `cn=*-admins,ou=groups,dc=example,dc=com` matches every admin group.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GroupRule {
+    pub group: String,
+    pub roles: Vec<String>,
+}
+
+impl GroupRule {
+    pub fn new(group: &str, roles: Vec<String>) -> Self {
+        GroupRule {
+            group: group.to_string(),
+            roles,
+        }
+    }
+}
+
+/// Mapping of LDAP/AD groups to role names.
+///
+/// Rules are swapped atomically by [.reload()][GroupRoleMap#method.reload], so mapping may be reloaded while it's used by concurrent requests,
+/// the same way roles are updated by [RbacServiceUpdater][crate::RbacServiceUpdater].
+#[derive(Debug, Default)]
+pub struct GroupRoleMap {
+    /// Rules with groups normalized
+    rules: ArcSwap<Vec<GroupRule>>,
+}
+
+impl GroupRoleMap {
+    pub fn new(rules: Vec<GroupRule>) -> Self {
+        GroupRoleMap {
+            rules: ArcSwap::new(Arc::new(normalized(rules))),
+        }
+    }
+
+    /// Replaces all rules at once
+    pub fn reload(&self, rules: Vec<GroupRule>) {
+        self.rules.store(Arc::new(normalized(rules)));
+    }
+
+    /// Replaces all rules with JSON array of rules read from reader. Rules are kept as they were, if reader data is malformed.
+    #[cfg(feature = "json")]
+    pub fn reload_from_reader(&self, reader: impl std::io::Read) -> Result<(), crate::RbacError> {
+        let rules: Vec<GroupRule> =
+            serde_json::from_reader(std::io::BufReader::new(reader)).map_err(|e| crate::RbacError::InvalidRoleData(e.to_string()))?;
+        self.reload(rules);
+        Ok(())
+    }
+
+    /// Current rules, with groups normalized
+    pub fn rules(&self) -> Vec<GroupRule> {
+        self.rules.load().to_vec()
+    }
+
+    /// Roles granted to member of given groups, sorted and deduplicated
+    pub fn roles_for<T: AsRef<str>>(&self, groups: &[T]) -> Vec<String> {
+        let rules = self.rules.load();
+        let groups: Vec<String> = groups.iter().map(|group| normalize_dn(group.as_ref())).collect();
+        let mut roles: Vec<String> = rules
+            .iter()
+            .filter(|rule| groups.iter().any(|group| glob_match(&rule.group, group)))
+            .flat_map(|rule| rule.roles.iter().cloned())
+            .collect();
+        roles.sort_unstable();
+        roles.dedup();
+        roles
+    }
+}
+
+fn normalized(mut rules: Vec<GroupRule>) -> Vec<GroupRule> {
+    for rule in &mut rules {
+        rule.group = normalize_dn(&rule.group);
+    }
+    rules
+}
+
+/// Lowercases DN and strips spaces around its separators: `CN=Admins, OU=Groups` → `cn=admins,ou=groups`
+fn normalize_dn(dn: &str) -> String {
+    dn.split(',')
+        .map(|component| match component.split_once('=') {
+            Some((attribute, value)) => format!("{}={}", attribute.trim(), value.trim()),
+            None => component.trim().to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+        .to_lowercase()
+}
+
+/// Matches text against pattern, where `*` matches any (possibly empty) run of characters
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = text.strip_prefix(first) else {
+        return false;
+    };
+    let mut parts: Vec<&str> = parts.collect();
+    let Some(last) = parts.pop() else {
+        // No wildcards
+        return rest.is_empty();
+    };
+    for part in parts {
+        match rest.find(part) {
+            Some(position) => rest = &rest[position + part.len()..],
+            None => return false,
+        }
+    }
+    rest.len() >= last.len() && rest.ends_with(last)
+}
@@ -113,22 +113,153 @@
 use std::{
     collections::{HashMap, HashSet},
     fmt,
+    sync::{Arc, Mutex, PoisonError},
+    time::SystemTime,
 };
+mod alias;
+mod analysis;
+mod audit;
+mod bundle;
+mod cache;
+mod catalog;
+#[cfg(feature = "json")]
+mod claims;
+#[cfg(feature = "clap")]
+mod cli;
+mod condition;
+mod config;
+mod csv;
+mod decision;
+mod deprecation;
+mod dto;
+#[cfg(feature = "encryption")]
+mod encryption;
+mod events;
 mod example;
+mod flags;
+mod graph;
+mod groups;
+mod machine;
 mod r#macro;
+mod memory;
+mod message;
+mod migration;
+mod mining;
+mod mutation;
+mod naming;
+#[cfg(feature = "otel")]
+mod otel;
+mod policy;
+#[cfg(feature = "json")]
+mod publisher;
+mod quota;
+mod resolver;
+mod review;
+mod route;
+mod schedule;
+mod scope;
 mod service;
+mod session;
+#[cfg(all(unix, feature = "signal"))]
+mod signal;
+mod snapshot;
+mod storage;
+mod subject;
+mod suggest;
+mod sync;
+/// [Rocket](https://rocket.rs) integration (`rocket` feature)
+#[cfg(feature = "rocket")]
+pub mod rocket;
+/// [tower](https://docs.rs/tower) integration (`tower` feature)
+#[cfg(feature = "tower")]
+pub mod tower;
+/// [warp](https://docs.rs/warp) integration (`warp` feature)
+#[cfg(feature = "warp")]
+pub mod warp;
+/// Matcher with C ABI for `wasm32-unknown-unknown` guests (`wasm` feature)
+#[cfg(feature = "wasm")]
+pub mod wasm;
+#[cfg(feature = "reqwest")]
+mod webhook;
 #[cfg(test)]
 mod tests;
+mod usage;
+mod validation;
 
 use serde::{Deserialize, Serialize};
-pub use service::{RbacService, RbacServiceBuilder, RbacServiceUpdater};
+use smallvec::SmallVec;
+pub use analysis::{PolicyConflict, RoleRemovalImpact};
+#[cfg(feature = "json")]
+pub use audit::JsonLinesAuditSink;
+#[cfg(unix)]
+pub use audit::SyslogAuditSink;
+pub use audit::{AuditSink, StderrAuditSink};
+pub use bundle::{BUNDLE_FORMAT_VERSION, PolicyBundle};
+pub use catalog::{CatalogDiff, PermissionCatalog, PermissionQuery};
+#[cfg(feature = "clap")]
+pub use cli::{CatalogPermissionParser, PermissionParser, RoleArg, permission_parser};
+#[cfg(feature = "json")]
+pub use claims::RbacVerifier;
+#[cfg(feature = "expressions")]
+pub use condition::CompareOp;
+pub use condition::{Attribute, Attributes, Condition, ConditionInput};
+pub use config::{RbacConfig, RbacOptions};
+pub use decision::RbacDecision;
+pub use deprecation::DeprecationWarning;
+pub use dto::{CatalogDto, DecisionDto, RoleDto};
+#[cfg(feature = "encryption")]
+pub use encryption::RoleFileKey;
+pub use events::{RoleChangeEvent, RoleChangeKind, RoleChangeSink};
+pub use flags::FlagProvider;
+pub use graph::{GraphEdge, GraphNode, GraphNodeKind, PolicyGraph};
+pub use groups::{GroupRoleMap, GroupRule};
+pub use machine::MachineSubject;
+pub use memory::{MemoryStats, RoleMemoryStats};
+pub use message::{ROLES_HEADER, authorize_message};
+pub use naming::{ROLE_NAMESPACE_SEPARATOR, RoleNameRules, namespaced_role_name, split_role_name};
+pub use policy::{PolicyAgreement, PolicyStatus};
+#[cfg(feature = "nats")]
+pub use publisher::NatsPublisher;
+#[cfg(feature = "json")]
+pub use publisher::{EventPublisher, PublisherSink};
+pub use quota::{MemoryQuotaStore, Quota, QuotaStore};
+pub use scope::{ScopeFormat, ScopeMapper};
+pub use migration::{RoleMigrator, RoleRewrite};
+pub use mining::{CandidateRole, RoleMiningReport, UnusedGrant};
+pub use mutation::{MutationReport, PolicyExpectation, PolicyMutation};
+pub use resolver::RoleResolver;
+pub use review::{AccessReview, GrantedPermission, SubjectAccess};
+pub use route::{RouteMap, RouteRule};
+pub use schedule::ScheduledUpdate;
+pub use session::RbacSession;
+#[cfg(all(unix, feature = "signal"))]
+pub use signal::SignalReload;
+pub use snapshot::{RbacSnapshot, SNAPSHOT_FORMAT_VERSION};
+pub use service::{ANONYMOUS_ROLE, DuplicateRolePolicy, FrozenRbacService, RbacService, RbacServiceBuilder, RbacServiceUpdater};
+#[cfg(feature = "parking_lot")]
+pub use service::RbacServiceSync;
+#[cfg(feature = "parking_lot")]
+pub use storage::LockedRoles;
+pub use storage::{AtomicRoles, FrozenRoles, MutableRoleStorage, RoleStorage};
+pub use subject::{ANONYMOUS_SUBJECT, Subject};
+pub use sync::RoleSyncReport;
+pub use usage::{LAST_CHECKED_SAMPLE, MAX_UNREGISTERED_PERMISSIONS, PermissionUsage};
+pub use validation::{PartialPattern, PatternState, PatternValidator};
+#[cfg(feature = "reqwest")]
+pub use webhook::{WebhookOptions, WebhookSink};
 
-/// Trait that all permission enums must implement
-pub trait Permission:
-    Sized + fmt::Display + fmt::Debug + Clone + PartialEq + Eq + std::hash::Hash
-{
+/// Dependencies of [define_permissions!] and [map_permissions!] expansions
+#[doc(hidden)]
+pub mod __private {
+    pub use crate::r#macro::{PermissionList, StringCase, cased_bytes, check_policy, join_parts, joined_bytes, renamed, replacement, unmapped_permission, utf8, write_cased};
+    pub use paste::paste;
+}
+
+/// Object-safe part of [Permission], so permissions of different types may be stored together as `Box<dyn PermissionCore>`
+/// (route tables, plugin registries) and checked the same way as typed ones.
+pub trait PermissionCore {
     /// Returns the domain name (e.g., "Users", "Templates")
-    fn domain() -> &'static str;
+    fn domain(&self) -> &'static str;
 
     /// Returns the object type (e.g., "User", "Method", "Template")
     fn object_type(&self) -> &'static str;
@@ -136,75 +267,419 @@ pub trait Permission:
     /// Returns the action name (e.g., "Read", "Write")
     fn action(&self) -> &'static str;
 
+    /// Get human-readable description
+    fn description(&self) -> &'static str;
+
+    /// Returns full permission string (e.g., "Users::User::Read") without allocation
+    fn full_name(&self) -> &'static str;
+
+    /// Returns deprecation note, if permission is deprecated (e.g. by `#[deprecated(note = "use Update")]` in [define_permissions!]).
+    /// Full name of permission deprecated in favor of other one is name of its replacement
+    /// (its own name is known to service only once registered, see [define_permissions!]).
+    fn deprecated(&self) -> Option<&'static str> {
+        None
+    }
+
     /// Returns full permission string (e.g., "Users::User::Read")
     fn to_permission_string(&self) -> String {
-        format!(
-            "{}::{}::{}",
-            Self::domain(),
-            self.object_type(),
-            self.action()
-        )
+        self.full_name().to_string()
     }
+}
 
+/// Trait that all permission enums must implement
+///
+/// `AsRef<Self>` lets checks take permission either by value or by reference (e.g. held in config map), without cloning it.
+/// [define_permissions!] implements it, so it only matters for hand-written permissions.
+pub trait Permission:
+    PermissionCore + Sized + fmt::Display + fmt::Debug + Clone + PartialEq + Eq + std::hash::Hash + AsRef<Self>
+{
     /// Parse from string representation
     fn from_string(s: &str) -> Option<Self>;
 
     /// Get all possible permissions for this resource
     fn all_permissions() -> Vec<Self>;
-
-    /// Get human-readable description
-    fn description(&self) -> &'static str;
 }
 
 /// Trait that any of the subjects (like User or Client) must implement to check permissions
 pub trait RbacSubject {
     fn get_roles(&self) -> &Vec<String>;
     fn name(&self) -> &str;
+
+    /// Attributes conditional patterns are evaluated against (see [Condition]), none by default
+    fn attributes(&self) -> Attributes {
+        Attributes::new()
+    }
+
+    /// Anonymous (guest) subjects are checked against [anonymous roles][RbacServiceBuilder::set_anonymous_roles] instead of their own roles
+    fn is_anonymous(&self) -> bool {
+        false
+    }
+
+    /// Machine (service-to-service) subjects are the only ones [machine-only patterns][MACHINE_ONLY] apply to, see [MachineSubject]
+    fn is_machine(&self) -> bool {
+        false
+    }
+
+    /// Audiences (services) machine subject may call, checked against [service audience][RbacServiceBuilder::set_audience]
+    fn audiences(&self) -> &[String] {
+        &[]
+    }
+
+    /// Correlation (request) ID attached to [decisions][RbacDecision::correlation_id] of subject checks, so audit records can be joined with request logs
+    fn correlation_id(&self) -> Option<&str> {
+        None
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum RbacError {
     PermissionDenied(String),
+    InvalidRoleData(String),
+    DuplicateRole(String),
+    /// Role doesn't exist in the service
+    RoleNotFound(String),
+    /// Role pattern isn't one of forms roles compile (e.g. `Orders::*::Read`)
+    InvalidPattern {
+        role: String,
+        pattern: String,
+    },
+    /// Role pattern compiled with warning (see [RbacServiceBuilder::set_compile_check])
+    CompileWarning {
+        role: String,
+        warning: CompileWarning,
+    },
+    /// Machine subject isn't allowed to call this service (see [RbacServiceBuilder::set_audience])
+    AudienceMismatch {
+        subject: String,
+        audience: String,
+    },
+    /// Role is protected from runtime modification (see [RbacServiceBuilder::protect_role])
+    ProtectedRole(String),
+    /// Role name breaks [RoleNameRules]
+    InvalidRoleName {
+        role: String,
+        reason: String,
+    },
+    RevisionMismatch {
+        role: String,
+        expected: u64,
+        actual: u64,
+    },
+    /// Permission string isn't in `Domain::Object::Action` form
+    InvalidPermission(String),
+    /// Permission string isn't among registered permissions, with closest registered ones as suggestions for typos
+    UnknownPermission {
+        permission: String,
+        suggestions: Vec<String>,
+    },
+    /// Pattern condition can't be parsed, see [Condition]
+    InvalidCondition(String),
+    /// Quota can't be parsed, see [Quota::parse]
+    InvalidQuota(String),
+    /// Permission is granted, but subject used up its [Quota]
+    QuotaExceeded {
+        permission: String,
+        limit: u64,
+    },
+    /// No [RouteMap] rule matches request
+    UnmappedRoute(String),
+    /// Fallback roles don't exist among service roles (see [RbacServiceBuilder::set_fallback_check])
+    MissingFallbackRoles(Vec<String>),
+    /// Permission denied, and some of subject roles don't exist in the service (see [RbacServiceBuilder::set_report_unknown_roles])
+    UnknownRoles {
+        permission: String,
+        roles: Vec<String>,
+    },
+    /// Permission is gated by feature flag, which is disabled (see [RbacServiceBuilder::gate_permissions])
+    FeatureDisabled {
+        permission: String,
+        flag: String,
+    },
+    /// Pattern gated by feature flag isn't one of forms roles compile, so it gates nothing (see [RbacServiceBuilder::gate_permissions])
+    InvalidGatePattern {
+        pattern: String,
+        flag: String,
+    },
+    /// [PolicyBundle] catalog doesn't match registered permissions (see [RbacServiceBuilder::set_bundle_check])
+    IncompatibleBundle {
+        /// Registered permissions missing in bundle catalog
+        missing: Vec<String>,
+        /// Bundle catalog permissions, which aren't registered
+        extra: Vec<String>,
+    },
+    /// Service didn't reach expected roles generation in time (see [RbacService::wait_for_generation])
+    GenerationTimeout {
+        expected: u64,
+        actual: u64,
+    },
 }
 
 impl fmt::Display for RbacError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
             Self::PermissionDenied(p) => write!(f, "Permission denied: {}", p),
+            Self::InvalidRoleData(e) => write!(f, "Invalid role data: {}", e),
+            Self::DuplicateRole(r) => write!(f, "Duplicate role: {}", r),
+            Self::RoleNotFound(r) => write!(f, "Role not found: {}", r),
+            Self::InvalidPattern { role, pattern } => write!(f, "Invalid pattern of role {}: {}", role, pattern),
+            Self::CompileWarning { role, warning } => write!(f, "Compile warning of role {}: {}", role, warning),
+            Self::AudienceMismatch { subject, audience } => write!(f, "Audience mismatch: {} may not call {}", subject, audience),
+            Self::ProtectedRole(r) => write!(f, "Protected role: {}", r),
+            Self::InvalidRoleName { role, reason } => write!(f, "Invalid role name {}: {}", role, reason),
+            Self::RevisionMismatch { role, expected, actual } => write!(
+                f,
+                "Revision mismatch for role {}: expected {}, actual {}",
+                role, expected, actual
+            ),
+            Self::InvalidPermission(p) => write!(f, "Invalid permission: {}", p),
+            Self::UnknownPermission { permission, suggestions } if suggestions.is_empty() => {
+                write!(f, "Unknown permission: {}", permission)
+            }
+            Self::UnknownPermission { permission, suggestions } => {
+                write!(f, "Unknown permission: {} (did you mean {}?)", permission, suggestions.join(", "))
+            }
+            Self::InvalidCondition(c) => write!(f, "Invalid condition: {}", c),
+            Self::InvalidQuota(q) => write!(f, "Invalid quota: {}", q),
+            Self::QuotaExceeded { permission, limit } => write!(f, "Quota exceeded: {} (limit {})", permission, limit),
+            Self::UnmappedRoute(route) => write!(f, "Unmapped route: {}", route),
+            Self::MissingFallbackRoles(roles) => write!(f, "Missing fallback roles: {}", roles.join(", ")),
+            Self::UnknownRoles { permission, roles } => write!(
+                f,
+                "Permission denied: {} (unknown roles: {})",
+                permission,
+                roles.join(", ")
+            ),
+            Self::FeatureDisabled { permission, flag } => write!(f, "Feature disabled: {} (flag {})", permission, flag),
+            Self::InvalidGatePattern { pattern, flag } => write!(f, "Invalid pattern gated by flag {}: {}", flag, pattern),
+            Self::IncompatibleBundle { missing, extra } => write!(
+                f,
+                "Incompatible bundle: missing permissions: {}; extra permissions: {}",
+                missing.join(", "),
+                extra.join(", ")
+            ),
+            Self::GenerationTimeout { expected, actual } => write!(f, "Generation timeout: expected {}, actual {}", expected, actual),
         }
     }
 }
 
 impl std::error::Error for RbacError {}
 
-#[derive(Debug, Clone)]
+/// How seriously failed configuration check should be treated
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Severity {
+    /// Don't check at all
+    Ignore,
+    /// Report as warning, but continue
+    #[default]
+    Warn,
+    /// Fail with error
+    Error,
+}
+
+/// Configuration problem found, when service was built (see [RbacService::build_warnings])
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BuildWarning {
+    /// Fallback role doesn't exist among service roles, so subjects without roles are denied everything
+    MissingFallbackRole(String),
+    /// Role pattern compiled with warning
+    RolePattern {
+        role: String,
+        warning: CompileWarning,
+    },
+    /// Role permission was rewritten (see [RbacServiceBuilder::migrate_roles])
+    MigratedPermission(RoleRewrite),
+    /// Pattern gated by feature flag is malformed, so it gates nothing (see [RbacServiceBuilder::gate_permissions])
+    GatePattern {
+        pattern: String,
+        flag: String,
+    },
+}
+
+impl fmt::Display for BuildWarning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::MissingFallbackRole(r) => write!(f, "Fallback role {} doesn't exist", r),
+            Self::RolePattern { role, warning } => write!(f, "Role {}: {}", role, warning),
+            Self::MigratedPermission(rewrite) => write!(f, "Migrated {}", rewrite),
+            Self::GatePattern { pattern, flag } => write!(f, "Flag {} gates malformed pattern {}", flag, pattern),
+        }
+    }
+}
+
+/// Problem of role pattern found by [CompiledPermissions::compile_with_warnings]. Roles still compile as best they can.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CompileWarning {
+    /// Pattern is covered by broader pattern of the same role, so it has no effect
+    ShadowedPattern {
+        pattern: String,
+        by: String,
+    },
+    /// Pattern (or its condition) can't be parsed, so it's dropped
+    MalformedPattern(String),
+    /// Action set has no actions (`Orders::Order::{}`), so pattern grants nothing
+    EmptyActionSet(String),
+}
+
+impl fmt::Display for CompileWarning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::ShadowedPattern { pattern, by } => write!(f, "pattern {} is shadowed by {}", pattern, by),
+            Self::MalformedPattern(p) => write!(f, "malformed pattern {}", p),
+            Self::EmptyActionSet(p) => write!(f, "empty action set {}", p),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct PermissionInfo {
     pub domain: String,
     pub object_type: String,
     pub action: String,
     pub full_name: String,
     pub description: String,
+    /// Deprecation note, if permission is deprecated (see [.deprecate_permission()][RbacServiceBuilder#method.deprecate_permission]
+    /// and [PermissionCore::deprecated])
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deprecated: Option<String>,
+}
+
+impl PermissionInfo {
+    /// Info of permission under its own name, even if it's deprecated in favor of other one
+    pub fn of(permission: &(impl PermissionCore + ?Sized)) -> Self {
+        let deprecated = permission.deprecated();
+        let full_name = match deprecated {
+            Some(_) => format!("{}::{}::{}", permission.domain(), permission.object_type(), permission.action()),
+            None => permission.full_name().to_string(),
+        };
+        PermissionInfo {
+            domain: permission.domain().to_string(),
+            object_type: permission.object_type().to_string(),
+            action: permission.action().to_string(),
+            full_name,
+            description: permission.description().to_string(),
+            deprecated: deprecated.map(str::to_string),
+        }
+    }
+}
+
+/// Domain of permissions, implemented by `Domain` marker type [define_permissions!] generates in every domain module,
+/// so generic code may be written over domains (e.g. `Orders::Domain`).
+pub trait PermissionDomain {
+    fn name() -> &'static str;
+
+    /// Info of all permissions of all domain objects
+    fn all() -> Vec<PermissionInfo>;
+
+    /// Registers all domain permissions in builder
+    fn register(builder: &mut RbacServiceBuilder);
+}
+
+/// Domain or tuple of domains accepted by [.register_domains()][RbacServiceBuilder#method.register_domains]
+pub trait PermissionDomains {
+    fn register_all(builder: &mut RbacServiceBuilder);
+}
+
+impl<D: PermissionDomain> PermissionDomains for D {
+    fn register_all(builder: &mut RbacServiceBuilder) {
+        D::register(builder)
+    }
 }
 
+macro_rules! impl_permission_domains {
+    ($($domain:ident),+) => {
+        impl<$($domain: PermissionDomain),+> PermissionDomains for ($($domain,)+) {
+            fn register_all(builder: &mut RbacServiceBuilder) {
+                $($domain::register(builder);)+
+            }
+        }
+    };
+}
+
+impl_permission_domains!(A);
+impl_permission_domains!(A, B);
+impl_permission_domains!(A, B, C);
+impl_permission_domains!(A, B, C, D);
+impl_permission_domains!(A, B, C, D, E);
+impl_permission_domains!(A, B, C, D, E, F);
+impl_permission_domains!(A, B, C, D, E, F, G);
+impl_permission_domains!(A, B, C, D, E, F, G, H);
+impl_permission_domains!(A, B, C, D, E, F, G, H, I);
+impl_permission_domains!(A, B, C, D, E, F, G, H, I, J);
+impl_permission_domains!(A, B, C, D, E, F, G, H, I, J, K);
+impl_permission_domains!(A, B, C, D, E, F, G, H, I, J, K, L);
+
+/// Current version of serialized role format ([RoleS])
+pub const ROLE_FORMAT_VERSION: u32 = 1;
+
 /// Role definition with permissions
+///
+/// Serialized form of [Role]. Unknown fields are ignored, so data written by newer versions still loads,
+/// and data written by older versions is migrated to [ROLE_FORMAT_VERSION] on deserialization.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RoleS {
+    /// Format version, `0` for data written before versioning was introduced
+    #[serde(default)]
+    pub version: u32,
     pub name: String,
     pub permissions: Vec<String>,
+    #[serde(default)]
+    pub revision: u64,
+    #[serde(default)]
+    pub deny: bool,
+    #[serde(default)]
+    pub priority: i32,
+    #[serde(default = "enabled_default")]
+    pub enabled: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deleted_at: Option<SystemTime>,
 }
 
+fn enabled_default() -> bool {
+    true
+}
+
+impl RoleS {
+    /// Upgrades role data written by older versions of format to [ROLE_FORMAT_VERSION]
+    pub fn migrate(mut self) -> Self {
+        // Every step upgrades data by one version: add new arm here, when format changes
+        while self.version < ROLE_FORMAT_VERSION {
+            self.version = match self.version {
+                // v1 only introduced `version` field itself, data is the same
+                0 => 1,
+                version => version + 1,
+            };
+        }
+        self
+    }
+}
+
+/// Role is always serialized in normalized form (see [Role::normalized])
 impl From<Role> for RoleS {
     fn from(value: Role) -> Self {
         RoleS {
+            version: ROLE_FORMAT_VERSION,
+            permissions: value.compiled_permissions.to_patterns(),
             name: value.name,
-            permissions: value.permissions,
+            revision: value.revision,
+            deny: value.deny,
+            priority: value.priority,
+            enabled: value.enabled,
+            deleted_at: value.deleted_at,
         }
     }
 }
 
 impl From<RoleS> for Role {
     fn from(value: RoleS) -> Self {
-        Role::new(&value.name, value.permissions)
+        let value = value.migrate();
+        Role {
+            revision: value.revision,
+            deny: value.deny,
+            priority: value.priority,
+            enabled: value.enabled,
+            deleted_at: value.deleted_at,
+            ..Role::new(&value.name, value.permissions)
+        }
     }
 }
 
@@ -216,6 +691,21 @@ pub struct Role {
     pub name: String,
     pub permissions: Vec<String>,
     pub compiled_permissions: CompiledPermissions,
+    /// Revision of role definition: `0` for new roles, incremented on every upsert by [RbacServiceUpdater].
+    /// External stores may use it for optimistic locking with [.upsert_if_match()][RbacServiceUpdater#method.upsert_if_match].
+    pub revision: u64,
+    /// Deny role takes its permissions away from subject, overriding any other role granting them
+    /// (e.g. "Suspended" role with `*` suspends account regardless of its grants)
+    pub deny: bool,
+    /// Resolves conflicts between allow and deny roles matching the same permission: role with higher priority wins,
+    /// on equal priority deny wins. `0` by default, so without priorities deny always wins.
+    pub priority: i32,
+    /// Disabled role is kept, but grants and denies nothing, e.g. while incident is investigated
+    /// (see [.set_role_enabled()][RbacServiceUpdater#method.set_role_enabled])
+    pub enabled: bool,
+    /// When role was tombstoned by [.tombstone_role()][RbacServiceUpdater#method.tombstone_role]. Tombstoned role is kept, so decisions
+    /// referencing it can still be explained, but it grants and denies nothing until [restored][RbacServiceUpdater#method.restore_role].
+    pub deleted_at: Option<SystemTime>,
 }
 
 impl Role {
@@ -224,26 +714,182 @@ impl Role {
             name: name.to_string(),
             compiled_permissions: CompiledPermissions::compile(&permissions),
             permissions,
+            revision: 0,
+            deny: false,
+            priority: 0,
+            enabled: true,
+            deleted_at: None,
+        }
+    }
+
+    /// Creates deny role, see [Role::deny]
+    pub fn new_deny(name: &str, permissions: Vec<String>) -> Self {
+        Role {
+            deny: true,
+            ..Role::new(name, permissions)
+        }
+    }
+
+    /// Checks that conditions of all conditional patterns parse (otherwise such patterns are silently dropped on compilation)
+    pub fn validate_conditions(&self) -> Result<(), RbacError> {
+        for permission in &self.permissions {
+            if let (_, _, Some(condition)) = split_pattern(permission) {
+                Condition::parse(condition)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Role takes part in checks: it's [enabled][Role::enabled] and isn't [tombstoned][Role::deleted_at]
+    pub fn is_active(&self) -> bool {
+        self.enabled && self.deleted_at.is_none()
+    }
+
+    /// Sets [Role::priority]
+    pub fn with_priority(self, priority: i32) -> Self {
+        Role { priority, ..self }
+    }
+
+    /// Returns role with the same effective permissions written in canonical minimal form:
+    /// sorted, without duplicates, malformed entries and patterns shadowed by wildcards, with actions of the same object joined into single set.
+    pub fn normalized(&self) -> Role {
+        Role {
+            name: self.name.clone(),
+            permissions: self.compiled_permissions.to_patterns(),
+            compiled_permissions: self.compiled_permissions.clone(),
+            revision: self.revision,
+            deny: self.deny,
+            priority: self.priority,
+            enabled: self.enabled,
+            deleted_at: self.deleted_at,
         }
     }
 }
 
 
+/// Set of interned names
+type NameSet = HashSet<Arc<str>>;
+/// Object → set of actions
+type ObjectActions = HashMap<Arc<str>, NameSet>;
+
 #[derive(Debug, Default, Clone)]
 pub struct CompiledPermissions {
     global_permission: bool,
-    domain_wildcards: HashSet<String>,
+    domain_wildcards: NameSet,
     /// Domain → set of object types with wildcard permissions
-    object_wildcards: HashMap<String, HashSet<String>>,
+    object_wildcards: HashMap<Arc<str>, NameSet>,
     /// Domain → Object → set of actions
-    exact_permissions: HashMap<String, HashMap<String, HashSet<String>>>,
+    exact_permissions: HashMap<Arc<str>, ObjectActions>,
+    /// Patterns granted only when their condition holds
+    conditional: Vec<ConditionalPattern>,
+}
+
+#[derive(Debug, Clone)]
+struct ConditionalPattern {
+    pattern: String,
+    permissions: CompiledPermissions,
+    condition: Condition,
+    /// Pattern applies to machine subjects only
+    machine_only: bool,
+}
+
+impl ConditionalPattern {
+    fn to_pattern(&self) -> String {
+        let prefix = if self.machine_only { MACHINE_ONLY } else { "" };
+        match &self.condition {
+            Condition::All(conditions) if conditions.is_empty() => format!("{}{}", prefix, self.pattern),
+            condition => format!("{}{} if {}", prefix, self.pattern, condition),
+        }
+    }
+
+    fn matches(&self, domain: &str, object_type: &str, action: &str, input: &ConditionInput) -> bool {
+        (!self.machine_only || input.machine) && self.permissions.matches(domain, object_type, action) && self.condition.evaluate(input)
+    }
+}
+
+/// Prefix of patterns applying to [machine subjects][RbacSubject::is_machine] only: `"@machine Orders::Invoice::Generate"`.
+/// Checks of bare roles have no subject, so machine-only patterns don't apply to them.
+pub const MACHINE_ONLY: &str = "@machine ";
+
+/// Splits pattern into machine-only marker, pattern itself and its condition
+pub(crate) fn split_pattern(pattern: &str) -> (bool, &str, Option<&str>) {
+    let (machine_only, pattern) = match pattern.strip_prefix(MACHINE_ONLY) {
+        Some(pattern) => (true, pattern.trim_start()),
+        None => (false, pattern),
+    };
+    match pattern.split_once(" if ") {
+        Some((pattern, condition)) => (machine_only, pattern.trim(), Some(condition)),
+        None => (machine_only, pattern, None),
+    }
+}
+
+fn compile_warnings(permissions: &[String]) -> Vec<CompileWarning> {
+    // Conditional and machine-only patterns don't shadow anything, as they may not apply
+    let unconditional: Vec<&str> = permissions
+        .iter()
+        .filter_map(|pattern| match split_pattern(pattern) {
+            (false, pattern, None) => Some(pattern),
+            _ => None,
+        })
+        .collect();
+    let covers = |broader: &str, pattern: &str| match broader.strip_suffix('*') {
+        Some(prefix) => broader != pattern && (prefix.is_empty() || prefix.ends_with("::")) && pattern.starts_with(prefix),
+        None => false,
+    };
+
+    let mut warnings = Vec::new();
+    for pattern in permissions {
+        let (_, unmarked, condition) = split_pattern(pattern);
+        let warning = if unmarked.ends_with("::{}") {
+            CompileWarning::EmptyActionSet(pattern.clone())
+        } else if !validation::is_valid_pattern(pattern) || condition.is_some_and(|condition| Condition::parse(condition).is_err()) {
+            CompileWarning::MalformedPattern(pattern.clone())
+        } else if let Some(by) = unconditional.iter().find(|broader| covers(broader, unmarked)) {
+            CompileWarning::ShadowedPattern {
+                pattern: pattern.clone(),
+                by: by.to_string(),
+            }
+        } else {
+            continue;
+        };
+        warnings.push(warning);
+    }
+    warnings
 }
 
 impl CompiledPermissions {
+    /// Compiles patterns as [.compile()][CompiledPermissions::compile] does, collecting problems it silently works around:
+    /// patterns shadowed by broader ones, malformed patterns and empty action sets
+    pub fn compile_with_warnings(permissions: &Vec<String>) -> (Self, Vec<CompileWarning>) {
+        (Self::compile(permissions), compile_warnings(permissions))
+    }
+
+    /// Compiles patterns into lookup structure. Result doesn't depend on pattern order:
+    /// wildcards are collected first, then narrower patterns are added only when no wildcard covers them.
     pub fn compile(permissions: &Vec<String>) -> Self {
         let mut compiled = CompiledPermissions::default();
+        // Names repeat a lot within the role, so even standalone role shares them between patterns
+        let mut table = HashSet::new();
+        let mut narrower: Vec<SmallVec<[&str; 3]>> = Vec::new();
 
+        // Pass 1: conditional patterns and wildcards
         for perm in permissions {
+            let (machine_only, perm, condition) = split_pattern(perm);
+            if machine_only || condition.is_some() {
+                let condition = match condition.map(Condition::parse) {
+                    Some(Ok(condition)) => condition,
+                    Some(Err(_)) => continue,
+                    None => Condition::All(Vec::new()),
+                };
+                compiled.conditional.push(ConditionalPattern {
+                    permissions: CompiledPermissions::compile(&vec![perm.to_string()]),
+                    pattern: perm.to_string(),
+                    condition,
+                    machine_only,
+                });
+                continue;
+            }
+
             // Check for global wildcard
             if perm == "*" {
                 // Global wildcard covers everything - no need to process anything else
@@ -253,89 +899,124 @@ impl CompiledPermissions {
                 };
             }
 
-            let parts: Vec<&str> = perm.split("::").collect();
-
+            let parts: SmallVec<[&str; 3]> = perm.split("::").collect();
             match parts.len() {
                 2 if parts[1] == "*" => {
                     // Domain wildcard: "Users::*"
-                    let domain = parts[0].to_string();
-                    compiled.domain_wildcards.insert(domain.clone());
-
-                    // Remove any object wildcards or exact permissions for this domain
-                    compiled.object_wildcards.remove(&domain);
-                    compiled.exact_permissions.remove(&domain);
+                    compiled.domain_wildcards.insert(symbol(&mut table, parts[0]));
                 }
                 3 if parts[2] == "*" => {
                     // Object wildcard: "Users::User::*"
-                    let domain = parts[0].to_string();
-                    let object = parts[1].to_string();
-
-                    // Only add if there's no domain wildcard covering this
-                    if !compiled.domain_wildcards.contains(&domain) {
-                        compiled.object_wildcards
-                            .entry(domain.clone())
-                            .or_default()
-                            .insert(object.clone());
-
-                        // Remove any exact permissions for this domain::object
-                        if let Some(objects) = compiled.exact_permissions.get_mut(&domain) {
-                            objects.remove(&object);
-                        }
-                    }
-                }
-                3 if parts[2].starts_with('{') && parts[2].ends_with('}') => {
-                    // Action set: "Users::User::{Create,Write}"
-                    let domain = parts[0].to_string();
-                    let object = parts[1].to_string();
-
-                    // Only process if not covered by domain or object wildcard
-                    if !compiled.domain_wildcards.contains(&domain)
-                        && !compiled.object_wildcards
-                            .get(&domain)
-                            .is_some_and(|objs| objs.contains(&object))
-                    {
-                        let actions_str = &parts[2][1..parts[2].len() - 1];
-                        let actions = actions_str.split(',').map(|s| s.trim());
-
-                        let action_set = compiled.exact_permissions
-                            .entry(domain)
-                            .or_default()
-                            .entry(object)
-                            .or_default();
-
-                        for action in actions {
-                            action_set.insert(action.to_string());
-                        }
-                    }
-                }
-                _ => {
-                    // Exact permission
-                    if parts.len() == 3 {
-                        let domain = parts[0].to_string();
-                        let object = parts[1].to_string();
-                        let action = parts[2].to_string();
-
-                        // Only add if not covered by domain or object wildcard
-                        if !compiled.domain_wildcards.contains(&domain)
-                            && !compiled.object_wildcards
-                                .get(&domain)
-                                .is_some_and(|objs| objs.contains(&object))
-                        {
-                            compiled.exact_permissions
-                                .entry(domain)
-                                .or_default()
-                                .entry(object)
-                                .or_default()
-                                .insert(action);
-                        }
-                    }
+                    let domain = symbol(&mut table, parts[0]);
+                    let object = symbol(&mut table, parts[1]);
+                    compiled.object_wildcards.entry(domain).or_default().insert(object);
                 }
+                // Malformed patterns grant nothing
+                3 => narrower.push(parts),
+                _ => {}
+            }
+        }
+
+        // Object wildcards covered by domain wildcards are redundant
+        let domain_wildcards = &compiled.domain_wildcards;
+        compiled.object_wildcards.retain(|domain, _| !domain_wildcards.contains(domain));
+
+        // Pass 2: action sets and exact permissions not covered by wildcards
+        for parts in narrower {
+            let domain = symbol(&mut table, parts[0]);
+            let object = symbol(&mut table, parts[1]);
+            if compiled.domain_wildcards.contains(&domain)
+                || compiled.object_wildcards.get(&domain).is_some_and(|objs| objs.contains(&object))
+            {
+                continue;
+            }
+
+            // Action set: "Users::User::{Create,Write}", or exact permission
+            let actions = match parts[2].strip_prefix('{').and_then(|a| a.strip_suffix('}')) {
+                Some(actions_str) => actions_str.split(',').map(|s| s.trim()).collect(),
+                None => SmallVec::<[&str; 3]>::from_slice(&[parts[2]]),
+            };
+            let action_set = compiled.exact_permissions.entry(domain).or_default().entry(object).or_default();
+            for action in actions {
+                action_set.insert(symbol(&mut table, action));
             }
         }
 
         compiled
     }
 
+    /// Canonical sorted list of patterns producing the same compiled permissions
+    pub fn to_patterns(&self) -> Vec<String> {
+        if self.global_permission {
+            return vec!["*".to_string()];
+        }
+
+        let mut patterns: Vec<String> = self
+            .domain_wildcards
+            .iter()
+            .map(|domain| format!("{}::*", domain))
+            .collect();
+
+        for (domain, objects) in &self.object_wildcards {
+            if self.domain_wildcards.contains(domain) {
+                continue;
+            }
+            patterns.extend(objects.iter().map(|object| format!("{}::{}::*", domain, object)));
+        }
+
+        for (domain, objects) in &self.exact_permissions {
+            if self.domain_wildcards.contains(domain) {
+                continue;
+            }
+            for (object, actions) in objects {
+                if actions.is_empty()
+                    || self.object_wildcards.get(domain).is_some_and(|objs| objs.contains(object))
+                {
+                    continue;
+                }
+                let mut actions: Vec<&str> = actions.iter().map(|action| action.as_ref()).collect();
+                actions.sort_unstable();
+                patterns.push(match actions.as_slice() {
+                    [action] => format!("{}::{}::{}", domain, object, action),
+                    _ => format!("{}::{}::{{{}}}", domain, object, actions.join(",")),
+                });
+            }
+        }
+
+        patterns.extend(self.conditional.iter().map(ConditionalPattern::to_pattern));
+        patterns.sort_unstable();
+        patterns.dedup();
+        patterns
+    }
+
+    /// Same as [.matching_pattern()][CompiledPermissions#method.matching_pattern], but also considers conditional patterns
+    /// (returned with their condition), evaluating conditions against given input
+    pub fn matching_pattern_with(&self, domain: &str, object_type: &str, action: &str, input: &ConditionInput) -> Option<String> {
+        self.matching_pattern(domain, object_type, action).or_else(|| {
+            self.conditional
+                .iter()
+                .find(|conditional| conditional.matches(domain, object_type, action, input))
+                .map(ConditionalPattern::to_pattern)
+        })
+    }
+
+    /// Canonical pattern granting permission (e.g. `Orders::*` for `Orders::Order::Read`), if any
+    pub fn matching_pattern(&self, domain: &str, object_type: &str, action: &str) -> Option<String> {
+        if self.global_permission {
+            Some("*".to_string())
+        } else if self.domain_wildcards.contains(domain) {
+            Some(format!("{}::*", domain))
+        } else if self.object_wildcards.get(domain).is_some_and(|objs| objs.contains(object_type)) {
+            Some(format!("{}::{}::*", domain, object_type))
+        } else {
+            self.exact_permissions
+                .get(domain)
+                .and_then(|objs| objs.get(object_type))
+                .is_some_and(|actions| actions.contains(action))
+                .then(|| format!("{}::{}::{}", domain, object_type, action))
+        }
+    }
+
     /// Check if permission matches
     #[inline]
     pub fn matches(
@@ -369,4 +1050,88 @@ impl CompiledPermissions {
 
         false
     }
+
+    /// Same as [.matches()][CompiledPermissions#method.matches], but also considers conditional patterns,
+    /// evaluating conditions against given input
+    #[inline]
+    pub fn matches_with(&self, domain: &str, object_type: &str, action: &str, input: &ConditionInput) -> bool {
+        self.matches(domain, object_type, action)
+            || self.conditional.iter().any(|conditional| conditional.matches(domain, object_type, action, input))
+    }
+}
+
+/// Shared storage of domain, object and action names.
+/// Service interns compiled permissions of every added role, so thousands of similar roles reference the same strings instead of holding own copies.
+/// Names no role uses anymore are pruned whenever roles are replaced.
+#[derive(Debug, Default)]
+pub(crate) struct Interner(Mutex<HashSet<Arc<str>>>);
+
+impl Interner {
+    /// Replaces names inside compiled permissions with shared ones
+    pub(crate) fn intern(&self, compiled: &mut CompiledPermissions) {
+        let mut table = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+
+        compiled.domain_wildcards = compiled
+            .domain_wildcards
+            .drain()
+            .map(|domain| symbol(&mut table, &domain))
+            .collect();
+
+        compiled.object_wildcards = compiled
+            .object_wildcards
+            .drain()
+            .map(|(domain, objects)| {
+                let objects = objects.iter().map(|object| symbol(&mut table, object)).collect();
+                (symbol(&mut table, &domain), objects)
+            })
+            .collect();
+
+        compiled.exact_permissions = compiled
+            .exact_permissions
+            .drain()
+            .map(|(domain, objects)| {
+                let objects = objects
+                    .into_iter()
+                    .map(|(object, actions)| {
+                        let actions = actions.iter().map(|action| symbol(&mut table, action)).collect();
+                        (symbol(&mut table, &object), actions)
+                    })
+                    .collect();
+                (symbol(&mut table, &domain), objects)
+            })
+            .collect();
+
+        drop(table);
+        for conditional in &mut compiled.conditional {
+            self.intern(&mut conditional.permissions);
+        }
+    }
+
+    /// Drops names held only by table
+    pub(crate) fn prune(&self) {
+        let mut table = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+        table.retain(|name| Arc::strong_count(name) > 1);
+        if table.len() < table.capacity() / 4 {
+            table.shrink_to_fit();
+        }
+    }
+
+    /// Interned names (heap part of each string and its slot)
+    pub(crate) fn heap_bytes(&self) -> usize {
+        let table = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+        table.capacity() * (std::mem::size_of::<Arc<str>>() + 1)
+            + table.iter().map(|name| 2 * std::mem::size_of::<usize>() + name.len()).sum::<usize>()
+    }
+}
+
+/// Returns shared copy of name from table, adding it if missing
+fn symbol(table: &mut HashSet<Arc<str>>, name: &str) -> Arc<str> {
+    match table.get(name) {
+        Some(symbol) => symbol.clone(),
+        None => {
+            let symbol: Arc<str> = Arc::from(name);
+            table.insert(symbol.clone());
+            symbol
+        }
+    }
 }
@@ -0,0 +1,42 @@
+use crate::RbacSubject;
+
+/// Service-to-service subject: calling service with its roles and audiences (services it may call).
+///
+/// Only machine subjects get permissions of [machine-only patterns][crate::MACHINE_ONLY] (`"@machine Orders::Invoice::Generate"`),
+/// so human and service principals can share roles without humans being over-granted.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MachineSubject {
+    /// Name of calling service
+    pub service: String,
+    pub roles: Vec<String>,
+    /// Services subject may call, checked against [service audience][crate::RbacServiceBuilder#method.set_audience]
+    pub audiences: Vec<String>,
+}
+
+impl MachineSubject {
+    pub fn new(service: &str, roles: Vec<String>, audiences: Vec<String>) -> Self {
+        MachineSubject {
+            service: service.to_string(),
+            roles,
+            audiences,
+        }
+    }
+}
+
+impl RbacSubject for MachineSubject {
+    fn get_roles(&self) -> &Vec<String> {
+        &self.roles
+    }
+
+    fn name(&self) -> &str {
+        &self.service
+    }
+
+    fn is_machine(&self) -> bool {
+        true
+    }
+
+    fn audiences(&self) -> &[String] {
+        &self.audiences
+    }
+}
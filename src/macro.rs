@@ -1,5 +1,71 @@
+use crate::{RbacService, Subject};
+
 /// Macro for generating module permission set with 3-level hierarchy: Domain::Object::Permission
-/// 
+///
+/// Every object enum gets string constant per action (`Orders::Order::READ_STR == "Orders::Order::Read"`, `SEND_NOTIFICATION_STR` for `SendNotification`)
+/// and `const fn permission_str()`, so full permission strings are available without allocation.
+///
+/// Besides permission enums, domain module gets `Domain` marker type implementing [PermissionDomain][crate::PermissionDomain]
+/// (so `Domain` can't be used as object name), and constant of that type named after the domain.
+///
+/// Permission strings may follow naming conventions other than Rust ones: domain attribute `#[rbac(string_case = "kebab")]`
+/// (or `"snake"`, `"lower"`) converts every part of them (`OrderItem` to `order-item`), and object type attribute
+/// `#[rbac(rename = "line-item")]` sets object type part as is. Roles are matched against converted strings,
+/// as are strings parsed by [Permission::from_string][crate::Permission::from_string]:
+/// ```
+/// use rbacrab::{PermissionCore, define_permissions};
+///
+/// define_permissions! {
+///     #[rbac(string_case = "kebab")]
+///     pub domain LegacyOrders {
+///         OrderItem {
+///             Read => "View order items",
+///         },
+///         #[rbac(rename = "inv")]
+///         Invoice {
+///             SendReminder => "Send payment reminder",
+///         },
+///     }
+/// }
+///
+/// assert_eq!(LegacyOrders::OrderItem::READ_STR, "legacy-orders::order-item::read");
+/// assert_eq!(LegacyOrders::Invoice::SendReminder.full_name(), "legacy-orders::inv::send-reminder");
+/// ```
+///
+/// Renamed object type, which can't be part of permission string (empty or containing `:`, `*`, `{`, `}` or whitespace), fails to compile:
+/// ```compile_fail
+/// # use rbacrab::define_permissions;
+/// define_permissions! {
+///     pub domain Orders {
+///         #[rbac(rename = "line::item")]
+///         OrderItem {
+///             Read => "View order items",
+///         },
+///     }
+/// }
+/// ```
+///
+/// Actions may be deprecated with `#[deprecated(note = "use Update")]` (or `"use Orders::Order::Update"`). Variant stays, but its permission string
+/// is that of replacement action named by the note, so its checks are checks of replacement. Note naming no action of the object type fails
+/// to compile (notes of more words, like `"use Update instead"`, are left alone). Its own string is still parsed and, once object type is
+/// registered by [.register_permissions()][crate::RbacServiceBuilder#method.register_permissions], flagged as deprecated in
+/// [PermissionInfo][crate::PermissionInfo] and granted by role patterns naming it, so roles may be migrated gradually.
+/// Services without object type registered don't know the old string, so roles naming it grant nothing.
+///
+/// Deprecated in favor of action the object type doesn't have:
+/// ```compile_fail
+/// # use rbacrab::define_permissions;
+/// define_permissions! {
+///     pub domain Orders {
+///         Order {
+///             #[deprecated(note = "use Updat")]
+///             Edit => "Edit orders",
+///             Update => "Update orders",
+///         }
+///     }
+/// }
+/// ```
+///
 /// Example usage:
 /// ```
 /// use rbacrab::define_permissions;
@@ -32,101 +98,649 @@
 #[macro_export]
 macro_rules! define_permissions {
     (
-        $(#[$meta:meta])*
+        $(#[$($meta:tt)*])*
         $vis:vis domain $domain_mod:ident {
             $(
-                $(#[$obj_meta:meta])*
+                $(#[$($obj_meta:tt)*])*
                 $object_type:ident {
                     $(
-                        $(#[$action_meta:meta])*
+                        $(#[$($action_meta:tt)*])*
                         $action:ident => $description:literal
                     ),* $(,)?
                 }
             ),* $(,)?
         }
     ) => {
-        $(#[$meta])*
-        #[allow(non_snake_case)]
-        $vis mod $domain_mod {
-
-            // Object type enums
-            $(
-                $(#[$obj_meta])*
-                #[derive(Debug, Clone, PartialEq, Eq, Hash)]
-                pub enum $object_type {
-                    $(
-                        $(#[$action_meta])*
-                        $action,
-                    )*
-                }
+        $crate::__rbac_item! {
+            [$(#[$($meta)*])*] []
+            #[allow(non_snake_case, deprecated)]
+            $vis mod $domain_mod {
+                /// Case of permission strings set by `#[rbac(string_case = "...")]`
+                const __STRING_CASE: $crate::__private::StringCase = $crate::__rbac_case!($(#[$($meta)*])*);
+                /// Domain name in permission strings
+                const __DOMAIN: &str = $crate::__rbac_name!(stringify!($domain_mod), __STRING_CASE);
 
-                impl $object_type {
-                    pub fn description(&self) -> &'static str {
-                        match self {
-                            $(Self::$action => $description,)*
+                // Object type enums
+                $(
+                    $crate::__rbac_item! {
+                        [$(#[$($obj_meta)*])*] []
+                        #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+                        pub enum $object_type {
+                            $(
+                                $(#[$($action_meta)*])*
+                                $action,
+                            )*
                         }
                     }
 
-                    pub fn action(&self) -> &'static str {
-                        match self {
-                            $(Self::$action => stringify!($action),)*
+                    impl $object_type {
+                        /// Object type name in permission strings, renamed by `#[rbac(rename = "...")]`
+                        const __OBJECT: &'static str = match $crate::__rbac_rename!($(#[$($obj_meta)*])*) {
+                            Some(name) => $crate::__private::renamed(name),
+                            None => $crate::__rbac_name!(stringify!($object_type), __STRING_CASE),
+                        };
+
+                        $crate::__private::paste! {
+                            $(
+                                /// Permission string action is declared with, differs from permission string of deprecated action replaced by other one
+                                const [<__ $action:snake:upper _NAME>]: &'static str = $crate::__rbac_join!(
+                                    __DOMAIN,
+                                    $object_type::__OBJECT,
+                                    $crate::__rbac_name!(stringify!($action), __STRING_CASE)
+                                );
+
+                                #[doc = concat!("Permission string of `", stringify!($object_type), "::", stringify!($action), "`")]
+                                #[allow(unused)]
+                                pub const [<$action:snake:upper _STR>]: &'static str =
+                                    match $crate::__private::replacement($crate::__rbac_deprecated!($(#[$($action_meta)*])*), Self::__ACTIONS) {
+                                        Some(replacement) => replacement,
+                                        None => Self::[<__ $action:snake:upper _NAME>],
+                                    };
+                            )*
+
+                            /// Declared permission strings by action name, for finding replacements of deprecated actions
+                            const __ACTIONS: &'static [(&'static str, &'static str)] = &[
+                                $((stringify!($action), Self::[<__ $action:snake:upper _NAME>]),)*
+                            ];
+
+                            /// Full permission string, without allocation of [to_permission_string()][$crate::PermissionCore::to_permission_string]
+                            #[allow(unused)]
+                            pub const fn permission_str(&self) -> &'static str {
+                                match self {
+                                    $(Self::$action => Self::[<$action:snake:upper _STR>],)*
+                                }
+                            }
+                        }
+
+                        pub fn description(&self) -> &'static str {
+                            match self {
+                                $(Self::$action => $description,)*
+                            }
+                        }
+
+                        pub fn action(&self) -> &'static str {
+                            match self {
+                                $(Self::$action => $crate::__rbac_name!(stringify!($action), __STRING_CASE),)*
+                            }
+                        }
+
+                        #[allow(unused)]
+                        pub fn domain() -> &'static str {
+                            __DOMAIN
+                        }
+
+                        #[allow(unused)]
+                        pub fn object_type() -> &'static str {
+                            Self::__OBJECT
                         }
                     }
 
-                    #[allow(unused)]
-                    pub fn object_type() -> &'static str {
-                        stringify!($object_type)
+                    impl std::fmt::Display for $object_type {
+                        fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                            f.write_str(self.permission_str())
+                        }
                     }
-                }
 
-                impl std::fmt::Display for $object_type {
-                    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-                        write!(f, "{}::{}::{}", stringify!($domain_mod), stringify!($object_type), self.action())
+                    /// Parses full permission string (`"Orders::Order::Read"`), so permissions may be read from config or command line
+                    impl std::str::FromStr for $object_type {
+                        type Err = $crate::RbacError;
+
+                        fn from_str(s: &str) -> Result<Self, Self::Err> {
+                            <Self as $crate::Permission>::from_string(s).ok_or_else(|| $crate::RbacError::InvalidPermission(s.to_string()))
+                        }
                     }
-                }
 
-                impl $crate::Permission for $object_type {
-                    fn domain() -> &'static str {
-                        stringify!($domain_mod)
+                    impl AsRef<$object_type> for $object_type {
+                        fn as_ref(&self) -> &Self {
+                            self
+                        }
                     }
 
-                    fn object_type(&self) -> &'static str {
-                        stringify!($object_type)
+                    impl $crate::PermissionCore for $object_type {
+                        fn domain(&self) -> &'static str {
+                            __DOMAIN
+                        }
+
+                        fn object_type(&self) -> &'static str {
+                            Self::__OBJECT
+                        }
+
+                        fn action(&self) -> &'static str {
+                            self.action()
+                        }
+
+                        fn description(&self) -> &'static str {
+                            self.description()
+                        }
+
+                        fn full_name(&self) -> &'static str {
+                            self.permission_str()
+                        }
+
+                        fn deprecated(&self) -> Option<&'static str> {
+                            match self {
+                                $(Self::$action => $crate::__rbac_deprecated!($(#[$($action_meta)*])*),)*
+                            }
+                        }
                     }
 
-                    fn action(&self) -> &'static str {
-                        self.action()
+                    impl $crate::__private::PermissionList for $object_type {
+                        const PERMISSION_STRS: &'static [&'static str] = &[
+                            $($object_type::$action.permission_str(),)*
+                        ];
                     }
 
-                    fn from_string(s: &str) -> Option<Self> {
-                        let parts: Vec<&str> = s.split("::").collect();
-                        if parts.len() != 3 || parts[0] != stringify!($domain_mod) || parts[1] != stringify!($object_type) {
-                            return None;
+                    impl $crate::Permission for $object_type {
+                        fn from_string(s: &str) -> Option<Self> {
+                            $crate::__private::paste! {
+                                $(
+                                    if s == Self::[<__ $action:snake:upper _NAME>] {
+                                        return Some(Self::$action);
+                                    }
+                                )*
+                            }
+                            None
                         }
-                        
-                        match parts[2] {
-                            $(stringify!($action) => Some(Self::$action),)*
-                            _ => None,
+
+                        fn all_permissions() -> Vec<Self> {
+                            vec![$(Self::$action,)*]
                         }
                     }
+                )*
+
+                // Helper function to register all permissions from this domain
+                pub fn register_all(registry: &mut $crate::RbacServiceBuilder) {
+                    $(
+                        registry.register_permissions::<$object_type>();
+                    )*
+                }
+
+                /// Marker type of the domain
+                #[allow(unused)]
+                pub struct Domain;
+
+                impl $crate::PermissionDomain for Domain {
+                    fn name() -> &'static str {
+                        __DOMAIN
+                    }
 
-                    fn all_permissions() -> Vec<Self> {
-                        vec![$(Self::$action,)*]
+                    fn all() -> Vec<$crate::PermissionInfo> {
+                        let mut all = Vec::new();
+                        $(
+                            all.extend(
+                                <$object_type as $crate::Permission>::all_permissions()
+                                    .iter()
+                                    .map($crate::PermissionInfo::of),
+                            );
+                        )*
+                        all
                     }
 
-                    fn description(&self) -> &'static str {
-                        self.description()
+                    fn register(builder: &mut $crate::RbacServiceBuilder) {
+                        register_all(builder)
                     }
                 }
+            }
+        }
+
+        /// Domain marker value, so domain may be passed by its name (e.g. to [.register_domains()][$crate::RbacServiceBuilder#method.register_domains])
+        #[allow(unused, non_upper_case_globals)]
+        $vis const $domain_mod: $domain_mod::Domain = $domain_mod::Domain;
+    };
+}
+
+/// Emits item with its attributes, except `#[rbac(...)]` ones consumed by [define_permissions!]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __rbac_item {
+    ([#[rbac $($args:tt)*] $($rest:tt)*] [$($kept:tt)*] $($item:tt)*) => {
+        $crate::__rbac_item! { [$($rest)*] [$($kept)*] $($item)* }
+    };
+    ([#[$($attr:tt)*] $($rest:tt)*] [$($kept:tt)*] $($item:tt)*) => {
+        $crate::__rbac_item! { [$($rest)*] [$($kept)* #[$($attr)*]] $($item)* }
+    };
+    ([] [$($kept:tt)*] $($item:tt)*) => {
+        $($kept)* $($item)*
+    };
+}
+
+/// [StringCase] set by domain `#[rbac(string_case = "...")]` attribute
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __rbac_case {
+    () => {
+        $crate::__private::StringCase::Unchanged
+    };
+    (#[rbac(string_case = $case:literal)] $($rest:tt)*) => {
+        $crate::__private::StringCase::parse($case)
+    };
+    (#[rbac $($args:tt)*] $($rest:tt)*) => {
+        compile_error!("unsupported rbac attribute of domain, expected #[rbac(string_case = \"...\")]")
+    };
+    (#[$($attr:tt)*] $($rest:tt)*) => {
+        $crate::__rbac_case!($($rest)*)
+    };
+}
+
+/// Name set by object `#[rbac(rename = "...")]` attribute
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __rbac_rename {
+    () => {
+        None
+    };
+    (#[rbac(rename = $name:literal)] $($rest:tt)*) => {
+        Some($name)
+    };
+    (#[rbac $($args:tt)*] $($rest:tt)*) => {
+        compile_error!("unsupported rbac attribute of object type, expected #[rbac(rename = \"...\")]")
+    };
+    (#[$($attr:tt)*] $($rest:tt)*) => {
+        $crate::__rbac_rename!($($rest)*)
+    };
+}
+
+/// Note of action `#[deprecated]` attribute, `Some("")` if it has no note
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __rbac_deprecated {
+    () => {
+        None
+    };
+    (#[deprecated = $note:literal] $($rest:tt)*) => {
+        Some($note)
+    };
+    (#[deprecated($($args:tt)*)] $($rest:tt)*) => {
+        $crate::__rbac_deprecated!(@note $($args)*)
+    };
+    (#[deprecated] $($rest:tt)*) => {
+        Some("")
+    };
+    (#[$($attr:tt)*] $($rest:tt)*) => {
+        $crate::__rbac_deprecated!($($rest)*)
+    };
+    (@note) => {
+        Some("")
+    };
+    (@note note = $note:literal $($rest:tt)*) => {
+        Some($note)
+    };
+    (@note $key:ident = $value:literal $(, $($rest:tt)*)?) => {
+        $crate::__rbac_deprecated!(@note $($($rest)*)?)
+    };
+}
+
+/// Name converted to [StringCase] as `&'static str` constant
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __rbac_name {
+    ($name:expr, $case:expr) => {{
+        const LEN: usize = $crate::__private::write_cased($name, $case, &mut [], 0);
+        const BYTES: [u8; LEN] = $crate::__private::cased_bytes($name, $case);
+        const NAME: &str = $crate::__private::utf8(&BYTES);
+        NAME
+    }};
+}
+
+/// `Domain::Object::Action` permission string of its parts as `&'static str` constant
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __rbac_join {
+    ($domain:expr, $object_type:expr, $action:expr) => {{
+        const PARTS: [&str; 3] = [$domain, $object_type, $action];
+        const LEN: usize = $crate::__private::join_parts(PARTS, &mut []);
+        const BYTES: [u8; LEN] = $crate::__private::joined_bytes(PARTS);
+        const NAME: &str = $crate::__private::utf8(&BYTES);
+        NAME
+    }};
+}
+
+/// Case of permission string parts, set by `#[rbac(string_case = "...")]` attribute of [define_permissions!] domain
+#[doc(hidden)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StringCase {
+    /// Names as written
+    Unchanged,
+    /// `OrderItem` as `order-item`
+    Kebab,
+    /// `OrderItem` as `order_item`
+    Snake,
+    /// `OrderItem` as `orderitem`
+    Lower,
+}
+
+impl StringCase {
+    /// Fails compilation of unknown case, as it's evaluated in constant
+    pub const fn parse(case: &str) -> Self {
+        match case.as_bytes() {
+            b"kebab" => StringCase::Kebab,
+            b"snake" => StringCase::Snake,
+            b"lower" => StringCase::Lower,
+            _ => panic!("unknown rbac string_case, expected \"kebab\", \"snake\" or \"lower\""),
+        }
+    }
+}
+
+/// Object type name set by `#[rbac(rename = "...")]`. Fails compilation of name which can't be part of permission string, as it's evaluated in constant.
+#[doc(hidden)]
+pub const fn renamed(name: &'static str) -> &'static str {
+    let bytes = name.as_bytes();
+    if bytes.is_empty() {
+        panic!("rbac rename of object type is empty");
+    }
+    let mut i = 0;
+    while i < bytes.len() {
+        if matches!(bytes[i], b':' | b'*' | b'{' | b'}') || bytes[i].is_ascii_whitespace() {
+            panic!("rbac rename of object type can't contain ':', '*', '{{', '}}' or whitespace");
+        }
+        i += 1;
+    }
+    name
+}
+
+/// Writes `name` in `case` to `out` starting at `at` (as much of it as fits), returns position after it
+#[doc(hidden)]
+pub const fn write_cased(name: &str, case: StringCase, out: &mut [u8], mut at: usize) -> usize {
+    let separator = match case {
+        StringCase::Kebab => b'-',
+        _ => b'_',
+    };
+    let bytes = name.as_bytes();
+    let mut i = 0;
+    while i < bytes.len() {
+        let c = bytes[i];
+        let word_start = c.is_ascii_uppercase() && i > 0 && (bytes[i - 1].is_ascii_lowercase() || bytes[i - 1].is_ascii_digit());
+        let (separate, c) = match case {
+            StringCase::Unchanged => (false, c),
+            StringCase::Lower => (false, c.to_ascii_lowercase()),
+            StringCase::Kebab | StringCase::Snake if c == b'_' || c == b'-' => (false, separator),
+            StringCase::Kebab | StringCase::Snake => (word_start, c.to_ascii_lowercase()),
+        };
+        if separate {
+            if at < out.len() {
+                out[at] = separator;
+            }
+            at += 1;
+        }
+        if at < out.len() {
+            out[at] = c;
+        }
+        at += 1;
+        i += 1;
+    }
+    at
+}
+
+#[doc(hidden)]
+pub const fn cased_bytes<const N: usize>(name: &str, case: StringCase) -> [u8; N] {
+    let mut out = [0; N];
+    write_cased(name, case, &mut out, 0);
+    out
+}
+
+/// Writes parts joined with `::` to `out` (as much as fits), returns length of joined string
+#[doc(hidden)]
+pub const fn join_parts(parts: [&str; 3], out: &mut [u8]) -> usize {
+    let mut at = 0;
+    let mut i = 0;
+    while i < parts.len() {
+        if i > 0 {
+            at = write_cased("::", StringCase::Unchanged, out, at);
+        }
+        at = write_cased(parts[i], StringCase::Unchanged, out, at);
+        i += 1;
+    }
+    at
+}
+
+#[doc(hidden)]
+pub const fn joined_bytes<const N: usize>(parts: [&str; 3]) -> [u8; N] {
+    let mut out = [0; N];
+    join_parts(parts, &mut out);
+    out
+}
+
+/// Permission string of action deprecated with `note` naming its replacement (`"use Update"` or `"use Orders::Order::Update"`)
+/// among `actions` of the same object type. Fails compilation of note naming no such action, as it's evaluated in constant.
+#[doc(hidden)]
+pub const fn replacement(note: Option<&str>, actions: &[(&str, &'static str)]) -> Option<&'static str> {
+    let Some(note) = note else {
+        return None;
+    };
+    let Some((b"use ", name)) = note.as_bytes().split_at_checked(4) else {
+        return None;
+    };
+    let Ok(name) = std::str::from_utf8(name) else {
+        return None;
+    };
+    let mut i = 0;
+    while i < name.len() {
+        // Free-form note, not naming replacement
+        if name.as_bytes()[i] == b' ' {
+            return None;
+        }
+        i += 1;
+    }
+    let mut i = 0;
+    while i < actions.len() {
+        if str_eq(actions[i].0, name) || str_eq(actions[i].1, name) {
+            return Some(actions[i].1);
+        }
+        i += 1;
+    }
+    panic!("deprecated action note names no action of its object type, expected \"use <Action>\"")
+}
+
+#[doc(hidden)]
+pub const fn utf8(bytes: &'static [u8]) -> &'static str {
+    match std::str::from_utf8(bytes) {
+        Ok(name) => name,
+        Err(_) => panic!("permission name isn't valid UTF-8"),
+    }
+}
+
+/// Macro mapping every permission of listed object types to value (handler, route, label...), for building admin routers that must cover every permission.
+///
+/// Expands to `Vec<(&'static str, T)>` of full permission strings and mapped values.
+/// Fails to compile, if any action of object type used in the map isn't mapped, so actions added later can't be forgotten.
+///
+/// Example usage:
+/// ```
+/// use rbacrab::{define_permissions, map_permissions};
+///
+/// define_permissions! {
+///     pub domain Orders {
+///         Invoice {
+///             Read => "View invoices",
+///             Send => "Send invoices to customers",
+///         },
+///     }
+/// }
+///
+/// fn read_invoice() -> &'static str { "invoice" }
+/// fn send_invoice() -> &'static str { "sent" }
+///
+/// let handlers: Vec<(&str, fn() -> &'static str)> = map_permissions! {
+///     Orders::Invoice::Read => read_invoice,
+///     Orders::Invoice::Send => send_invoice,
+/// };
+/// assert_eq!(handlers[1].0, "Orders::Invoice::Send");
+/// ```
+///
+/// Map missing `Orders::Invoice::Send` fails to compile:
+/// ```compile_fail
+/// # use rbacrab::{define_permissions, map_permissions};
+/// # define_permissions! { pub domain Orders { Invoice { Read => "View invoices", Send => "Send invoices to customers" } } }
+/// let handlers: Vec<(&str, u8)> = map_permissions! {
+///     Orders::Invoice::Read => 1,
+/// };
+/// ```
+#[macro_export]
+macro_rules! map_permissions {
+    ($($permission:path => $value:expr),* $(,)?) => {{
+        const _: () = {
+            const MAPPED: &[&str] = &[$($permission.permission_str(),)*];
+            $(
+                // Panic message is the first unmapped permission of the object type
+                if let Some(unmapped) = $crate::__private::unmapped_permission(&$permission, MAPPED) {
+                    panic!("{}", unmapped);
+                }
             )*
+        };
+        vec![$(($crate::PermissionCore::full_name(&$permission), $value)),*]
+    }};
+}
 
-            // Helper function to register all permissions from this domain
-            pub fn register_all(registry: &mut $crate::RbacServiceBuilder) {
+/// Macro declaring policy unit tests as a table: roles, subjects and expected outcomes of their checks.
+///
+/// Expands to `#[cfg(test)]` module with `#[test]` function per case. Every case builds service of declared roles and checks
+/// permission (typed one or string) of declared subject; failed case reports check [explanation][crate::RbacService#method.explain],
+/// e.g. `policy test failed: expected bob to be allowed Orders::Order::Cancel, got deny Orders::Order::Cancel subject=bob roles=Viewer`.
+///
+/// Example usage:
+/// ```
+/// use rbacrab::rbac_tests;
+///
+/// rbac_tests! {
+///     mod order_policy {
+///         roles {
+///             "OrderManager" => ["Orders::*"],
+///             "Viewer" => ["Orders::Order::Read"],
+///         }
+///         subjects {
+///             alice => ["OrderManager"],
+///             bob => ["Viewer"],
+///         }
+///         tests {
+///             manager_cancels_orders: alice allow "Orders::Order::Cancel",
+///             viewer_reads_orders: bob allow "Orders::Order::Read",
+///             viewer_cant_cancel_orders: bob deny "Orders::Order::Cancel",
+///         }
+///     }
+/// }
+/// ```
+#[macro_export]
+macro_rules! rbac_tests {
+    (
+        $vis:vis mod $module:ident {
+            roles {
+                $($role:literal => [$($pattern:literal),* $(,)?]),* $(,)?
+            }
+            subjects {
+                $($subject:ident => [$($subject_role:literal),* $(,)?]),* $(,)?
+            }
+            tests {
+                $($test:ident: $test_subject:ident $outcome:ident $permission:expr),* $(,)?
+            }
+        }
+    ) => {
+        #[cfg(test)]
+        #[allow(unused_imports)]
+        $vis mod $module {
+            use super::*;
+
+            fn service() -> $crate::RbacService {
+                let mut builder = $crate::RbacService::builder();
                 $(
-                    registry.register_permissions::<$object_type>();
+                    builder.add_role($crate::Role::new($role, vec![$($pattern.to_string()),*]));
                 )*
+                builder.build()
+            }
+
+            fn subject(name: &str) -> $crate::Subject {
+                match name {
+                    $(stringify!($subject) => $crate::Subject::new(name, vec![$($subject_role.to_string()),*]),)*
+                    _ => unreachable!(),
+                }
             }
+
+            $(
+                #[test]
+                fn $test() {
+                    let allowed = $crate::rbac_tests!(@outcome $outcome);
+                    let permission = $permission.to_string();
+                    if let Err(message) = $crate::__private::check_policy(&service(), &subject(stringify!($test_subject)), &permission, allowed) {
+                        panic!("{}", message);
+                    }
+                }
+            )*
         }
     };
+    (@outcome allow) => { true };
+    (@outcome deny) => { false };
+}
+
+/// Checks single [rbac_tests!] case, returning failure message, if outcome isn't the expected one
+#[doc(hidden)]
+pub fn check_policy(service: &RbacService, subject: &Subject, permission: &str, allowed: bool) -> Result<(), String> {
+    let decision = service.explain_str(subject, permission).map_err(|e| format!("policy test failed: {}", e))?;
+    match decision.allowed == allowed {
+        true => Ok(()),
+        false => Err(format!(
+            "policy test failed: expected {} to be {} {}, got {}",
+            subject.name,
+            if allowed { "allowed" } else { "denied" },
+            permission,
+            decision
+        )),
+    }
 }
 
+/// All permission strings of object type, implemented by [define_permissions!] for [map_permissions!] exhaustiveness check
+#[doc(hidden)]
+pub trait PermissionList {
+    const PERMISSION_STRS: &'static [&'static str];
+}
+
+/// First permission of `permission` object type missing in `mapped`
+#[doc(hidden)]
+pub const fn unmapped_permission<T: PermissionList>(_permission: &T, mapped: &[&str]) -> Option<&'static str> {
+    let mut i = 0;
+    'permissions: while i < T::PERMISSION_STRS.len() {
+        let mut j = 0;
+        while j < mapped.len() {
+            if str_eq(T::PERMISSION_STRS[i], mapped[j]) {
+                i += 1;
+                continue 'permissions;
+            }
+            j += 1;
+        }
+        return Some(T::PERMISSION_STRS[i]);
+    }
+    None
+}
+
+const fn str_eq(a: &str, b: &str) -> bool {
+    let (a, b) = (a.as_bytes(), b.as_bytes());
+    if a.len() != b.len() {
+        return false;
+    }
+    let mut i = 0;
+    while i < a.len() {
+        if a[i] != b[i] {
+            return false;
+        }
+        i += 1;
+    }
+    true
+}
@@ -0,0 +1,127 @@
+use std::{collections::BTreeMap, mem::size_of, sync::Arc};
+
+use crate::{CompiledPermissions, Interner, NameSet, ObjectActions, PermissionInfo, Role, service::RoleMap};
+
+/// Approximate memory usage of [RbacService][crate::RbacService] returned by [.memory_stats()][crate::RbacService#method.memory_stats].
+///
+/// Numbers are estimated from collection capacities and string lengths (heap part plus inline size of stored values),
+/// so they are good enough for capacity planning, but won't match allocator statistics byte to byte.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MemoryStats {
+    /// Role map and role definitions (names and permission patterns)
+    pub roles_bytes: usize,
+    /// Compiled permission sets of all roles
+    pub compiled_bytes: usize,
+    /// Domain, object and action names shared by compiled permission sets
+    pub interned_bytes: usize,
+    /// Registered permissions list
+    pub registry_bytes: usize,
+    /// Per role breakdown
+    pub per_role: BTreeMap<String, RoleMemoryStats>,
+}
+
+/// Approximate memory usage of single role
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RoleMemoryStats {
+    /// Role name and permission patterns
+    pub definition_bytes: usize,
+    /// Compiled permission sets (without shared names, see [MemoryStats::interned_bytes])
+    pub compiled_bytes: usize,
+}
+
+impl MemoryStats {
+    pub(crate) fn collect(
+        roles: &RoleMap,
+        all_permissions: &BTreeMap<String, PermissionInfo>,
+        interner: &Interner,
+    ) -> Self {
+        let mut stats = MemoryStats {
+            // Persistent map has no capacity, count entries only
+            roles_bytes: roles.len() * (size_of::<String>() + size_of::<Role>()),
+            registry_bytes: all_permissions
+                .iter()
+                .map(|(name, info)| size_of::<String>() + size_of::<PermissionInfo>() + name.capacity() + info.heap_bytes())
+                .sum(),
+            interned_bytes: interner.heap_bytes(),
+            ..Default::default()
+        };
+
+        for (key, role) in roles {
+            let role_stats = RoleMemoryStats {
+                definition_bytes: key.capacity() + role.definition_heap_bytes(),
+                compiled_bytes: role.compiled_permissions.heap_bytes(),
+            };
+            stats.roles_bytes += role_stats.definition_bytes;
+            stats.compiled_bytes += role_stats.compiled_bytes;
+            stats.per_role.insert(role.name.clone(), role_stats);
+        }
+
+        stats
+    }
+
+    /// Sum of all tracked parts
+    pub fn total_bytes(&self) -> usize {
+        self.roles_bytes + self.compiled_bytes + self.interned_bytes + self.registry_bytes
+    }
+}
+
+impl RoleMemoryStats {
+    pub fn total_bytes(&self) -> usize {
+        self.definition_bytes + self.compiled_bytes
+    }
+}
+
+impl Role {
+    fn definition_heap_bytes(&self) -> usize {
+        self.name.capacity()
+            + self.permissions.capacity() * size_of::<String>()
+            + self.permissions.iter().map(String::capacity).sum::<usize>()
+    }
+}
+
+impl PermissionInfo {
+    fn heap_bytes(&self) -> usize {
+        self.domain.capacity()
+            + self.object_type.capacity()
+            + self.action.capacity()
+            + self.full_name.capacity()
+            + self.description.capacity()
+            + self.deprecated.as_ref().map_or(0, String::capacity)
+    }
+}
+
+impl CompiledPermissions {
+    fn heap_bytes(&self) -> usize {
+        let object_wildcards = table_bytes::<Arc<str>, NameSet>(self.object_wildcards.capacity())
+            + self.object_wildcards.values().map(set_bytes).sum::<usize>();
+
+        let exact_permissions = table_bytes::<Arc<str>, ObjectActions>(self.exact_permissions.capacity())
+            + self
+                .exact_permissions
+                .values()
+                .map(|objects| {
+                    table_bytes::<Arc<str>, NameSet>(objects.capacity())
+                        + objects.values().map(set_bytes).sum::<usize>()
+                })
+                .sum::<usize>();
+
+        let conditional = self.conditional.capacity() * size_of::<crate::ConditionalPattern>()
+            + self
+                .conditional
+                .iter()
+                .map(|conditional| conditional.pattern.capacity() + conditional.permissions.heap_bytes())
+                .sum::<usize>();
+
+        set_bytes(&self.domain_wildcards) + object_wildcards + exact_permissions + conditional
+    }
+}
+
+/// Hash table slots (key, value and control byte per slot), without heap parts of keys and values
+fn table_bytes<K, V>(capacity: usize) -> usize {
+    capacity * (size_of::<K>() + size_of::<V>() + 1)
+}
+
+/// Set of interned names, names themselves are counted once in [MemoryStats::interned_bytes]
+fn set_bytes(set: &NameSet) -> usize {
+    table_bytes::<Arc<str>, ()>(set.capacity())
+}
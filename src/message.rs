@@ -0,0 +1,21 @@
+use crate::{PermissionCore, RbacError, RbacService, RoleStorage};
+
+/// Conventional header carrying comma-separated roles of message sender
+pub const ROLES_HEADER: &str = "x-rbac-roles";
+
+/// Checks permission of RPC call or bus message (NATS, Kafka, ...), given roles from its headers as comma-separated list
+/// (e.g. `"OrderManager, Auditor"`). Empty list is checked against fallback roles, same as subject without roles.
+///
+/// See `examples/message_bus.rs` for adapter over message headers.
+pub fn authorize_message<R: RoleStorage, P: PermissionCore + ?Sized>(
+    rbac_service: &RbacService<R>,
+    headers_roles: &str,
+    required_permission: impl AsRef<P>,
+) -> Result<(), RbacError> {
+    let roles: Vec<&str> = headers_roles
+        .split(',')
+        .map(str::trim)
+        .filter(|role| !role.is_empty())
+        .collect();
+    rbac_service.has_permission_with_roles(&roles, required_permission)
+}
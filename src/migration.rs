@@ -0,0 +1,119 @@
+use std::{collections::BTreeMap, fmt};
+
+use crate::{CatalogDiff, Role};
+
+/// Rewrites permissions of stored roles after permissions were renamed or removed, so persisted roles keep working
+/// (applied to builder roles by [.migrate_roles()][crate::RbacServiceBuilder#method.migrate_roles]).
+///
+/// Rules apply to permissions named explicitly, including ones in action sets (`Orders::Invoice::{Read,Send}`).
+/// Wildcards are left as is.
+#[derive(Debug, Clone, Default)]
+pub struct RoleMigrator {
+    /// Old permission → new one, `None` for removed permission
+    rules: BTreeMap<String, Option<String>>,
+}
+
+/// Single permission rewritten by [RoleMigrator]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RoleRewrite {
+    pub role: String,
+    pub from: String,
+    /// New permission, `None` if permission was removed from role
+    pub to: Option<String>,
+}
+
+impl fmt::Display for RoleRewrite {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.to {
+            Some(to) => write!(f, "role {}: {} renamed to {}", self.role, self.from, to),
+            None => write!(f, "role {}: {} removed", self.role, self.from),
+        }
+    }
+}
+
+impl RoleMigrator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Migrator with rules for all renames and removals of [CatalogDiff]
+    pub fn from_diff(diff: &CatalogDiff) -> Self {
+        let mut migrator = Self::new();
+        for (from, to) in &diff.renamed {
+            migrator.rename(from, to);
+        }
+        for name in &diff.removed {
+            migrator.remove(name);
+        }
+        migrator
+    }
+
+    pub fn rename(&mut self, from: &str, to: &str) -> &mut Self {
+        self.rules.insert(from.to_string(), Some(to.to_string()));
+        self
+    }
+
+    pub fn remove(&mut self, permission: &str) -> &mut Self {
+        self.rules.insert(permission.to_string(), None);
+        self
+    }
+
+    /// Rewrites roles in place, returning every rewrite made
+    pub fn migrate(&self, roles: &mut [Role]) -> Vec<RoleRewrite> {
+        let mut rewrites = Vec::new();
+        for role in roles {
+            let (permissions, role_rewrites) = self.rewrite(role);
+            if !role_rewrites.is_empty() {
+                *role = Role {
+                    revision: role.revision,
+                    deny: role.deny,
+                    priority: role.priority,
+                    enabled: role.enabled,
+                    deleted_at: role.deleted_at,
+                    ..Role::new(&role.name, permissions)
+                }
+                .normalized();
+                rewrites.extend(role_rewrites);
+            }
+        }
+        rewrites
+    }
+
+    fn rewrite(&self, role: &Role) -> (Vec<String>, Vec<RoleRewrite>) {
+        let mut permissions = Vec::new();
+        let mut rewrites = Vec::new();
+        for pattern in &role.permissions {
+            // Condition and machine-only marker stay attached to every permission of the pattern
+            let (machine_only, permission, condition) = crate::split_pattern(pattern);
+            let marker = if machine_only { crate::MACHINE_ONLY } else { "" };
+            let condition = condition.map(|condition| format!(" if {}", condition)).unwrap_or_default();
+            let Some((prefix, actions)) = permission.rsplit_once("::") else {
+                permissions.push(pattern.clone());
+                continue;
+            };
+            let names: Vec<String> = match actions.strip_prefix('{').and_then(|a| a.strip_suffix('}')) {
+                Some(set) => set.split(',').map(|action| format!("{}::{}", prefix, action.trim())).collect(),
+                None => vec![permission.to_string()],
+            };
+            if !names.iter().any(|name| self.rules.contains_key(name)) {
+                permissions.push(pattern.clone());
+                continue;
+            }
+
+            for name in names {
+                match self.rules.get(&name) {
+                    Some(to) => {
+                        permissions.extend(to.as_ref().map(|to| format!("{}{}{}", marker, to, condition)));
+                        rewrites.push(RoleRewrite {
+                            role: role.name.clone(),
+                            from: name,
+                            to: to.clone(),
+                        });
+                    }
+                    None => permissions.push(format!("{}{}{}", marker, name, condition)),
+                }
+            }
+        }
+        (permissions, rewrites)
+    }
+}
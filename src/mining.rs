@@ -0,0 +1,95 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::{PermissionUsage, RbacDecision, RbacService, Role, RoleStorage, analysis::explicit_permissions};
+
+/// Role refactoring suggestions produced by [.mine_roles()][RbacService#method.mine_roles]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RoleMiningReport {
+    /// Permissions always allowed together, to the same subjects, sorted by permissions
+    pub candidate_roles: Vec<CandidateRole>,
+    /// Permissions granted by roles, but never allowed, sorted by role and permission
+    pub unused_grants: Vec<UnusedGrant>,
+}
+
+/// Group of permissions which may be granted by single role
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CandidateRole {
+    /// At least two permissions, sorted
+    pub permissions: Vec<String>,
+    /// Subjects using all of the permissions (and none of them without others), sorted
+    pub subjects: Vec<String>,
+}
+
+/// Permission granted by role, which may be removed from it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnusedGrant {
+    pub role: String,
+    pub permission: String,
+}
+
+impl<R: RoleStorage> RbacService<R> {
+    /// Suggests role refactorings from recorded decisions (e.g. collected by [AuditSink][crate::AuditSink]) and [usage stats][RbacService#method.usage_stats]:
+    /// permissions always allowed to the same subjects are candidate roles, and permissions granted by roles but never allowed are removal candidates.
+    ///
+    /// Grant is used, if any allowed decision was matched by the role, or usage stats counted permission allowed.
+    /// Only registered permissions and ones explicitly named in role patterns are reported as unused, conditional grants aren't.
+    pub fn mine_roles(&self, decisions: &[RbacDecision], usage: &BTreeMap<String, PermissionUsage>) -> RoleMiningReport {
+        let allowed: Vec<&RbacDecision> = decisions.iter().filter(|decision| decision.allowed).collect();
+
+        let mut subjects_by_permission: BTreeMap<&str, BTreeSet<&str>> = BTreeMap::new();
+        for decision in &allowed {
+            if let Some(subject) = &decision.subject {
+                subjects_by_permission.entry(&decision.permission).or_default().insert(subject);
+            }
+        }
+        let mut groups: BTreeMap<BTreeSet<&str>, Vec<String>> = BTreeMap::new();
+        for (permission, subjects) in subjects_by_permission {
+            groups.entry(subjects).or_default().push(permission.to_string());
+        }
+        let mut candidate_roles: Vec<CandidateRole> = groups
+            .into_iter()
+            .filter(|(_, permissions)| permissions.len() > 1)
+            .map(|(subjects, permissions)| CandidateRole {
+                permissions,
+                subjects: subjects.into_iter().map(str::to_string).collect(),
+            })
+            .collect();
+        candidate_roles.sort_by(|a, b| a.permissions.cmp(&b.permissions));
+
+        let used: BTreeSet<(&str, &str)> = allowed
+            .iter()
+            .filter_map(|decision| Some((decision.matched_role.as_deref()?, decision.permission.as_str())))
+            .collect();
+        let mut roles = self.get_roles();
+        roles.retain(|role| role.is_active() && !role.deny);
+        roles.sort_by(|a, b| a.name.cmp(&b.name));
+
+        let mut unused_grants = Vec::new();
+        for role in &roles {
+            for (domain, object_type, action) in granted_permissions(self, role) {
+                let permission = format!("{}::{}::{}", domain, object_type, action);
+                let used_by_role = used.contains(&(role.name.as_str(), permission.as_str()));
+                if !used_by_role && usage.get(&permission).is_none_or(|usage| usage.allowed == 0) {
+                    unused_grants.push(UnusedGrant {
+                        role: role.name.clone(),
+                        permission,
+                    });
+                }
+            }
+        }
+
+        RoleMiningReport { candidate_roles, unused_grants }
+    }
+}
+
+/// Registered and explicitly named permissions role grants unconditionally
+fn granted_permissions<R: RoleStorage>(rbac_service: &RbacService<R>, role: &Role) -> BTreeSet<(String, String, String)> {
+    let mut candidates: BTreeSet<(String, String, String)> = rbac_service
+        .get_all_permissions()
+        .into_iter()
+        .map(|info| (info.domain, info.object_type, info.action))
+        .collect();
+    candidates.extend(explicit_permissions(role));
+    candidates.retain(|(domain, object_type, action)| role.compiled_permissions.matches(domain, object_type, action));
+    candidates
+}
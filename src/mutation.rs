@@ -0,0 +1,164 @@
+use std::fmt;
+
+use crate::{CompiledPermissions, MACHINE_ONLY, RbacService, RbacSnapshot, Role, RoleStorage, Subject, r#macro::check_policy, split_pattern};
+
+/// Expected outcome of single check, see [.check_mutations()][RbacService#method.check_mutations]
+#[derive(Debug, Clone, PartialEq)]
+pub struct PolicyExpectation {
+    pub subject: Subject,
+    /// Permission string, parsed like by [.has_permission_str()][RbacService#method.has_permission_str]
+    pub permission: String,
+    pub allowed: bool,
+}
+
+impl PolicyExpectation {
+    pub fn allow(subject: &Subject, permission: &str) -> Self {
+        PolicyExpectation {
+            subject: subject.clone(),
+            permission: permission.to_string(),
+            allowed: true,
+        }
+    }
+
+    pub fn deny(subject: &Subject, permission: &str) -> Self {
+        PolicyExpectation {
+            allowed: false,
+            ..Self::allow(subject, permission)
+        }
+    }
+}
+
+/// Single change of role patterns made by [.check_mutations()][RbacService#method.check_mutations]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PolicyMutation {
+    /// Pattern removed from role
+    DropPattern { role: String, pattern: String },
+    /// Pattern replaced with one level wider wildcard: `Orders::Order::Read` with `Orders::Order::*`, `Orders::Order::*` with `Orders::*` etc.
+    WidenPattern { role: String, pattern: String, widened: String },
+}
+
+impl fmt::Display for PolicyMutation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::DropPattern { role, pattern } => write!(f, "drop {} from role {}", pattern, role),
+            Self::WidenPattern { role, pattern, widened } => write!(f, "widen {} to {} in role {}", pattern, widened, role),
+        }
+    }
+}
+
+/// Result of [.check_mutations()][RbacService#method.check_mutations]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MutationReport {
+    /// Number of mutations tried
+    pub mutations: usize,
+    /// Mutations no expectation caught, i.e. parts of policy nothing tests
+    pub survived: Vec<PolicyMutation>,
+    /// Failures of expectations on unmutated policy, such expectations don't catch mutations
+    pub baseline_failures: Vec<String>,
+}
+
+impl MutationReport {
+    /// Share of mutations caught by expectations, `1.0` if there were no mutations
+    pub fn score(&self) -> f64 {
+        match self.mutations {
+            0 => 1.0,
+            mutations => (mutations - self.survived.len()) as f64 / mutations as f64,
+        }
+    }
+}
+
+/// Pattern with its wildcard widened by one level, `None` for `*`
+fn widen(pattern: &str) -> Option<String> {
+    let (machine_only, core, condition) = split_pattern(pattern);
+    let parts: Vec<&str> = core.split("::").collect();
+    let widened = match parts[..] {
+        [_, "*"] => "*".to_string(),
+        [domain, _, "*"] => format!("{}::*", domain),
+        [domain, object_type, _] => format!("{}::{}::*", domain, object_type),
+        _ => return None,
+    };
+    let mut widened = match machine_only {
+        true => format!("{}{}", MACHINE_ONLY, widened),
+        false => widened,
+    };
+    if let Some(condition) = condition {
+        widened.push_str(" if ");
+        widened.push_str(condition);
+    }
+    Some(widened)
+}
+
+impl<R: RoleStorage> RbacService<R> {
+    /// Mutation testing of policy: drops every role pattern and widens every pattern wildcard one by one, checking `expectations`
+    /// against each mutated policy. Mutations none of expectations (passing on unmutated policy) fails on are reported as survived,
+    /// pointing at untested parts of policy.
+    ///
+    /// Mutated policies are built from roles, fallback roles and registered permissions only, so resolver, feature gates
+    /// and quotas don't apply to them. Checks don't count as checks of this service.
+    pub fn check_mutations(&self, expectations: &[PolicyExpectation]) -> MutationReport {
+        let snapshot = self.snapshot();
+        let build = |snapshot: RbacSnapshot| {
+            let mut builder = RbacService::builder();
+            builder.all_permissions = self.get_all_permissions().into_iter().map(|info| (info.full_name.clone(), info)).collect();
+            builder.load_snapshot(snapshot);
+            builder.build()
+        };
+
+        let baseline = build(snapshot.clone());
+        let mut report = MutationReport::default();
+        let mut passing = Vec::new();
+        for expectation in expectations {
+            match check_policy(&baseline, &expectation.subject, &expectation.permission, expectation.allowed) {
+                Ok(()) => passing.push(expectation),
+                Err(message) => report.baseline_failures.push(message),
+            }
+        }
+
+        for (i, role) in snapshot.roles.iter().enumerate() {
+            for (j, pattern) in role.permissions.iter().enumerate() {
+                let mut mutations = vec![(
+                    PolicyMutation::DropPattern {
+                        role: role.name.clone(),
+                        pattern: pattern.clone(),
+                    },
+                    None,
+                )];
+                if let Some(widened) = widen(pattern) {
+                    mutations.push((
+                        PolicyMutation::WidenPattern {
+                            role: role.name.clone(),
+                            pattern: pattern.clone(),
+                            widened: widened.clone(),
+                        },
+                        Some(widened),
+                    ));
+                }
+
+                for (mutation, replacement) in mutations {
+                    let mut mutated = snapshot.clone();
+                    let mut permissions = role.permissions.clone();
+                    match replacement {
+                        Some(widened) => permissions[j] = widened,
+                        None => {
+                            permissions.remove(j);
+                        }
+                    }
+                    mutated.roles[i] = Role {
+                        compiled_permissions: CompiledPermissions::compile(&permissions),
+                        permissions,
+                        ..role.clone()
+                    };
+                    let mutant = build(mutated);
+                    report.mutations += 1;
+                    let caught = passing
+                        .iter()
+                        .any(|expectation| check_policy(&mutant, &expectation.subject, &expectation.permission, expectation.allowed).is_err());
+                    if !caught {
+                        report.survived.push(mutation);
+                    }
+                }
+            }
+        }
+        report
+    }
+}
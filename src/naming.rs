@@ -0,0 +1,96 @@
+use crate::RbacError;
+
+/// Separator of namespace and role name (`"billing:Admin"`)
+pub const ROLE_NAMESPACE_SEPARATOR: char = ':';
+
+/// Role name in namespace (e.g. of the team owning the role): `namespaced_role_name("billing", "Admin") == "billing:Admin"`
+pub fn namespaced_role_name(namespace: &str, name: &str) -> String {
+    format!("{namespace}{ROLE_NAMESPACE_SEPARATOR}{name}")
+}
+
+/// Splits role name into namespace and name within it, namespace is `None` for role names without namespace
+pub fn split_role_name(role_name: &str) -> (Option<&str>, &str) {
+    match role_name.split_once(ROLE_NAMESPACE_SEPARATOR) {
+        Some((namespace, name)) => (Some(namespace), name),
+        None => (None, role_name),
+    }
+}
+
+/// Rules role names must follow, set by [.set_role_name_rules()][crate::RbacServiceBuilder#method.set_role_name_rules].
+///
+/// Rules are enforced by [.try_add_role()][crate::RbacServiceBuilder#method.try_add_role] of builder and by updaters,
+/// while [builder.add_role()][crate::RbacServiceBuilder#method.add_role] adds roles unchecked, so system roles may still be bootstrapped.
+/// By default names consist of ASCII alphanumerics and `_-.:`, up to 64 characters.
+#[derive(Debug, Clone)]
+pub struct RoleNameRules {
+    extra_chars: String,
+    max_len: usize,
+    reserved_prefixes: Vec<String>,
+    require_namespace: bool,
+}
+
+impl Default for RoleNameRules {
+    fn default() -> Self {
+        Self {
+            extra_chars: "_-.:".to_string(),
+            max_len: 64,
+            reserved_prefixes: Vec::new(),
+            require_namespace: false,
+        }
+    }
+}
+
+impl RoleNameRules {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets characters allowed in role names besides ASCII alphanumerics
+    pub fn allow_chars(&mut self, chars: &str) -> &mut Self {
+        self.extra_chars = chars.to_string();
+        self
+    }
+
+    pub fn set_max_len(&mut self, max_len: usize) -> &mut Self {
+        self.max_len = max_len;
+        self
+    }
+
+    /// Reserves prefix (e.g. `"system:"`), so roles starting with it can't be added at runtime
+    pub fn reserve_prefix(&mut self, prefix: &str) -> &mut Self {
+        self.reserved_prefixes.push(prefix.to_string());
+        self
+    }
+
+    /// Requires every role name to be [namespaced][namespaced_role_name], so roles of different teams can't collide
+    pub fn set_require_namespace(&mut self, require: bool) -> &mut Self {
+        self.require_namespace = require;
+        self
+    }
+
+    /// Fails with [RbacError::InvalidRoleName], if role name breaks any rule
+    pub fn validate(&self, role_name: &str) -> Result<(), RbacError> {
+        let invalid = |reason: String| {
+            Err(RbacError::InvalidRoleName {
+                role: role_name.to_string(),
+                reason,
+            })
+        };
+        if role_name.is_empty() {
+            return invalid("name is empty".to_string());
+        }
+        if role_name.chars().count() > self.max_len {
+            return invalid(format!("name is longer than {} characters", self.max_len));
+        }
+        if let Some(c) = role_name.chars().find(|c| !c.is_ascii_alphanumeric() && !self.extra_chars.contains(*c)) {
+            return invalid(format!("character {c:?} isn't allowed"));
+        }
+        if let Some(prefix) = self.reserved_prefixes.iter().find(|prefix| role_name.starts_with(prefix.as_str())) {
+            return invalid(format!("prefix {prefix:?} is reserved"));
+        }
+        if self.require_namespace && !matches!(split_role_name(role_name), (Some(namespace), name) if !namespace.is_empty() && !name.is_empty()) {
+            return invalid(format!("name must be in namespace (namespace{ROLE_NAMESPACE_SEPARATOR}name)"));
+        }
+        Ok(())
+    }
+}
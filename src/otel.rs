@@ -0,0 +1,59 @@
+use opentelemetry::{
+    Array, KeyValue, StringValue, Value,
+    trace::{SpanRef, get_active_span},
+};
+
+use crate::service::PermissionKey;
+
+/// Runs `f` with current span, unless it isn't recorded (then attributes aren't even built)
+fn with_recording_span(f: impl FnOnce(&SpanRef)) {
+    get_active_span(|span| {
+        if span.is_recording() {
+            f(&span)
+        }
+    })
+}
+
+/// Adds `rbac.check` event with check decision to current span
+pub(crate) fn record_check<T: AsRef<str>>(permission: PermissionKey, roles: &[T], allowed: bool, fallback_used: bool) {
+    with_recording_span(|span| {
+        let roles: Vec<StringValue> = roles.iter().map(|role| role.as_ref().to_string().into()).collect();
+        span.add_event(
+            "rbac.check",
+            vec![
+                KeyValue::new("rbac.permission", permission.to_string()),
+                KeyValue::new("rbac.roles", Value::Array(Array::String(roles))),
+                KeyValue::new("rbac.allowed", allowed),
+                KeyValue::new("rbac.fallback_used", fallback_used),
+            ],
+        );
+    })
+}
+
+/// Adds `rbac.deprecated_permission` event with check of deprecated permission to current span
+pub(crate) fn record_deprecated(warning: &crate::DeprecationWarning) {
+    with_recording_span(|span| {
+        span.add_event(
+            "rbac.deprecated_permission",
+            vec![
+                KeyValue::new("rbac.permission", warning.permission.clone()),
+                KeyValue::new("rbac.deprecation_note", warning.note.clone()),
+                KeyValue::new("code.filepath", warning.caller.file()),
+                KeyValue::new("code.lineno", warning.caller.line() as i64),
+            ],
+        );
+    })
+}
+
+/// Adds `rbac.roles_swap` event with new roles generation to current span
+pub(crate) fn record_swap(generation: u64, roles: usize) {
+    with_recording_span(|span| {
+        span.add_event(
+            "rbac.roles_swap",
+            vec![
+                KeyValue::new("rbac.generation", generation as i64),
+                KeyValue::new("rbac.role_count", roles as i64),
+            ],
+        );
+    })
+}
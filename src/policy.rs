@@ -0,0 +1,126 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{RbacService, Role, RoleStorage};
+
+/// 64-bit FNV-1a, which (unlike `std` hashers) is guaranteed to give the same result on every build and platform
+struct Fnv64(u64);
+
+impl Fnv64 {
+    fn new() -> Self {
+        Fnv64(0xcbf2_9ce4_8422_2325)
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= *byte as u64;
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+
+    /// Writes length-prefixed string, so adjacent fields can't run into each other
+    fn write_str(&mut self, s: &str) {
+        self.write(&(s.len() as u64).to_le_bytes());
+        self.write(s.as_bytes());
+    }
+}
+
+/// Hash of permission names in given order, see [PermissionCatalog::hash][crate::PermissionCatalog::hash]
+pub(crate) fn names_hash<'a>(names: impl ExactSizeIterator<Item = &'a str>) -> u64 {
+    let mut hasher = Fnv64::new();
+    hasher.write(&(names.len() as u64).to_le_bytes());
+    for name in names {
+        hasher.write_str(name);
+    }
+    hasher.0
+}
+
+/// [Policy hash][RbacService#method.policy_hash] of given roles
+pub(crate) fn roles_hash(mut roles: Vec<Role>) -> u64 {
+    roles.sort_by(|a, b| a.name.cmp(&b.name));
+
+    let mut hasher = Fnv64::new();
+    hasher.write(&(roles.len() as u64).to_le_bytes());
+    for role in roles {
+        hasher.write_str(&role.name);
+        hasher.write(&[role.deny as u8, role.enabled as u8, role.deleted_at.is_some() as u8]);
+        hasher.write(&role.priority.to_le_bytes());
+        let patterns = role.compiled_permissions.to_patterns();
+        hasher.write(&(patterns.len() as u64).to_le_bytes());
+        for pattern in patterns {
+            hasher.write_str(&pattern);
+        }
+    }
+    hasher.0
+}
+
+impl<R: RoleStorage> RbacService<R> {
+    /// Hash of effective policy: roles in normalized form (see [Role::normalized][crate::Role::normalized]), sorted by name.
+    ///
+    /// Doesn't depend on role order, pattern spelling or revisions, and is stable across builds and platforms,
+    /// so instances (or edge caches) may compare it to verify they enforce the same policy.
+    pub fn policy_hash(&self) -> u64 {
+        roles_hash(self.get_roles())
+    }
+
+    /// Current [PolicyStatus] of this instance
+    pub fn policy_status(&self, instance: &str) -> PolicyStatus {
+        let (roles, generation) = self.with_policy(|policy| (policy.roles.values().cloned().collect(), policy.generation));
+        PolicyStatus {
+            instance: instance.to_string(),
+            policy_hash: roles_hash(roles),
+            generation,
+        }
+    }
+}
+
+/// Policy version served by an instance, returned by [.policy_status()][RbacService#method.policy_status].
+///
+/// Instances publish it (e.g. on health endpoint), so operator tools may collect statuses of the fleet and compare them with [PolicyStatus::agreement].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PolicyStatus {
+    /// Instance identifier given by caller (host name, pod name, ...)
+    pub instance: String,
+    pub policy_hash: u64,
+    /// Number of role swaps since instance start, see [.generation()][RbacService#method.generation]
+    pub generation: u64,
+}
+
+/// Result of comparing statuses of fleet members by [PolicyStatus::agreement]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PolicyAgreement {
+    /// Hash served by most instances, `None` for empty fleet
+    pub policy_hash: Option<u64>,
+    /// Instances serving other policies
+    pub stale: Vec<PolicyStatus>,
+}
+
+impl PolicyAgreement {
+    pub fn is_agreed(&self) -> bool {
+        self.stale.is_empty()
+    }
+}
+
+impl PolicyStatus {
+    /// Finds policy served by most instances and reports the rest as stale.
+    /// On tie the policy of instance with higher generation wins, as it has applied more updates.
+    pub fn agreement(statuses: &[PolicyStatus]) -> PolicyAgreement {
+        let mut votes: BTreeMap<u64, (usize, u64)> = BTreeMap::new();
+        for status in statuses {
+            let (count, generation) = votes.entry(status.policy_hash).or_default();
+            *count += 1;
+            *generation = (*generation).max(status.generation);
+        }
+        let policy_hash = votes.into_iter().max_by_key(|(_, vote)| *vote).map(|(hash, _)| hash);
+
+        PolicyAgreement {
+            policy_hash,
+            stale: statuses
+                .iter()
+                .filter(|status| Some(status.policy_hash) != policy_hash)
+                .cloned()
+                .collect(),
+        }
+    }
+}
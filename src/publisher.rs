@@ -0,0 +1,131 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde_json::{Value, json};
+
+use crate::{AuditSink, DecisionDto, RbacDecision, RoleChangeEvent, RoleChangeKind, RoleChangeSink};
+
+/// Decision as JSON event: [DecisionDto] fields plus `"event": "decision"`
+pub(crate) fn decision_event(decision: &RbacDecision) -> Value {
+    let mut event = json!(DecisionDto::from(decision));
+    event["event"] = json!("decision");
+    event
+}
+
+/// Role change as JSON event with `"event": "role_change"`, lowercase kind and roles before and after change
+pub(crate) fn role_change_event(event: &RoleChangeEvent) -> Value {
+    json!({
+        "event": "role_change",
+        "kind": kind_name(event.kind),
+        "role": event.role,
+        "before": event.before,
+        "after": event.after,
+        "actor": event.actor,
+        "timestamp_ms": SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_millis() as u64,
+    })
+}
+
+fn kind_name(kind: RoleChangeKind) -> &'static str {
+    match kind {
+        RoleChangeKind::Added => "added",
+        RoleChangeKind::Updated => "updated",
+        RoleChangeKind::Removed => "removed",
+    }
+}
+
+/// Message bus client used by [PublisherSink], implemented for Kafka producer, NATS client etc.
+///
+/// Called synchronously on every check, so implementation should only enqueue message (as producers of most buses do)
+/// and report delivery failures on its own.
+pub trait EventPublisher: Send + Sync {
+    fn publish(&self, subject: &str, payload: Vec<u8>);
+}
+
+/// Audit and role change sink streaming events as JSON messages to message bus through [EventPublisher].
+///
+/// Decisions are published to `<prefix>.decision.allowed` or `<prefix>.decision.denied`, role changes to
+/// `<prefix>.role.added`, `<prefix>.role.updated` or `<prefix>.role.removed`, so consumers may subscribe to part of them.
+/// Payloads are the same as of `WebhookSink` events.
+pub struct PublisherSink<P> {
+    publisher: P,
+    prefix: String,
+}
+
+impl<P: EventPublisher> PublisherSink<P> {
+    pub fn new(publisher: P, prefix: &str) -> Self {
+        PublisherSink {
+            publisher,
+            prefix: prefix.to_string(),
+        }
+    }
+
+    pub fn publisher(&self) -> &P {
+        &self.publisher
+    }
+}
+
+impl<P: EventPublisher> AuditSink for PublisherSink<P> {
+    fn on_decision(&self, decision: &RbacDecision) {
+        let outcome = if decision.allowed { "allowed" } else { "denied" };
+        let subject = format!("{}.decision.{}", self.prefix, outcome);
+        self.publisher.publish(&subject, decision_event(decision).to_string().into_bytes());
+    }
+}
+
+impl<P: EventPublisher> RoleChangeSink for PublisherSink<P> {
+    fn on_role_change(&self, event: &RoleChangeEvent) {
+        let subject = format!("{}.role.{}", self.prefix, kind_name(event.kind));
+        self.publisher.publish(&subject, role_change_event(event).to_string().into_bytes());
+    }
+}
+
+/// [EventPublisher] publishing to [NATS](https://nats.io) with `async_nats` client (`nats` feature):
+/// `PublisherSink::new(NatsPublisher::new(client), "rbac")`.
+///
+/// Messages are queued without blocking checks and published one by one by single background task, spawned on runtime publisher was created in.
+/// When queue is full, new messages are dropped. Dropped messages and failures are counted by [.failed()][NatsPublisher#method.failed].
+/// Background task stops after publishing queued messages, once publisher is dropped.
+#[cfg(feature = "nats")]
+pub struct NatsPublisher {
+    sender: tokio::sync::mpsc::Sender<(String, Vec<u8>)>,
+    failed: std::sync::Arc<std::sync::atomic::AtomicU64>,
+}
+
+#[cfg(feature = "nats")]
+impl NatsPublisher {
+    /// Default maximum number of queued messages
+    pub const QUEUE_CAPACITY: usize = 10_000;
+
+    /// Must be called within tokio runtime
+    pub fn new(client: async_nats::Client) -> Self {
+        Self::with_capacity(client, Self::QUEUE_CAPACITY)
+    }
+
+    /// Like [NatsPublisher::new], but with given maximum number of queued messages
+    pub fn with_capacity(client: async_nats::Client, capacity: usize) -> Self {
+        let (sender, mut receiver) = tokio::sync::mpsc::channel::<(String, Vec<u8>)>(capacity.max(1));
+        let failed = std::sync::Arc::new(std::sync::atomic::AtomicU64::new(0));
+        let task_failed = failed.clone();
+        tokio::spawn(async move {
+            while let Some((subject, payload)) = receiver.recv().await {
+                if client.publish(subject, payload.into()).await.is_err() {
+                    task_failed.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+                }
+            }
+        });
+        NatsPublisher { sender, failed }
+    }
+
+    /// Number of messages dropped because queue was full or client failed to publish them
+    pub fn failed(&self) -> u64 {
+        self.failed.load(std::sync::atomic::Ordering::Relaxed)
+    }
+}
+
+#[cfg(feature = "nats")]
+impl EventPublisher for NatsPublisher {
+    fn publish(&self, subject: &str, payload: Vec<u8>) {
+        if self.sender.try_send((subject.to_string(), payload)).is_err() {
+            self.failed.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        }
+    }
+}
@@ -0,0 +1,116 @@
+use std::{
+    collections::HashMap,
+    sync::{Mutex, PoisonError},
+    time::{Duration, Instant},
+};
+
+use crate::RbacError;
+
+/// Limit of allowed checks of permission per subject within fixed time window, set by [.add_quota()][crate::RbacServiceBuilder#method.add_quota].
+///
+/// Exceeding checks fail with [RbacError::QuotaExceeded], even though subject roles grant permission.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Quota {
+    pub limit: u64,
+    pub window: Duration,
+}
+
+impl Quota {
+    pub fn new(limit: u64, window: Duration) -> Self {
+        Quota { limit, window }
+    }
+
+    /// Parses quota from config form, `"10/day"` (`second`, `minute`, `hour` and `day` windows are supported)
+    pub fn parse(quota: &str) -> Result<Quota, RbacError> {
+        let invalid = || RbacError::InvalidQuota(quota.to_string());
+        let (limit, window) = quota.split_once('/').ok_or_else(invalid)?;
+        let window = match window.trim() {
+            "second" => Duration::from_secs(1),
+            "minute" => Duration::from_secs(60),
+            "hour" => Duration::from_secs(60 * 60),
+            "day" => Duration::from_secs(24 * 60 * 60),
+            _ => return Err(invalid()),
+        };
+        Ok(Quota {
+            limit: limit.trim().parse().map_err(|_| invalid())?,
+            window,
+        })
+    }
+}
+
+/// Storage of quota counters, so they may be shared by instances (e.g. kept in Redis).
+/// [MemoryQuotaStore] is used, unless other store is set by [.set_quota_store()][crate::RbacServiceBuilder#method.set_quota_store].
+pub trait QuotaStore: Send + Sync {
+    /// Counts use of permission by subject, returning number of uses within current window including this one
+    fn increment(&self, subject: &str, permission: &str, window: Duration) -> u64;
+
+    /// Number of uses of permission by subject within current window, without counting new one.
+    /// Looked up by checks, which don't consume quota (e.g. [.explain()][crate::RbacService#method.explain]), stores unable to tell may return `0`.
+    fn uses(&self, _subject: &str, _permission: &str, _window: Duration) -> u64 {
+        0
+    }
+}
+
+/// Process-local quota counters. Counters of expired windows are evicted as new ones are added, so they don't pile up with subjects.
+#[derive(Debug, Default)]
+pub struct MemoryQuotaStore {
+    counters: Mutex<Counters>,
+}
+
+#[derive(Debug, Default)]
+struct Counters {
+    /// (subject, permission) → (window start, window, uses)
+    windows: HashMap<(String, String), (Instant, Duration, u64)>,
+    /// Number of counters, at which expired ones are evicted next time
+    evict_at: usize,
+}
+
+/// Least number of counters store evicts expired ones at
+const EVICT_AT_LEAST: usize = 1024;
+
+impl Counters {
+    fn evict_expired(&mut self, now: Instant) {
+        self.windows.retain(|_, (start, window, _)| now.duration_since(*start) < *window);
+        self.evict_at = (self.windows.len() * 2).max(EVICT_AT_LEAST);
+    }
+}
+
+impl QuotaStore for MemoryQuotaStore {
+    fn increment(&self, subject: &str, permission: &str, window: Duration) -> u64 {
+        let now = Instant::now();
+        let mut counters = self.counters.lock().unwrap_or_else(PoisonError::into_inner);
+        if counters.windows.len() >= counters.evict_at {
+            counters.evict_expired(now);
+        }
+        let (start, current, uses) = counters
+            .windows
+            .entry((subject.to_string(), permission.to_string()))
+            .or_insert((now, window, 0));
+        if now.duration_since(*start) >= *current {
+            *start = now;
+            *uses = 0;
+        }
+        *current = window;
+        *uses += 1;
+        *uses
+    }
+
+    fn uses(&self, subject: &str, permission: &str, window: Duration) -> u64 {
+        let counters = self.counters.lock().unwrap_or_else(PoisonError::into_inner);
+        match counters.windows.get(&(subject.to_string(), permission.to_string())) {
+            Some((start, _, uses)) if start.elapsed() < window => *uses,
+            _ => 0,
+        }
+    }
+}
+
+impl MemoryQuotaStore {
+    /// Number of counters kept, including ones of expired windows not evicted yet
+    pub fn len(&self) -> usize {
+        self.counters.lock().unwrap_or_else(PoisonError::into_inner).windows.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
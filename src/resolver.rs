@@ -0,0 +1,76 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex, PoisonError},
+};
+
+use crate::{Interner, Role};
+
+/// Source of roles unknown to the service, registered by [.set_role_resolver()][crate::RbacServiceBuilder#method.set_role_resolver].
+///
+/// Called when subject references role, which isn't in the service roles map (e.g. to fetch it from DB or external service).
+/// Implemented for closures, so `|role_name: &str| -> Option<Role> {...}` may be used as resolver.
+pub trait RoleResolver: Send + Sync {
+    fn resolve(&self, role_name: &str) -> Option<Role>;
+}
+
+impl<F: Fn(&str) -> Option<Role> + Send + Sync> RoleResolver for F {
+    fn resolve(&self, role_name: &str) -> Option<Role> {
+        self(role_name)
+    }
+}
+
+/// Most roles [ResolvedRoles] keeps, known and unknown ones together
+const RESOLVED_ROLES_KEPT: usize = 1024;
+
+/// Resolver with cache of its answers, including roles it doesn't know, so unknown roles aren't resolved again on every check.
+/// Cache is dropped on every roles update and its entries are tagged with service generation they were resolved in,
+/// so resolved roles never outlive the policy they were fetched for, even if resolution finished after update.
+pub(crate) struct ResolvedRoles {
+    resolver: Arc<dyn RoleResolver>,
+    cache: Mutex<HashMap<String, Resolved>>,
+}
+
+/// Resolver answer with service generation it was resolved in
+struct Resolved {
+    generation: u64,
+    role: Option<Arc<Role>>,
+}
+
+impl ResolvedRoles {
+    pub(crate) fn new(resolver: Arc<dyn RoleResolver>) -> Self {
+        ResolvedRoles {
+            resolver,
+            cache: Mutex::default(),
+        }
+    }
+
+    /// Returns answer cached in current `generation` or asks resolver for role. When cache is full, arbitrary entry is dropped to make room.
+    pub(crate) fn get(&self, role_name: &str, generation: u64, interner: &Interner) -> Option<Arc<Role>> {
+        if let Some(cached) = self.cache.lock().unwrap_or_else(PoisonError::into_inner).get(role_name).filter(|cached| cached.generation == generation) {
+            return cached.role.clone();
+        }
+
+        // Lock isn't held while resolving, as resolver may be slow
+        let role = self.resolver.resolve(role_name).map(|mut role| {
+            interner.intern(&mut role.compiled_permissions);
+            Arc::new(role)
+        });
+
+        let mut cache = self.cache.lock().unwrap_or_else(PoisonError::into_inner);
+        // Answer resolved while roles were updated is returned, but never replaces one of newer generation
+        if cache.get(role_name).is_some_and(|cached| cached.generation > generation) {
+            return role;
+        }
+        if cache.len() >= RESOLVED_ROLES_KEPT
+            && let Some(evicted) = cache.keys().next().cloned()
+        {
+            cache.remove(&evicted);
+        }
+        cache.insert(role_name.to_string(), Resolved { generation, role: role.clone() });
+        role
+    }
+
+    pub(crate) fn clear(&self) {
+        self.cache.lock().unwrap_or_else(PoisonError::into_inner).clear();
+    }
+}
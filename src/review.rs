@@ -0,0 +1,134 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{ConditionInput, RbacService, RbacSubject, RoleStorage, csv::csv_field, service::PermissionKey};
+
+/// Access review report produced by [.access_review()][crate::RbacService#method.access_review]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AccessReview {
+    pub subjects: Vec<SubjectAccess>,
+}
+
+/// Registered permissions granted to single subject
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SubjectAccess {
+    pub subject: String,
+    /// Roles permissions were checked against (fallback roles, if subject had none)
+    pub roles: Vec<String>,
+    pub permissions: Vec<GrantedPermission>,
+}
+
+/// Permission with the role and pattern granting it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GrantedPermission {
+    pub permission: String,
+    pub role: String,
+    pub pattern: String,
+}
+
+impl AccessReview {
+    /// One `subject,permission,role,pattern` row per granted permission, with header
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("subject,permission,role,pattern\n");
+        for access in &self.subjects {
+            for granted in &access.permissions {
+                let row = [&access.subject, &granted.permission, &granted.role, &granted.pattern].map(|field| csv_field(field));
+                csv.push_str(&row.join(","));
+                csv.push('\n');
+            }
+        }
+        csv
+    }
+}
+
+impl<R: RoleStorage> RbacService<R> {
+    /// Expands every registered permission granted to each subject, along with role and pattern it came from, for periodic access reviews.
+    /// Like [.explain()][RbacService#method.explain] it doesn't count as a check.
+    pub fn access_review<S: RbacSubject>(&self, subjects: &[S]) -> AccessReview {
+        let permissions = self.get_all_permissions();
+        // Every subject is reviewed against the same policy, even if roles are updated meanwhile
+        let subjects = self.with_policy(|policy| {
+            subjects
+                .iter()
+                .map(|subject| {
+                    let input = ConditionInput::of(subject);
+                    let roles = self.checked_roles(self.subject_roles(subject), &input);
+                    let granted = permissions
+                        .iter()
+                        .filter_map(|info| {
+                            let key = PermissionKey {
+                                domain: &info.domain,
+                                object_type: &info.object_type,
+                                action: &info.action,
+                                full_name: &info.full_name,
+                            };
+                            let (decision, _) = self.evaluate_in(policy, Some(subject.name()), &input, &roles, key, false);
+                            match (decision.allowed, decision.matched_role, decision.matched_pattern) {
+                                (true, Some(role), Some(pattern)) => Some(GrantedPermission {
+                                    permission: info.full_name.clone(),
+                                    role,
+                                    pattern,
+                                }),
+                                _ => None,
+                            }
+                        })
+                        .collect();
+                    SubjectAccess {
+                        subject: subject.name().to_string(),
+                        roles: roles.names,
+                        permissions: granted,
+                    }
+                })
+                .collect()
+        });
+        AccessReview { subjects }
+    }
+
+    /// Renders every role against every registered permission as text for golden-file (e.g. `insta`) snapshot tests,
+    /// so unintended policy changes fail CI. Block per role (sorted by name), line per permission (sorted by full name)
+    /// with outcome and granting pattern:
+    ///
+    /// ```text
+    /// role OrderManager
+    ///   allow Orders::Order::Read  Orders::*
+    ///   deny  Users::User::Read
+    /// ```
+    ///
+    /// [Deny roles][crate::Role::new_deny] are headed `role Name (deny)` and list permissions they revoke as `denies` with revoking pattern.
+    /// Disabled and deleted roles are headed `(inactive)`, they grant and revoke nothing.
+    ///
+    /// Each role is checked alone, without subject attributes, so conditional patterns don't grant anything.
+    /// Output doesn't depend on generation, revisions or check time, only on roles and registered permissions.
+    pub fn render_allow_matrix(&self) -> String {
+        let permissions = self.get_all_permissions();
+        let width = permissions.iter().map(|info| info.full_name.len()).max().unwrap_or_default();
+        let mut blocks = Vec::new();
+        for role in self.snapshot().roles {
+            let mut block = format!("role {}", role.name);
+            if role.deny {
+                block.push_str(" (deny)");
+            }
+            if !role.is_active() {
+                block.push_str(" (inactive)");
+            }
+            block.push('\n');
+            for info in &permissions {
+                let key = PermissionKey {
+                    domain: &info.domain,
+                    object_type: &info.object_type,
+                    action: &info.action,
+                    full_name: &info.full_name,
+                };
+                let decision = self.decision(None, &ConditionInput::default(), &[role.name.as_str()], key);
+                let line = match (decision.allowed, decision.matched_pattern) {
+                    (true, Some(pattern)) => format!("  allow {:width$}  {}", info.full_name, pattern),
+                    (false, Some(pattern)) if role.deny => format!("  denies {:width$}  {}", info.full_name, pattern),
+                    _ => format!("  deny  {}", info.full_name),
+                };
+                block.push_str(line.trim_end());
+                block.push('\n');
+            }
+            blocks.push(block);
+        }
+        blocks.join("\n")
+    }
+}
@@ -0,0 +1,70 @@
+use std::marker::PhantomData;
+
+use ::rocket::{
+    Request,
+    http::Status,
+    request::{FromRequest, Outcome},
+};
+
+use crate::{AtomicRoles, Permission, PermissionCore, RbacError, RbacService, RbacSubject, RoleStorage};
+
+/// Permission required by [Permit] guard, implemented by route-specific marker types:
+///
+/// ```ignore
+/// struct ReadOrders;
+///
+/// impl RequiredPermission for ReadOrders {
+///     type Subject = User;
+///     type Permission = Orders::Order;
+///     const PERMISSION: Orders::Order = Orders::Order::Read;
+/// }
+///
+/// #[get("/orders")]
+/// fn orders(permit: Permit<ReadOrders>) -> String { ... }
+/// ```
+pub trait RequiredPermission: Send + Sync + 'static {
+    /// Subject, resolved by its own request guard (e.g. from session or token)
+    type Subject: RbacSubject + for<'r> FromRequest<'r> + Send;
+    type Permission: Permission;
+    const PERMISSION: Self::Permission;
+}
+
+/// Request guard succeeding only for subjects having [RequiredPermission::PERMISSION].
+///
+/// Checks against [RbacService] from managed state (`rocket.manage(rbac_service)`), failing with `Status::Forbidden`.
+/// Service with other role storage is picked by second parameter, e.g. `Permit<ReadOrders, FrozenRoles>` for [frozen][RbacService#method.freeze] one.
+/// If subject can't be resolved, fails with status of subject guard.
+pub struct Permit<P: RequiredPermission, R: RoleStorage = AtomicRoles> {
+    pub subject: P::Subject,
+    storage: PhantomData<fn() -> R>,
+}
+
+impl<P: RequiredPermission, R: RoleStorage> Permit<P, R> {
+    pub fn into_subject(self) -> P::Subject {
+        self.subject
+    }
+}
+
+#[::rocket::async_trait]
+impl<'r, P: RequiredPermission, R: RoleStorage + Send + Sync + 'static> FromRequest<'r> for Permit<P, R> {
+    type Error = RbacError;
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let denied = || RbacError::PermissionDenied(P::PERMISSION.to_permission_string());
+
+        let subject = match P::Subject::from_request(request).await {
+            Outcome::Success(subject) => subject,
+            Outcome::Error((status, _)) => return Outcome::Error((status, denied())),
+            Outcome::Forward(status) => return Outcome::Forward(status),
+        };
+
+        let Some(rbac_service) = request.rocket().state::<RbacService<R>>() else {
+            return Outcome::Error((Status::InternalServerError, denied()));
+        };
+
+        match rbac_service.has_permission(&subject, P::PERMISSION) {
+            Ok(()) => Outcome::Success(Permit { subject, storage: PhantomData }),
+            Err(e) => Outcome::Error((Status::Forbidden, e)),
+        }
+    }
+}
@@ -0,0 +1,85 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{RbacError, RbacService, RbacSubject, RoleStorage};
+
+/// Single route rule: HTTP method and path pattern, mapped to permission string (e.g. `"Orders::Order::Read"`)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RouteRule {
+    /// HTTP method (case-insensitive), `*` matches any
+    pub method: String,
+    /// Path pattern: `*` or `{name}` segment matches any single segment, trailing `**` matches the rest of the path
+    pub path: String,
+    pub permission: String,
+}
+
+/// Route-to-permission table for API gateways enforcing coarse permissions before requests reach typed handlers.
+///
+/// Serializes as list of [RouteRule], so it may be loaded from config. Rules are matched in order, first match wins.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct RouteMap {
+    rules: Vec<RouteRule>,
+}
+
+impl RouteMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends rule, it is matched after all rules added before
+    pub fn add_route(&mut self, method: &str, path: &str, permission: &str) -> &mut Self {
+        self.rules.push(RouteRule {
+            method: method.to_string(),
+            path: path.to_string(),
+            permission: permission.to_string(),
+        });
+        self
+    }
+
+    pub fn rules(&self) -> &[RouteRule] {
+        &self.rules
+    }
+
+    /// Permission of the first rule matching request
+    pub fn lookup(&self, method: &str, path: &str) -> Option<&str> {
+        self.rules
+            .iter()
+            .find(|rule| {
+                (rule.method == "*" || rule.method.eq_ignore_ascii_case(method)) && path_matches(&rule.path, path)
+            })
+            .map(|rule| rule.permission.as_str())
+    }
+
+    /// Looks up request permission and checks it with [.has_permission_str()][RbacService#method.has_permission_str].
+    /// Requests without matching rule are denied with [RbacError::UnmappedRoute].
+    pub fn check<R: RoleStorage>(
+        &self,
+        rbac_service: &RbacService<R>,
+        subject: &impl RbacSubject,
+        method: &str,
+        path: &str,
+    ) -> Result<(), RbacError> {
+        match self.lookup(method, path) {
+            Some(permission) => rbac_service.has_permission_str(subject, permission),
+            None => Err(RbacError::UnmappedRoute(format!("{} {}", method, path))),
+        }
+    }
+}
+
+fn path_matches(pattern: &str, path: &str) -> bool {
+    let mut pattern = pattern.split('/').filter(|s| !s.is_empty());
+    let mut path = path.split('/').filter(|s| !s.is_empty());
+    loop {
+        match (pattern.next(), path.next()) {
+            (Some("**"), _) => return pattern.next().is_none(),
+            (None, None) => return true,
+            (Some(expected), Some(segment)) => {
+                let wildcard = expected == "*" || (expected.starts_with('{') && expected.ends_with('}'));
+                if !wildcard && expected != segment {
+                    return false;
+                }
+            }
+            _ => return false,
+        }
+    }
+}
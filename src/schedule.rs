@@ -0,0 +1,140 @@
+use std::{
+    panic::{self, AssertUnwindSafe},
+    sync::{Arc, Condvar, Mutex, MutexGuard, OnceLock, PoisonError},
+    thread,
+    time::SystemTime,
+};
+
+use crate::{RbacService, MutableRoleStorage, RbacServiceUpdater};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    Pending,
+    Cancelled,
+    Applied,
+}
+
+type Shared = Arc<(Mutex<State>, Condvar)>;
+
+fn locked<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+struct Job {
+    at: SystemTime,
+    state: Shared,
+    apply: Box<dyn FnOnce() + Send>,
+}
+
+/// Single background thread applying all scheduled updates, started with the first of them
+struct Scheduler {
+    jobs: Mutex<Vec<Job>>,
+    wakeup: Condvar,
+}
+
+impl Scheduler {
+    fn get() -> &'static Scheduler {
+        static SCHEDULER: OnceLock<Scheduler> = OnceLock::new();
+        SCHEDULER.get_or_init(|| {
+            thread::Builder::new()
+                .name("rbac-scheduler".to_string())
+                .spawn(|| Scheduler::get().run())
+                .expect("failed to spawn rbac scheduler thread");
+            Scheduler { jobs: Mutex::new(Vec::new()), wakeup: Condvar::new() }
+        })
+    }
+
+    fn push(&self, job: Job) {
+        locked(&self.jobs).push(job);
+        self.wakeup.notify_one();
+    }
+
+    fn run(&self) {
+        let mut jobs = locked(&self.jobs);
+        loop {
+            jobs.retain(|job| *locked(&job.state.0) == State::Pending);
+            let Some(next) = jobs.iter().enumerate().min_by_key(|(_, job)| job.at).map(|(index, _)| index) else {
+                jobs = self.wakeup.wait(jobs).unwrap_or_else(PoisonError::into_inner);
+                continue;
+            };
+            match jobs[next].at.duration_since(SystemTime::now()) {
+                Ok(remaining) if !remaining.is_zero() => {
+                    jobs = self.wakeup.wait_timeout(jobs, remaining).unwrap_or_else(PoisonError::into_inner).0;
+                }
+                _ => {
+                    let job = jobs.swap_remove(next);
+                    drop(jobs);
+                    job.run();
+                    jobs = locked(&self.jobs);
+                }
+            }
+        }
+    }
+}
+
+impl Job {
+    fn run(self) {
+        let (lock, done) = &*self.state;
+        // Applied under lock, so cancellation can't race with it
+        let mut state = locked(lock);
+        if *state == State::Pending {
+            // Role change sinks may panic, scheduler thread has to outlive them
+            let _ = panic::catch_unwind(AssertUnwindSafe(self.apply));
+            *state = State::Applied;
+        }
+        done.notify_all();
+    }
+}
+
+/// Handle of role change staged by [updater.apply_at()][RbacServiceUpdater#method.apply_at]
+pub struct ScheduledUpdate {
+    state: Shared,
+}
+
+impl RbacServiceUpdater {
+    /// Stages update to be applied to the service by background thread at given time (immediately, if time has already come).
+    /// Returned handle may be used to cancel the change before it is applied.
+    ///
+    /// Only roles added, changed or removed by updater are applied, on top of service roles current at that time:
+    /// changes made to other roles after updater was created are kept. All scheduled updates share a single timer thread.
+    pub fn apply_at<R>(self, rbac_service: Arc<RbacService<R>>, at: SystemTime) -> ScheduledUpdate
+    where
+        R: MutableRoleStorage + Send + Sync + 'static,
+    {
+        let state: Shared = Arc::new((Mutex::new(State::Pending), Condvar::new()));
+        Scheduler::get().push(Job {
+            at,
+            state: state.clone(),
+            apply: Box::new(move || self.apply(&rbac_service, true)),
+        });
+        ScheduledUpdate { state }
+    }
+}
+
+impl ScheduledUpdate {
+    /// Cancels pending update. Returns `false` if update has already been applied (or cancelled).
+    pub fn cancel(&self) -> bool {
+        let (lock, done) = &*self.state;
+        let mut state = locked(lock);
+        if *state != State::Pending {
+            return false;
+        }
+        *state = State::Cancelled;
+        done.notify_all();
+        drop(state);
+        // Lets scheduler drop the update (and service it holds) right away
+        Scheduler::get().wakeup.notify_one();
+        true
+    }
+
+    pub fn is_applied(&self) -> bool {
+        *locked(&self.state.0) == State::Applied
+    }
+
+    /// Blocks until update is applied or cancelled. Returns `true` if it was applied.
+    pub fn join(self) -> bool {
+        let (lock, done) = &*self.state;
+        let state = done.wait_while(locked(lock), |state| *state == State::Pending).unwrap_or_else(PoisonError::into_inner);
+        *state == State::Applied
+    }
+}
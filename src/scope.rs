@@ -0,0 +1,126 @@
+use std::collections::{BTreeMap, HashMap};
+
+use crate::{PermissionCatalog, PermissionInfo, service::PermissionKey};
+
+/// How permissions are written as OAuth2 scopes: `Orders::OrderItem::Read` is `orders:order_item:read` by default
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScopeFormat {
+    pub separator: String,
+    /// Converts names to snake case (`OrderItem` → `order_item`), otherwise they are kept as is
+    pub snake_case: bool,
+}
+
+impl Default for ScopeFormat {
+    fn default() -> Self {
+        ScopeFormat {
+            separator: ":".to_string(),
+            snake_case: true,
+        }
+    }
+}
+
+impl ScopeFormat {
+    pub fn scope(&self, permission: &PermissionInfo) -> String {
+        self.join([&permission.domain, &permission.object_type, &permission.action])
+    }
+
+    fn join(&self, parts: [&str; 3]) -> String {
+        parts
+            .map(|name| match self.snake_case {
+                true => snake_case(name),
+                false => name.to_string(),
+            })
+            .join(&self.separator)
+    }
+}
+
+/// Translates OAuth2 scopes of access token into permissions, for [.has_permission_with_scopes()][crate::RbacService#method.has_permission_with_scopes].
+///
+/// Scope written in [ScopeFormat] of permission (e.g. `orders:order:read`) grants that permission directly,
+/// while scopes mapped by [.map_scope()][ScopeMapper#method.map_scope] act as virtual roles granting permissions of service roles.
+#[derive(Debug, Clone, Default)]
+pub struct ScopeMapper {
+    format: ScopeFormat,
+    /// Scope → role names
+    roles: HashMap<String, Vec<String>>,
+}
+
+impl ScopeMapper {
+    pub fn new(format: ScopeFormat) -> Self {
+        ScopeMapper {
+            format,
+            roles: HashMap::new(),
+        }
+    }
+
+    /// Token with scope gets permissions of given roles
+    pub fn map_scope(&mut self, scope: &str, roles: Vec<String>) -> &mut Self {
+        self.roles.entry(scope.to_string()).or_default().extend(roles);
+        self
+    }
+
+    /// Roles mapped to any of space separated scopes (as in `scope` claim of token)
+    pub fn roles<'a>(&'a self, scopes: &'a str) -> Vec<&'a str> {
+        let mut roles: Vec<&str> = scopes
+            .split_whitespace()
+            .filter_map(|scope| self.roles.get(scope))
+            .flatten()
+            .map(String::as_str)
+            .collect();
+        roles.sort_unstable();
+        roles.dedup();
+        roles
+    }
+
+    /// Whether any of space separated scopes grants permission directly
+    pub(crate) fn grants(&self, scopes: &str, permission: PermissionKey) -> bool {
+        let scope = self.format.join([permission.domain, permission.object_type, permission.action]);
+        scopes.split_whitespace().any(|granted| granted == scope)
+    }
+}
+
+fn snake_case(name: &str) -> String {
+    let mut snake = String::with_capacity(name.len() + 4);
+    for (i, c) in name.char_indices() {
+        if c.is_uppercase() && i > 0 {
+            snake.push('_');
+        }
+        snake.extend(c.to_lowercase());
+    }
+    snake
+}
+
+impl PermissionCatalog {
+    /// OAuth2 scopes of all permissions, mapped to permission descriptions
+    pub fn scopes(&self, format: &ScopeFormat) -> BTreeMap<String, String> {
+        self.permissions()
+            .map(|info| (format.scope(info), info.description.clone()))
+            .collect()
+    }
+
+    /// OpenAPI security scheme object of OAuth2 `flow` (e.g. `"clientCredentials"`) declaring all permissions as scopes.
+    /// `urls` are flow URLs, e.g. `[("tokenUrl", "https://auth.example.com/token")]`.
+    #[cfg(feature = "json")]
+    pub fn openapi_security_scheme(&self, format: &ScopeFormat, flow: &str, urls: &[(&str, &str)]) -> serde_json::Value {
+        let mut flow_object: serde_json::Map<String, serde_json::Value> = urls
+            .iter()
+            .map(|(name, url)| (name.to_string(), serde_json::Value::from(*url)))
+            .collect();
+        flow_object.insert("scopes".to_string(), serde_json::json!(self.scopes(format)));
+        serde_json::json!({
+            "type": "oauth2",
+            "flows": { flow: flow_object },
+        })
+    }
+
+    /// OpenAPI security requirement object requiring scopes of given permissions from `scheme`, e.g. `{"oauth2": ["orders:order:read"]}`
+    #[cfg(feature = "json")]
+    pub fn openapi_security_requirement(&self, format: &ScopeFormat, scheme: &str, permissions: &[&str]) -> serde_json::Value {
+        let scopes: Vec<String> = self
+            .permissions()
+            .filter(|info| permissions.contains(&info.full_name.as_str()))
+            .map(|info| format.scope(info))
+            .collect();
+        serde_json::json!({ scheme: scopes })
+    }
+}
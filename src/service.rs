@@ -1,42 +1,368 @@
-use std::{collections::{BTreeMap, HashMap}, sync::Arc};
+use std::{
+    collections::{BTreeMap, HashMap, HashSet, VecDeque},
+    fmt,
+    sync::{
+        Arc, Mutex, PoisonError,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::SystemTime,
+};
 
-use arc_swap::{ArcSwap};
+use arc_swap::ArcSwap;
 
-use crate::{Permission, PermissionInfo, RbacError, RbacSubject, Role};
+use crate::{
+    AtomicRoles, AuditSink, ConditionInput, DeprecationWarning, MemoryQuotaStore, Quota, QuotaStore, BuildWarning, CompileWarning, CompiledPermissions, Interner, MemoryStats, Permission, PermissionCore, PermissionDomain, PermissionDomains, PermissionInfo, RbacDecision, RbacError, RbacSnapshot, RbacSubject, Role, RoleChangeEvent, RoleMigrator, RoleRewrite,
+    FrozenRoles, MutableRoleStorage, RoleChangeSink, RoleNameRules, RoleResolver, RoleStorage, RoleSyncReport, ScopeMapper, Severity, alias::PermissionAliases, cache::CombinationCache,
+    flags::{FeatureGates, FlagProvider}, resolver::ResolvedRoles, session::MergedPermissions,
+    usage::{PermissionUsage, UsageCounters},
+};
+
+/// Permission being checked, either typed or parsed from string
+#[derive(Clone, Copy)]
+pub(crate) struct PermissionKey<'a> {
+    pub(crate) domain: &'a str,
+    pub(crate) object_type: &'a str,
+    pub(crate) action: &'a str,
+    /// `Domain::Object::Action`, kept so it's never formatted on check path
+    pub(crate) full_name: &'a str,
+}
+
+impl<'a> PermissionKey<'a> {
+    pub(crate) fn of<P: PermissionCore + ?Sized>(permission: &'a P) -> Self {
+        let full_name = permission.full_name();
+        // Full name of deprecated permission may be name of its replacement, which is what gets checked
+        if permission.deprecated().is_some()
+            && let Some((object, action)) = full_name.rsplit_once("::")
+            && let Some((domain, object_type)) = object.split_once("::")
+        {
+            return PermissionKey {
+                domain,
+                object_type,
+                action,
+                full_name,
+            };
+        }
+        PermissionKey {
+            domain: permission.domain(),
+            object_type: permission.object_type(),
+            action: permission.action(),
+            full_name,
+        }
+    }
+}
+
+impl fmt::Display for PermissionKey<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.full_name)
+    }
+}
+
+/// Role deciding check
+#[derive(Clone, Copy)]
+struct RoleMatch<'r> {
+    role: &'r str,
+    priority: i32,
+    deny: bool,
+}
+
+/// Service roles as of single generation, which several checks may be evaluated against
+#[derive(Clone, Copy)]
+pub(crate) struct PolicyView<'a> {
+    pub(crate) generation: u64,
+    pub(crate) roles: &'a RoleMap,
+}
+
+/// Counts of started and finished role swaps, so readers may tell roles they loaded belong to generation they read:
+/// with no swap in progress, generation read before roles is the one of those roles
+#[derive(Default)]
+struct SwapTracker {
+    started: AtomicU64,
+    finished: AtomicU64,
+}
+
+/// Marks swap as finished when dropped, even if swap panicked
+struct Swapping<'a>(&'a AtomicU64);
+
+impl Drop for Swapping<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+impl SwapTracker {
+    fn start(&self) -> Swapping<'_> {
+        self.started.fetch_add(1, Ordering::SeqCst);
+        Swapping(&self.finished)
+    }
+}
+
+/// Roles check is evaluated against, see [.checked_roles()][RbacService#method.checked_roles]
+pub(crate) struct CheckedRoles {
+    pub(crate) names: Vec<String>,
+    pub(crate) fallback_used: bool,
+}
+
+/// Number of most recent [idempotency keys][RbacServiceUpdater#method.set_idempotency_key] remembered by service
+const IDEMPOTENCY_KEYS_KEPT: usize = 1024;
+
+/// Default [anonymous role][RbacServiceBuilder#method.set_anonymous_roles]
+pub const ANONYMOUS_ROLE: &str = "Anonymous";
+
+/// Persistent (structurally shared) map of roles, so copying it for update costs O(1) and each change costs O(log n)
+pub type RoleMap = im::HashMap<String, Role>;
 
 /// RbacService - RBAC service that may be used to check if particular subject has particular permission by calling [.has_permission()][RbacService#method.has_permission].
-pub struct RbacService {
-    roles: ArcSwap<HashMap<String, Role>>,
+///
+/// By default roles are stored lock-free in [AtomicRoles], see `RbacServiceSync` (`parking_lot` feature) for `RwLock` based alternative.
+pub struct RbacService<R: RoleStorage = AtomicRoles> {
+    roles: R,
     fallback_roles: Vec<String>,
-    all_permissions: BTreeMap<String, PermissionInfo>,
+    anonymous_roles: Vec<String>,
+    audience: Option<String>,
+    /// Registered permissions, swapped as a whole when domains are registered after build
+    all_permissions: ArcSwap<BTreeMap<String, PermissionInfo>>,
+    interner: Arc<Interner>,
+    change_sinks: Vec<Arc<dyn RoleChangeSink>>,
+    audit_sinks: Vec<Arc<dyn AuditSink>>,
+    resolver: Option<ResolvedRoles>,
+    report_unknown_roles: bool,
+    deprecation_warnings: bool,
+    build_warnings: Vec<BuildWarning>,
+    generation: AtomicU64,
+    swaps: SwapTracker,
+    /// Idempotency keys of applied updates with generations they produced, oldest first
+    applied_keys: Mutex<VecDeque<(String, u64)>>,
+    #[cfg(feature = "tokio")]
+    generation_watch: tokio::sync::watch::Sender<u64>,
+    pub(crate) usage: Option<UsageCounters>,
+    quotas: HashMap<String, Quota>,
+    quota_store: Arc<dyn QuotaStore>,
+    combinations: Option<CombinationCache>,
+    pub(crate) bundle_check: Severity,
+    pub(crate) gates: FeatureGates,
+    role_name_rules: Option<Arc<RoleNameRules>>,
+    protected_roles: Arc<HashSet<String>>,
+    aliases: Arc<PermissionAliases>,
 }
 
 /// RbacServiceBuilder - used when you create RBAC service. 
 /// On this stage you may also register all possible permissions to create comprehensive list by calling [.get_all_permissions()][RbacService#method.get_all_permissions] on RbacService.
 pub struct RbacServiceBuilder {
-    roles: HashMap<String, Role>,
+    roles: RoleMap,
     fallback_roles: Option<Vec<String>>,
-    all_permissions: BTreeMap<String, PermissionInfo>,
+    anonymous_roles: Vec<String>,
+    audience: Option<String>,
+    pub(crate) all_permissions: BTreeMap<String, PermissionInfo>,
+    interner: Arc<Interner>,
+    duplicate_policy: DuplicateRolePolicy,
+    migrations: Vec<RoleRewrite>,
+    change_sinks: Vec<Arc<dyn RoleChangeSink>>,
+    audit_sinks: Vec<Arc<dyn AuditSink>>,
+    resolver: Option<Arc<dyn RoleResolver>>,
+    report_unknown_roles: bool,
+    deprecation_warnings: bool,
+    usage_stats: bool,
+    fallback_check: Severity,
+    compile_check: Severity,
+    quotas: HashMap<String, Quota>,
+    quota_store: Arc<dyn QuotaStore>,
+    combination_cache: usize,
+    bundle_check: Severity,
+    gates: FeatureGates,
+    role_name_rules: Option<Arc<RoleNameRules>>,
+    protected_roles: HashSet<String>,
+    aliases: PermissionAliases,
 }
 
+/// What [.try_add_role()][RbacServiceBuilder#method.try_add_role] does, when role with the same name already added
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DuplicateRolePolicy {
+    /// Return [RbacError::DuplicateRole] (default)
+    #[default]
+    Error,
+    /// Join permissions of both definitions into single role
+    Merge,
+    /// Replace previous definition (same as [.add_role()][RbacServiceBuilder#method.add_role])
+    Replace,
+}
+
+/// [RbacService] variant storing roles behind `parking_lot::RwLock` instead of `arc-swap`, created by [.build_sync()][RbacServiceBuilder#method.build_sync].
+#[cfg(feature = "parking_lot")]
+pub type RbacServiceSync = RbacService<crate::LockedRoles>;
+
+/// [RbacService] variant with immutable roles, created by [.freeze()][RbacService#method.freeze]
+pub type FrozenRbacService = RbacService<FrozenRoles>;
+
 impl RbacServiceBuilder {
 
+    /// Builds service. Failed checks are reported as [.build_warnings()][RbacService#method.build_warnings] regardless of their [Severity],
+    /// use [.try_build()][RbacServiceBuilder#method.try_build] to fail on them.
     pub fn build(&self) -> RbacService {
+        self.build_with()
+    }
+
+    /// Builds service, failing if any check configured with [Severity::Error] doesn't pass
+    pub fn try_build(&self) -> Result<RbacService, RbacError> {
+        self.check()?;
+        Ok(self.build_with())
+    }
+
+    /// Builds [RbacServiceSync], which has the same API as [RbacService]
+    #[cfg(feature = "parking_lot")]
+    pub fn build_sync(&self) -> RbacServiceSync {
+        self.build_with()
+    }
+
+    /// Builds [RbacServiceSync], failing if any check configured with [Severity::Error] doesn't pass
+    #[cfg(feature = "parking_lot")]
+    pub fn try_build_sync(&self) -> Result<RbacServiceSync, RbacError> {
+        self.check()?;
+        Ok(self.build_with())
+    }
+
+    fn fallback_roles(&self) -> Vec<String> {
+        match &self.fallback_roles {
+            Some(roles) => roles.clone(),
+            None => vec!["Default".to_string()],
+        }
+    }
+
+    /// Fallback roles, which don't exist among added roles
+    fn missing_fallback_roles(&self) -> Vec<String> {
+        self.fallback_roles()
+            .into_iter()
+            .filter(|role_name| !self.roles.contains_key(role_name))
+            .collect()
+    }
+
+    /// Compile warnings of added roles, sorted by role name
+    fn compile_warnings(&self) -> Vec<(String, CompileWarning)> {
+        let mut roles: Vec<&Role> = self.roles.values().collect();
+        roles.sort_by(|a, b| a.name.cmp(&b.name));
+        roles
+            .into_iter()
+            .flat_map(|role| {
+                let (_, warnings) = CompiledPermissions::compile_with_warnings(&role.permissions);
+                warnings.into_iter().map(|warning| (role.name.clone(), warning))
+            })
+            .collect()
+    }
+
+    fn check(&self) -> Result<(), RbacError> {
+        if self.fallback_check == Severity::Error {
+            let missing = self.missing_fallback_roles();
+            if !missing.is_empty() {
+                return Err(RbacError::MissingFallbackRoles(missing));
+            }
+        }
+        if self.compile_check == Severity::Error
+            && let Some((role, warning)) = self.compile_warnings().into_iter().next()
+        {
+            return Err(RbacError::CompileWarning { role, warning });
+        }
+        // Gate, which gates nothing, fails open, so it's always an error
+        if let Some((pattern, flag)) = self.gates.malformed().first() {
+            return Err(RbacError::InvalidGatePattern {
+                pattern: pattern.clone(),
+                flag: flag.clone(),
+            });
+        }
+        Ok(())
+    }
+
+    fn warnings(&self) -> Vec<BuildWarning> {
+        let mut warnings = Vec::new();
+        if self.fallback_check != Severity::Ignore {
+            warnings.extend(self.missing_fallback_roles().into_iter().map(BuildWarning::MissingFallbackRole));
+        }
+        if self.compile_check != Severity::Ignore {
+            warnings.extend(self.compile_warnings().into_iter().map(|(role, warning)| BuildWarning::RolePattern { role, warning }));
+        }
+        warnings.extend(self.migrations.iter().cloned().map(BuildWarning::MigratedPermission));
+        warnings.extend(self.gates.malformed().iter().map(|(pattern, flag)| BuildWarning::GatePattern {
+            pattern: pattern.clone(),
+            flag: flag.clone(),
+        }));
+        warnings
+    }
+
+    fn build_with<R: RoleStorage>(&self) -> RbacService<R> {
+        let mut roles = self.roles.clone();
+        for (_, role) in roles.iter_mut() {
+            if self.aliases.compile(role) {
+                self.interner.intern(&mut role.compiled_permissions);
+            }
+        }
         RbacService {
-            roles: ArcSwap::new( Arc::new(self.roles.clone())),
-            fallback_roles: match &self.fallback_roles {
-                Some(roles) => roles.clone(),
-                None => vec!["Default".to_string()],
-            },
-            all_permissions: self.all_permissions.clone(),
+            roles: R::new(roles),
+            fallback_roles: self.fallback_roles(),
+            anonymous_roles: self.anonymous_roles.clone(),
+            audience: self.audience.clone(),
+            all_permissions: ArcSwap::from_pointee(self.all_permissions.clone()),
+            interner: self.interner.clone(),
+            change_sinks: self.change_sinks.clone(),
+            audit_sinks: self.audit_sinks.clone(),
+            resolver: self.resolver.clone().map(ResolvedRoles::new),
+            report_unknown_roles: self.report_unknown_roles,
+            deprecation_warnings: self.deprecation_warnings,
+            build_warnings: self.warnings(),
+            generation: AtomicU64::new(0),
+            swaps: SwapTracker::default(),
+            applied_keys: Mutex::default(),
+            #[cfg(feature = "tokio")]
+            generation_watch: tokio::sync::watch::Sender::new(0),
+            usage: self.usage_stats.then(|| UsageCounters::new(self.all_permissions.values())),
+            quotas: self.quotas.clone(),
+            quota_store: self.quota_store.clone(),
+            combinations: (self.combination_cache > 0).then(|| CombinationCache::new(self.combination_cache)),
+            bundle_check: self.bundle_check,
+            gates: self.gates.clone(),
+            role_name_rules: self.role_name_rules.clone(),
+            protected_roles: Arc::new(self.protected_roles.clone()),
+            aliases: Arc::new(self.aliases.clone()),
         }
     }
 
-    pub fn add_role(&mut self, role: Role) -> &mut Self {
+    /// Adds role, silently replacing previously added role with the same name. Use [.try_add_role()][RbacServiceBuilder#method.try_add_role] to catch duplicates.
+    pub fn add_role(&mut self, mut role: Role) -> &mut Self {
+        self.interner.intern(&mut role.compiled_permissions);
         self.roles.insert(role.name.clone(), role);
         self
     }
 
+    /// Adds role, handling role with the same name according to [DuplicateRolePolicy] set by [.set_duplicate_policy()][RbacServiceBuilder#method.set_duplicate_policy].
+    /// Roles with malformed conditions (see [Role::validate_conditions]) or names breaking [RoleNameRules] are rejected.
+    pub fn try_add_role(&mut self, role: Role) -> Result<&mut Self, RbacError> {
+        role.validate_conditions()?;
+        if let Some(rules) = &self.role_name_rules {
+            rules.validate(&role.name)?;
+        }
+        let role = match self.roles.get(&role.name) {
+            None => role,
+            Some(existing) => match self.duplicate_policy {
+                DuplicateRolePolicy::Error => return Err(RbacError::DuplicateRole(role.name)),
+                // Merging grants into deny role (or vice versa) would flip their meaning
+                DuplicateRolePolicy::Merge if existing.deny != role.deny => {
+                    return Err(RbacError::DuplicateRole(role.name));
+                }
+                DuplicateRolePolicy::Merge => {
+                    let mut permissions = existing.permissions.clone();
+                    permissions.extend(role.permissions);
+                    Role {
+                        revision: existing.revision,
+                        deny: existing.deny,
+                        priority: existing.priority,
+                        enabled: existing.enabled,
+                        deleted_at: existing.deleted_at,
+                        ..Role::new(&role.name, permissions)
+                    }
+                }
+                DuplicateRolePolicy::Replace => role,
+            },
+        };
+        Ok(self.add_role(role))
+    }
+
     pub fn load_roles(&mut self, roles: Vec<Role>) -> &mut Self {
         for role in roles {
             self.add_role(role);
@@ -44,39 +370,354 @@ impl RbacServiceBuilder {
         self
     }
 
+    /// Loads multiple roles with [.try_add_role()][RbacServiceBuilder#method.try_add_role]. Stops at first error, in which case none of roles are added.
+    pub fn try_load_roles(&mut self, roles: Vec<Role>) -> Result<&mut Self, RbacError> {
+        let loaded = self.roles.clone();
+        for role in roles {
+            if let Err(e) = self.try_add_role(role) {
+                self.roles = loaded;
+                return Err(e);
+            }
+        }
+        Ok(self)
+    }
+
+    /// Rewrites permissions of roles added so far with [RoleMigrator]. Every rewrite is reported as [BuildWarning::MigratedPermission].
+    pub fn migrate_roles(&mut self, migrator: &RoleMigrator) -> &mut Self {
+        let mut roles: Vec<Role> = self.roles.values().cloned().collect();
+        roles.sort_by(|a, b| a.name.cmp(&b.name));
+        let rewrites = migrator.migrate(&mut roles);
+        self.migrations.extend(rewrites);
+        self.load_roles(roles)
+    }
+
+    /// Loads roles and fallback roles from [RbacSnapshot]
+    pub fn load_snapshot(&mut self, snapshot: RbacSnapshot) -> &mut Self {
+        self.load_roles(snapshot.roles);
+        self.set_fallback_roles(snapshot.fallback_roles)
+    }
+
+    /// Registers sink receiving [RoleChangeEvent] for every role added, updated or removed by [updater.update()][RbacServiceUpdater#method.update]
+    pub fn add_role_change_sink(&mut self, sink: impl RoleChangeSink + 'static) -> &mut Self {
+        self.change_sinks.push(Arc::new(sink));
+        self
+    }
+
+    /// Adds receiver of every check decision, see [AuditSink]
+    pub fn add_audit_sink(&mut self, sink: impl AuditSink + 'static) -> &mut Self {
+        self.audit_sinks.push(Arc::new(sink));
+        self
+    }
+
+    /// Sets resolver asked for roles subjects reference, but service doesn't have (otherwise such roles are skipped).
+    /// Resolved roles are cached until next [updater.update()][RbacServiceUpdater#method.update].
+    pub fn set_role_resolver(&mut self, resolver: impl RoleResolver + 'static) -> &mut Self {
+        self.resolver = Some(Arc::new(resolver));
+        self
+    }
+
+    /// When enabled, denied checks of subjects referencing roles that don't exist in the service (and can't be resolved)
+    /// fail with [RbacError::UnknownRoles] instead of [RbacError::PermissionDenied], so misconfigured deployments are obvious.
+    pub fn set_report_unknown_roles(&mut self, report: bool) -> &mut Self {
+        self.report_unknown_roles = report;
+        self
+    }
+
+    /// Enables collection of per-permission check counts, reported by [.usage_stats()][RbacService#method.usage_stats]
+    pub fn set_usage_stats(&mut self, enabled: bool) -> &mut Self {
+        self.usage_stats = enabled;
+        self
+    }
+
+    /// Enables caching of merged permissions per distinct combination of subject roles (up to `max_entries` combinations, `0` disables cache),
+    /// so checks of the many subjects sharing the same roles are answered without walking their roles.
+    /// Cache is invalidated by [updater.update()][RbacServiceUpdater#method.update].
+    pub fn set_combination_cache(&mut self, max_entries: usize) -> &mut Self {
+        self.combination_cache = max_entries;
+        self
+    }
+
+    /// Limits how many times each subject may be granted permission (e.g. `"Reports::Export::Run"`) within quota window.
+    /// Only checks of subjects are counted, checks of bare roles aren't limited.
+    pub fn add_quota(&mut self, permission: &str, quota: Quota) -> &mut Self {
+        self.quotas.insert(permission.to_string(), quota);
+        self
+    }
+
+    /// Sets storage of quota counters, [MemoryQuotaStore] by default
+    pub fn set_quota_store(&mut self, store: impl QuotaStore + 'static) -> &mut Self {
+        self.quota_store = Arc::new(store);
+        self
+    }
+
+    /// Sets how missing fallback roles are treated by [.try_build()][RbacServiceBuilder#method.try_build] (default is [Severity::Warn]).
+    /// Without explicitly set fallback roles service falls back to `"Default"` role, which usually doesn't exist.
+    pub fn set_fallback_check(&mut self, severity: Severity) -> &mut Self {
+        self.fallback_check = severity;
+        self
+    }
+
+    /// Sets how [compile warnings][CompileWarning] of added roles (shadowed, malformed patterns and empty action sets)
+    /// are treated by [.try_build()][RbacServiceBuilder#method.try_build] (default is [Severity::Warn])
+    pub fn set_compile_check(&mut self, severity: Severity) -> &mut Self {
+        self.compile_check = severity;
+        self
+    }
+
+    /// Gates permissions matching pattern (e.g. `"Orders::Refund::*"` for whole object type) by feature flag:
+    /// while flag is disabled, their checks fail with [RbacError::FeatureDisabled], even though subject roles grant them.
+    /// Pattern has to be in one of forms roles compile, without condition: malformed pattern gates nothing, so it's reported
+    /// as [BuildWarning::GatePattern] and fails [.try_build()][RbacServiceBuilder#method.try_build].
+    pub fn gate_permissions(&mut self, pattern: &str, flag: &str) -> &mut Self {
+        self.gates.add(pattern, flag);
+        self
+    }
+
+    /// Sets provider consulted for gated permissions on every check. Until it's set, all gated permissions are denied.
+    pub fn set_flag_provider(&mut self, provider: impl FlagProvider + 'static) -> &mut Self {
+        self.gates.set_provider(Arc::new(provider));
+        self
+    }
+
+    /// Marks registered permission as deprecated with note (e.g. what to use instead), so its checks may be reported.
+    /// Permission must be registered first, unknown permissions are ignored.
+    pub fn deprecate_permission(&mut self, permission: &str, note: &str) -> &mut Self {
+        if let Some(info) = self.all_permissions.get_mut(permission) {
+            info.deprecated = Some(note.to_string());
+        }
+        self
+    }
+
+    /// When enabled, checks of deprecated permissions are reported with code location of the check to
+    /// [AuditSink::on_deprecated][crate::AuditSink::on_deprecated] of audit sinks (and, with `otel` feature, to current OpenTelemetry span)
+    pub fn set_deprecation_warnings(&mut self, enabled: bool) -> &mut Self {
+        self.deprecation_warnings = enabled;
+        self
+    }
+
+    /// Sets how [.load_bundle()][RbacService#method.load_bundle] treats bundles written for different permissions than registered ones (default is [Severity::Warn])
+    pub fn set_bundle_check(&mut self, severity: Severity) -> &mut Self {
+        self.bundle_check = severity;
+        self
+    }
+
+    /// Protects role from runtime modification: updaters keep it as it was at build time, even clean ones,
+    /// and their fallible methods fail with [RbacError::ProtectedRole] instead of changing it (e.g. bootstrap Admin role)
+    pub fn protect_role(&mut self, role_name: &str) -> &mut Self {
+        self.protected_roles.insert(role_name.to_string());
+        self
+    }
+
+    /// Adds alias of permission (e.g. `"Orders::Order::View"` for `"Orders::Order::Read"`), for integrations with systems using other action names.
+    /// Role patterns naming alias (`"Orders::Order::{View,Update}"`) grant permission it stands for, and checks of alias strings
+    /// by [.has_permission_str()][RbacService#method.has_permission_str] check that permission. Patterns are kept as written.
+    pub fn add_permission_alias(&mut self, alias: &str, permission: &str) -> &mut Self {
+        self.aliases.insert(alias, permission);
+        self
+    }
+
+    /// Sets rules role names added by [.try_add_role()][RbacServiceBuilder#method.try_add_role] and by updaters must follow
+    pub fn set_role_name_rules(&mut self, rules: RoleNameRules) -> &mut Self {
+        self.role_name_rules = Some(Arc::new(rules));
+        self
+    }
+
+    /// Sets how [.try_add_role()][RbacServiceBuilder#method.try_add_role] handles roles with the same name
+    pub fn set_duplicate_policy(&mut self, policy: DuplicateRolePolicy) -> &mut Self {
+        self.duplicate_policy = policy;
+        self
+    }
+
+    /// Loads roles one by one from reader with NDJSON (or any other whitespace separated JSON) stream of roles,
+    /// without materializing them into `Vec<Role>` first. Roles are added with [.try_add_role()][RbacServiceBuilder#method.try_add_role],
+    /// roles read before malformed entry or duplicate stay added.
+    #[cfg(feature = "json")]
+    pub fn load_roles_from_reader(&mut self, reader: impl std::io::Read) -> Result<&mut Self, RbacError> {
+        let stream = serde_json::Deserializer::from_reader(std::io::BufReader::new(reader)).into_iter::<Role>();
+        for role in stream {
+            let role = role.map_err(|e| RbacError::InvalidRoleData(e.to_string()))?;
+            self.try_add_role(role)?;
+        }
+        Ok(self)
+    }
+
     pub fn set_fallback_roles(&mut self, fallback_roles: Vec<String>) -> &mut Self {
         self.fallback_roles = Some(fallback_roles);
         self
     }
 
+    /// Sets roles [anonymous][RbacSubject::is_anonymous] subjects (e.g. [Subject::anonymous][crate::Subject::anonymous]) are checked against,
+    /// `["Anonymous"]` by default. Unlike fallback roles they apply to guests only, not to authenticated subjects which lost their roles.
+    /// With no anonymous roles anonymous subjects are denied everything, they never fall back to fallback roles.
+    pub fn set_anonymous_roles(&mut self, anonymous_roles: Vec<String>) -> &mut Self {
+        self.anonymous_roles = anonymous_roles;
+        self
+    }
+
+    /// Sets audience (name) of this service: checks of [machine subjects][RbacSubject::is_machine], which audiences don't include it,
+    /// fail with [RbacError::AudienceMismatch]. Without audience machine subjects aren't checked against it.
+    pub fn set_audience(&mut self, audience: &str) -> &mut Self {
+        self.audience = Some(audience.to_string());
+        self
+    }
+
+    /// Registers permissions of all given domains at once: `builder.register_domains((Users, Templates, Orders))`
+    pub fn register_domains<D: PermissionDomains>(&mut self, _domains: D) -> &mut Self {
+        D::register_all(self);
+        self
+    }
+
+    /// Registers permissions of object type. Permissions deprecated in favor of other ones are registered
+    /// as aliases of their replacements, so roles still naming them keep working.
     pub fn register_permissions<P: Permission>(&mut self) {
         for perm in P::all_permissions() {
-            let info = PermissionInfo {
-                domain: P::domain().to_string(),
-                object_type: perm.object_type().to_string(),
-                action: perm.action().to_string(),
-                full_name: perm.to_permission_string(),
-                description: perm.description().to_string(),
-            };
+            let info = PermissionInfo::of(&perm);
+            if info.full_name != perm.full_name() {
+                self.aliases.insert(&info.full_name, perm.full_name());
+            }
             self.all_permissions.insert(info.full_name.clone(), info);
         }
     }
 }
 
 pub struct RbacServiceUpdater {
-    roles: HashMap<String, Role>,
+    roles: RoleMap,
+    /// Service roles updater was created from, to tell its own changes from ones made to service meanwhile
+    base: RoleMap,
     fallback_roles: Option<Vec<String>>,
+    interner: Arc<Interner>,
+    actor: Option<String>,
+    idempotency_key: Option<String>,
+    role_name_rules: Option<Arc<RoleNameRules>>,
+    protected_roles: Arc<HashSet<String>>,
+    aliases: Arc<PermissionAliases>,
 }
 
 impl RbacServiceUpdater {
-    /// Adds one Role to map
-    pub fn add_role(&mut self, role: Role) -> &mut Self {
+    /// Adds one Role to map (or replaces role with the same name). Role revision is set to revision of replaced role plus one.
+    /// Revisions are tracked against roles in updater, so use [.updater_copy()][RbacService#method.updater_copy] to keep them continuous.
+    /// [Protected roles][RbacServiceBuilder#method.protect_role] are left untouched.
+    pub fn add_role(&mut self, mut role: Role) -> &mut Self {
+        if self.is_protected(&role.name) {
+            return self;
+        }
+        role.revision = self.revision(&role.name) + 1;
+        self.aliases.compile(&mut role);
+        self.interner.intern(&mut role.compiled_permissions);
+        self.roles.insert(role.name.clone(), role);
+        self
+    }
+
+    /// Adds role as [.add_role()][RbacServiceUpdater#method.add_role] does, rejecting roles with malformed conditions
+    /// or names breaking [RoleNameRules] of the service
+    pub fn try_add_role(&mut self, role: Role) -> Result<&mut Self, RbacError> {
+        self.validate_role(&role)?;
+        Ok(self.add_role(role))
+    }
+
+    fn validate_role(&self, role: &Role) -> Result<(), RbacError> {
+        self.check_unprotected(&role.name)?;
+        role.validate_conditions()?;
+        match &self.role_name_rules {
+            Some(rules) => rules.validate(&role.name),
+            None => Ok(()),
+        }
+    }
+
+    /// Adds or replaces role only if current revision of role in updater is `expected_revision` (`0` when role doesn't exist yet),
+    /// otherwise returns [RbacError::RevisionMismatch] and leaves updater untouched. Role is validated as by [.try_add_role()][RbacServiceUpdater#method.try_add_role].
+    pub fn upsert_if_match(&mut self, role: Role, expected_revision: u64) -> Result<&mut Self, RbacError> {
+        self.validate_role(&role)?;
+        let actual = self.revision(&role.name);
+        if actual != expected_revision {
+            return Err(RbacError::RevisionMismatch {
+                role: role.name,
+                expected: expected_revision,
+                actual,
+            });
+        }
+        Ok(self.add_role(role))
+    }
+
+    /// Inserts role as is, keeping its revision
+    pub(crate) fn insert_role(&mut self, mut role: Role) -> &mut Self {
+        if self.is_protected(&role.name) {
+            return self;
+        }
+        self.aliases.compile(&mut role);
+        self.interner.intern(&mut role.compiled_permissions);
         self.roles.insert(role.name.clone(), role);
         self
     }
 
+    /// Current revision of role in updater, `0` if role doesn't exist
+    pub fn revision(&self, role_name: &str) -> u64 {
+        self.roles.get(role_name).map_or(0, |role| role.revision)
+    }
+
+    /// Removes role, unless it's [protected][RbacServiceBuilder#method.protect_role]
     pub fn remove_role(&mut self, role_name: &str) -> &mut Self {
-        self.roles.remove(role_name);
+        if !self.is_protected(role_name) {
+            self.roles.remove(role_name);
+        }
+        self
+    }
+
+    /// Removes role, failing with [RbacError::ProtectedRole] for [protected][RbacServiceBuilder#method.protect_role] role
+    pub fn try_remove_role(&mut self, role_name: &str) -> Result<&mut Self, RbacError> {
+        self.check_unprotected(role_name)?;
+        Ok(self.remove_role(role_name))
+    }
+
+    pub(crate) fn is_protected(&self, role_name: &str) -> bool {
+        self.protected_roles.contains(role_name)
+    }
+
+    fn check_unprotected(&self, role_name: &str) -> Result<(), RbacError> {
+        match self.is_protected(role_name) {
+            true => Err(RbacError::ProtectedRole(role_name.to_string())),
+            false => Ok(()),
+        }
+    }
+
+    /// Enables or disables role (see [Role::enabled]) keeping its permissions, so it can be restored later as it was.
+    /// Counts as role update, so revision is incremented. Unknown roles are ignored.
+    pub fn set_role_enabled(&mut self, role_name: &str, enabled: bool) -> &mut Self {
+        if let Some(role) = self.roles.get(role_name)
+            && role.enabled != enabled
+        {
+            let role = Role { enabled, ..role.clone() };
+            self.add_role(role);
+        }
+        self
+    }
+
+    /// Tombstones role instead of removing it (see [Role::deleted_at]). Counts as role update, so revision is incremented.
+    /// Unknown and already tombstoned roles are ignored.
+    pub fn tombstone_role(&mut self, role_name: &str) -> &mut Self {
+        if let Some(role) = self.roles.get(role_name)
+            && role.deleted_at.is_none()
+        {
+            let role = Role {
+                deleted_at: Some(SystemTime::now()),
+                ..role.clone()
+            };
+            self.add_role(role);
+        }
+        self
+    }
+
+    /// Restores tombstoned role as it was before [.tombstone_role()][RbacServiceUpdater#method.tombstone_role]
+    pub fn restore_role(&mut self, role_name: &str) -> &mut Self {
+        if let Some(role) = self.roles.get(role_name)
+            && role.deleted_at.is_some()
+        {
+            let role = Role {
+                deleted_at: None,
+                ..role.clone()
+            };
+            self.add_role(role);
+        }
         self
     }
 
@@ -88,14 +729,198 @@ impl RbacServiceUpdater {
         self
     }
 
+    /// Makes updater role definitions exactly the `desired` ones (e.g. full state exported by IdP sync job), touching only roles that actually differ:
+    /// unchanged roles keep their revisions and emit no change events, roles missing in `desired` are removed.
+    /// Use updater from [.updater_copy()][RbacService#method.updater_copy], so roles are compared against current ones.
+    /// [Protected roles][RbacServiceBuilder#method.protect_role] are neither changed nor removed.
+    ///
+    /// Only role definitions are synced: role assignments belong to subjects ([RbacSubject::get_roles]) and aren't stored by service,
+    /// so they are synced wherever subjects come from. Fails with [RbacError::DuplicateRole], leaving updater as it is, if `desired` names role twice.
+    pub fn sync_roles(&mut self, desired: Vec<Role>) -> Result<RoleSyncReport, RbacError> {
+        let desired = desired.into_iter().filter(|role| !self.is_protected(&role.name)).collect();
+        let (mut report, changed) = crate::sync::plan(&self.roles, desired)?;
+        report.removed.retain(|role_name| !self.is_protected(role_name));
+        for role_name in &report.removed {
+            self.roles.remove(role_name);
+        }
+        self.load_roles(changed);
+        Ok(report)
+    }
+
     /// Sets new fallback roles (roles that checked in user doesn't have any). Updater would ignore this, if None and leave old ones in affected service.
     pub fn set_fallback_roles(&mut self, fallback_roles: Vec<String>) -> &Self {
         self.fallback_roles = Some(fallback_roles);
         self
     }
 
-    pub fn update(&self, rbac_service: &RbacService) {
-        rbac_service.roles.swap(Arc::new(self.roles.clone()));
+    /// Sets who makes the change, reported in [RoleChangeEvent]s
+    pub fn set_actor(&mut self, actor: &str) -> &mut Self {
+        self.actor = Some(actor.to_string());
+        self
+    }
+
+    /// Sets idempotency key of the update (e.g. id of policy pipeline message): update with key already applied to service is skipped,
+    /// so redelivered updates don't swap roles and notify sinks again. Service remembers last 1024 keys with generations they produced,
+    /// see [.applied_generation()][RbacService#method.applied_generation].
+    pub fn set_idempotency_key(&mut self, key: &str) -> &mut Self {
+        self.idempotency_key = Some(key.to_string());
+        self
+    }
+
+    /// Validates all updater roles before they are applied to the service: pattern and condition syntax, references to registered permissions
+    /// (when permissions are registered), [RoleNameRules] and [protected roles][RbacServiceBuilder#method.protect_role].
+    /// [.try_update()][RbacServiceUpdater#method.try_update] applies updater only if validation passes.
+    pub fn validate<R: RoleStorage>(&self, rbac_service: &RbacService<R>) -> Result<(), Vec<RbacError>> {
+        let registry = rbac_service.all_permissions.load();
+        let mut roles: Vec<&Role> = self.roles.values().collect();
+        roles.sort_by(|a, b| a.name.cmp(&b.name));
+
+        let mut errors = Vec::new();
+        for role in roles {
+            if let Some(Err(e)) = self.role_name_rules.as_ref().map(|rules| rules.validate(&role.name)) {
+                errors.push(e);
+            }
+            errors.extend(crate::validation::role_errors(role, &registry, &self.aliases));
+        }
+        let current = rbac_service.roles.load();
+        let mut protected: Vec<&String> = self.protected_roles.iter().collect();
+        protected.sort();
+        for role_name in protected {
+            if current.get(role_name).map(|role| role.revision) != self.roles.get(role_name).map(|role| role.revision) {
+                errors.push(RbacError::ProtectedRole(role_name.clone()));
+            }
+        }
+
+        match errors.is_empty() {
+            true => Ok(()),
+            false => Err(errors),
+        }
+    }
+
+    /// [Validates][RbacServiceUpdater#method.validate] updater and applies it to the service, only if validation passed
+    pub fn try_update<R: MutableRoleStorage>(&self, rbac_service: &RbacService<R>) -> Result<(), Vec<RbacError>> {
+        self.validate(rbac_service)?;
+        self.update(rbac_service);
+        Ok(())
+    }
+
+    /// Swaps service roles with updater roles without validating them, see [.try_update()][RbacServiceUpdater#method.try_update] for validated update.
+    /// No-op, if update with the same [idempotency key][RbacServiceUpdater#method.set_idempotency_key] was already applied.
+    pub fn update<R: MutableRoleStorage>(&self, rbac_service: &RbacService<R>) {
+        self.apply(rbac_service, false);
+    }
+
+    /// Swaps service roles with updater roles or, when `rebase` is set, applies only roles updater added, changed or removed
+    /// since it was created on top of current service roles, so roles changed by others meanwhile are kept
+    pub(crate) fn apply<R: MutableRoleStorage>(&self, rbac_service: &RbacService<R>, rebase: bool) {
+        // Generation update produced, or current one, if update left roles as they are
+        let swap = || {
+            let replaced = rbac_service.replace_roles(
+                || match rebase {
+                    false => Some((rbac_service.roles.swap(self.roles.clone()), Arc::new(self.roles.clone()))),
+                    true => rbac_service.roles.update(|current| Some(self.rebased(current))),
+                },
+                self.actor.as_deref(),
+            );
+            replaced.unwrap_or_else(|| rbac_service.generation())
+        };
+        let Some(key) = &self.idempotency_key else {
+            swap();
+            return;
+        };
+
+        // Lock is held through swap, so concurrent deliveries of the same update can't both apply it
+        let mut applied_keys = rbac_service.applied_keys.lock().unwrap_or_else(PoisonError::into_inner);
+        if applied_keys.iter().any(|(applied, _)| applied == key) {
+            return;
+        }
+        let generation = swap();
+        if applied_keys.len() == IDEMPOTENCY_KEYS_KEPT {
+            applied_keys.pop_front();
+        }
+        applied_keys.push_back((key.clone(), generation));
+    }
+
+    /// Current roles with changes of updater applied to them, role by role
+    fn rebased(&self, current: &RoleMap) -> RoleMap {
+        let mut roles = current.clone();
+        for (name, role) in &self.roles {
+            if self.base.get(name).is_none_or(|old| crate::events::changed(old, role)) {
+                let mut role = role.clone();
+                // Revision has to grow past the one role got meanwhile
+                role.revision = role.revision.max(current.get(name).map_or(0, |role| role.revision + 1));
+                roles.insert(name.clone(), role);
+            }
+        }
+        for name in self.base.keys().filter(|name| !self.roles.contains_key(*name)) {
+            roles.remove(name);
+        }
+        roles
+    }
+}
+
+impl<R: MutableRoleStorage> RbacService<R> {
+    /// Applies `patch` to a copy of single role and swaps it in atomically, without copying other roles (they are shared with current ones),
+    /// so concurrent patches of different roles don't overwrite each other. Returns new revision of role.
+    ///
+    /// Role is recompiled after patch, its name can't be changed. Fails for unknown and [protected][RbacServiceBuilder#method.protect_role] roles,
+    /// and for roles with malformed conditions after patch. `patch` may be called more than once, if roles are replaced concurrently.
+    pub fn patch_role<F: FnMut(&mut Role)>(&self, role_name: &str, mut patch: F) -> Result<u64, RbacError> {
+        if self.protected_roles.contains(role_name) {
+            return Err(RbacError::ProtectedRole(role_name.to_string()));
+        }
+        let mut result = Err(RbacError::RoleNotFound(role_name.to_string()));
+        let patched = |roles: &RoleMap| {
+            let mut role = roles.get(role_name)?.clone();
+            patch(&mut role);
+            role.name = role_name.to_string();
+            role.revision += 1;
+            if let Err(e) = role.validate_conditions() {
+                result = Err(e);
+                return None;
+            }
+            role.compiled_permissions = CompiledPermissions::compile(&role.permissions);
+            self.aliases.compile(&mut role);
+            self.interner.intern(&mut role.compiled_permissions);
+            result = Ok(role.revision);
+            Some(roles.update(role.name.clone(), role))
+        };
+
+        self.replace_roles(|| self.roles.update(patched), None);
+        result
+    }
+
+    /// Freezes service for deployments where policy is immutable after startup: roles are kept as plain map, so checks skip atomic load.
+    /// Frozen service can't be updated.
+    pub fn freeze(self) -> FrozenRbacService {
+        RbacService {
+            roles: FrozenRoles::new(RoleMap::clone(&self.roles.load_full())),
+            fallback_roles: self.fallback_roles,
+            anonymous_roles: self.anonymous_roles,
+            audience: self.audience,
+            all_permissions: self.all_permissions,
+            interner: self.interner,
+            change_sinks: self.change_sinks,
+            audit_sinks: self.audit_sinks,
+            resolver: self.resolver,
+            report_unknown_roles: self.report_unknown_roles,
+            deprecation_warnings: self.deprecation_warnings,
+            build_warnings: self.build_warnings,
+            generation: self.generation,
+            swaps: self.swaps,
+            applied_keys: self.applied_keys,
+            #[cfg(feature = "tokio")]
+            generation_watch: self.generation_watch,
+            usage: self.usage,
+            quotas: self.quotas,
+            quota_store: self.quota_store,
+            combinations: self.combinations,
+            bundle_check: self.bundle_check,
+            gates: self.gates,
+            role_name_rules: self.role_name_rules,
+            protected_roles: self.protected_roles,
+            aliases: self.aliases,
+        }
     }
 }
 
@@ -103,67 +928,754 @@ impl RbacService {
     /// Creates builder ([RbacServiceBuilder]) for [RbacService]
     pub fn builder() -> RbacServiceBuilder {
         RbacServiceBuilder {
-            roles: HashMap::new(),
+            roles: RoleMap::new(),
             fallback_roles: None,
+            anonymous_roles: vec![ANONYMOUS_ROLE.to_string()],
+            audience: None,
             all_permissions: BTreeMap::new(),
+            interner: Arc::default(),
+            duplicate_policy: DuplicateRolePolicy::default(),
+            migrations: Vec::new(),
+            change_sinks: Vec::new(),
+            audit_sinks: Vec::new(),
+            resolver: None,
+            report_unknown_roles: false,
+            deprecation_warnings: false,
+            usage_stats: false,
+            fallback_check: Severity::default(),
+            compile_check: Severity::default(),
+            quotas: HashMap::new(),
+            quota_store: Arc::new(MemoryQuotaStore::default()),
+            combination_cache: 0,
+            bundle_check: Severity::default(),
+            gates: FeatureGates::default(),
+            role_name_rules: None,
+            protected_roles: HashSet::new(),
+            aliases: PermissionAliases::default(),
         }
     }
+}
+
+impl<R: RoleStorage> RbacService<R> {
     /// Creates clean updater ([RbacServiceUpdater]) for updating [RbacService] roles in runtime.
     /// Updated roles set would be swapped atomically, when [updater.update(&mut rbac_service)][RbacServiceUpdater#method.update] called.
     pub fn updater_clean(&self) -> RbacServiceUpdater {
+        let current = self.roles.load();
         RbacServiceUpdater {
-            roles: HashMap::new(),
+            roles: self.protected_roles.iter().filter_map(|name| current.get(name).map(|role| (name.clone(), role.clone()))).collect(),
+            base: RoleMap::clone(&current),
             fallback_roles: None,
+            interner: self.interner.clone(),
+            actor: None,
+            idempotency_key: None,
+            role_name_rules: self.role_name_rules.clone(),
+            protected_roles: self.protected_roles.clone(),
+            aliases: self.aliases.clone(),
         }
     }
 
     /// Creates updater ([RbacServiceUpdater]) for updating [RbacService] roles in runtime. 
-    /// Updater would have copy of roles, which may be handy in case if small number of roles should be added/updated/removed
+    /// Updater would have copy of roles, which may be handy in case if small number of roles should be added/updated/removed.
+    /// Copy is cheap regardless of number of roles: roles map is persistent and shares structure with the service.
     pub fn updater_copy(&self) -> RbacServiceUpdater {
+        let current = RoleMap::clone(&self.roles.load());
         RbacServiceUpdater {
-            roles: self.roles.load().as_ref().clone(),
+            roles: current.clone(),
+            base: current,
             fallback_roles: match self.fallback_roles.is_empty() {
                 true => None,
                 false => Some(self.fallback_roles.clone()),
             },
+            interner: self.interner.clone(),
+            actor: None,
+            idempotency_key: None,
+            role_name_rules: self.role_name_rules.clone(),
+            protected_roles: self.protected_roles.clone(),
+            aliases: self.aliases.clone(),
         }
     }
 
+    /// Replaces roles by `swap` (returning previous and new roles, or `None`, if roles were left as they are) and reports change.
+    /// Returns generation of new roles.
+    fn replace_roles(&self, swap: impl FnOnce() -> Option<(Arc<RoleMap>, Arc<RoleMap>)>, actor: Option<&str>) -> Option<u64> {
+        let (previous, current, generation) = {
+            let _swapping = self.swaps.start();
+            let (previous, current) = swap()?;
+            (previous, current, self.generation.fetch_add(1, Ordering::SeqCst) + 1)
+        };
+        self.swapped(previous, &current, generation, actor);
+        Some(generation)
+    }
+
+    /// Invalidates caches and reports change after roles were replaced
+    #[cfg_attr(not(any(feature = "otel", feature = "tokio")), allow(unused_variables))]
+    fn swapped(&self, previous: Arc<RoleMap>, current: &RoleMap, generation: u64, actor: Option<&str>) {
+        if let Some(resolver) = &self.resolver {
+            resolver.clear();
+        }
+        if let Some(combinations) = &self.combinations {
+            combinations.clear();
+        }
+        #[cfg(feature = "otel")]
+        crate::otel::record_swap(generation, current.len());
+        #[cfg(feature = "tokio")]
+        // Concurrent swaps may get here out of order, so watchers never see generation go back
+        self.generation_watch.send_if_modified(|watched| {
+            let newer = *watched < generation;
+            *watched = (*watched).max(generation);
+            newer
+        });
+
+        if !self.change_sinks.is_empty() {
+            for event in RoleChangeEvent::diff(&previous, current, actor) {
+                for sink in &self.change_sinks {
+                    sink.on_role_change(&event);
+                }
+            }
+        }
+        // Names of removed patterns are freed once nothing else (e.g. snapshot being checked against) holds previous roles
+        drop(previous);
+        self.interner.prune();
+    }
+
     /// Check if subject has a specific permission
-    pub fn has_permission<P: Permission>(
+    #[track_caller]
+    pub fn has_permission<P: PermissionCore + ?Sized>(
+        &self,
+        subject: &impl RbacSubject,
+        permission: impl AsRef<P>,
+    ) -> Result<(), RbacError> {
+        self.check_audience(subject)?;
+        self.check_roles(Some(subject.name()), &ConditionInput::of(subject), self.subject_roles(subject), PermissionKey::of(permission.as_ref()))
+    }
+
+    /// Check if given roles grant a specific permission. Same as [.has_permission()][RbacService#method.has_permission],
+    /// but for cases, when roles arrive pre-extracted (message headers, service-to-service calls) and there is no subject to construct.
+    #[track_caller]
+    pub fn has_permission_with_roles<P: PermissionCore + ?Sized>(&self, roles: &[&str], permission: impl AsRef<P>) -> Result<(), RbacError> {
+        self.check_roles(None, &ConditionInput::default(), roles, PermissionKey::of(permission.as_ref()))
+    }
+
+    /// Check if OAuth2 access token with given space separated `scopes` has a specific permission, with scopes translated by `mapper`.
+    /// Tokens have no roles of their own, so fallback roles aren't used for tokens, which scopes map to no roles.
+    #[track_caller]
+    pub fn has_permission_with_scopes<P: PermissionCore + ?Sized>(
+        &self,
+        mapper: &ScopeMapper,
+        scopes: &str,
+        permission: impl AsRef<P>,
+    ) -> Result<(), RbacError> {
+        let permission = PermissionKey::of(permission.as_ref());
+        let roles = mapper.roles(scopes);
+        let granted = mapper.grants(scopes, permission);
+        let input = ConditionInput::default();
+        if !granted && !roles.is_empty() {
+            return self.check_roles(None, &input, &roles, permission);
+        }
+
+        let (result, decision) = self.with_policy(|policy| {
+            let PermissionKey { domain, object_type, action, .. } = permission;
+            // Permission granted by scope directly is still taken away by deny roles other scopes map to
+            let matched = match roles.is_empty() {
+                true => None,
+                false => self.winning_role(policy.roles, &roles, &input, domain, object_type, action),
+            };
+            let result = self.gates.check(permission).and_then(|()| match granted && !matched.is_some_and(|matched| matched.deny) {
+                true => Ok(()),
+                false => Err(RbacError::PermissionDenied(permission.to_string())),
+            });
+            let decision = (!self.audit_sinks.is_empty()).then(|| RbacDecision {
+                allowed: result.is_ok(),
+                permission: permission.to_string(),
+                subject: None,
+                roles: roles.iter().map(|role| role.to_string()).collect(),
+                matched_role: matched.map(|matched| matched.role.to_string()),
+                matched_pattern: self.matched_pattern(policy.roles, matched, &input, permission),
+                fallback_used: false,
+                generation: policy.generation,
+                time: SystemTime::now(),
+                blocked_by: result.as_ref().err().filter(|e| matches!(e, RbacError::FeatureDisabled { .. })).map(RbacError::to_string),
+                correlation_id: None,
+            });
+            (result, decision)
+        });
+        self.record_check_with(None, permission, &roles, result.is_ok(), false, decision);
+        result
+    }
+
+    /// Check if subject has a permission given as string (e.g. `"Orders::Order::Read"`), for code mapping routes or messages to permissions from config.
+    /// If permissions are registered, string must be one of them ([RbacError::UnknownPermission] otherwise).
+    #[track_caller]
+    pub fn has_permission_str(&self, subject: &impl RbacSubject, permission: &str) -> Result<(), RbacError> {
+        self.check_audience(subject)?;
+        self.check_roles(Some(subject.name()), &ConditionInput::of(subject), self.subject_roles(subject), self.parse_permission(permission)?)
+    }
+
+    /// Check if subject has a specific permission, evaluating conditions against subject attributes and given `context.*` attributes
+    #[cfg(feature = "expressions")]
+    #[track_caller]
+    pub fn has_permission_in_context<P: PermissionCore + ?Sized>(
         &self,
         subject: &impl RbacSubject,
-        permission: P,
+        permission: impl AsRef<P>,
+        context: crate::Attributes,
     ) -> Result<(), RbacError> {
-        let domain = P::domain();
-        let object_type = permission.object_type();
-        let action = permission.action();
-        let subject_roles = subject.get_roles();
-        let subject_roles = if subject_roles.is_empty() {
-            &self.fallback_roles
-        } else {
-            subject_roles
+        let input = ConditionInput {
+            context,
+            ..ConditionInput::of(subject)
+        };
+        self.check_audience(subject)?;
+        self.check_roles(Some(subject.name()), &input, self.subject_roles(subject), PermissionKey::of(permission.as_ref()))
+    }
+
+    /// Fails, if machine subject isn't allowed to call this service (its audiences don't include [service audience][RbacServiceBuilder#method.set_audience])
+    pub(crate) fn check_audience(&self, subject: &impl RbacSubject) -> Result<(), RbacError> {
+        match &self.audience {
+            Some(audience) if subject.is_machine() && !subject.audiences().contains(audience) => Err(RbacError::AudienceMismatch {
+                subject: subject.name().to_string(),
+                audience: audience.clone(),
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    pub(crate) fn fallback_roles(&self) -> &[String] {
+        &self.fallback_roles
+    }
+
+    /// Roles subject is checked against: its own, or anonymous roles for anonymous subject
+    pub(crate) fn subject_roles<'s>(&'s self, subject: &'s impl RbacSubject) -> &'s Vec<String> {
+        match subject.is_anonymous() {
+            true => &self.anonymous_roles,
+            false => subject.get_roles(),
+        }
+    }
+
+    /// Splits permission string (or permission its alias stands for) into parts, validating it against registry when it's populated
+    pub(crate) fn parse_permission<'a>(&'a self, permission: &'a str) -> Result<PermissionKey<'a>, RbacError> {
+        let permission = self.aliases.resolve(permission);
+        let mut parts = permission.split("::");
+        let key = match (parts.next(), parts.next(), parts.next(), parts.next()) {
+            (Some(domain), Some(object_type), Some(action), None)
+                if ![domain, object_type, action].iter().any(|part| part.is_empty() || part.contains(['*', '{', '}'])) =>
+            {
+                PermissionKey {
+                    domain,
+                    object_type,
+                    action,
+                    full_name: permission,
+                }
+            }
+            _ => return Err(RbacError::InvalidPermission(permission.to_string())),
         };
 
+        let all_permissions = self.all_permissions.load();
+        if !all_permissions.is_empty() && !all_permissions.contains_key(permission) {
+            return Err(RbacError::UnknownPermission {
+                permission: permission.to_string(),
+                suggestions: crate::suggest::closest(permission, all_permissions.keys().map(String::as_str)),
+            });
+        }
+        Ok(key)
+    }
+
+    /// Checks permission against roles, or against fallback roles, if there are no roles
+    #[track_caller]
+    pub(crate) fn check_roles<T: AsRef<str>>(
+        &self,
+        subject: Option<&str>,
+        input: &ConditionInput,
+        roles: &[T],
+        permission: PermissionKey,
+    ) -> Result<(), RbacError> {
+        self.check_roles_with(subject, input, roles, permission, true)
+    }
+
+    /// [.check_roles()][RbacService#method.check_roles], which charges subject quota only if `charge` is set (checks, which don't enforce anything, just peek at it)
+    #[track_caller]
+    pub(crate) fn check_roles_with<T: AsRef<str>>(
+        &self,
+        subject: Option<&str>,
+        input: &ConditionInput,
+        roles: &[T],
+        permission: PermissionKey,
+        charge: bool,
+    ) -> Result<(), RbacError> {
+        // Audited checks are evaluated once, so decision reported to sinks is the one which produced result
+        if !self.audit_sinks.is_empty() {
+            let (decision, result) = self.evaluate(subject, input, roles, permission, charge);
+            self.record_check(subject, permission, roles, result.is_ok(), Some(decision));
+            return result;
+        }
+
+        let result = self.gates.check(permission).and_then(|()| match (roles.is_empty(), input.anonymous) {
+            (false, _) => self.decide(roles, input, permission),
+            (true, false) => self.decide(&self.fallback_roles, input, permission),
+            // Fallback roles are for authenticated subjects only, anonymous ones without anonymous roles are denied everything
+            (true, true) => Err(RbacError::PermissionDenied(permission.to_string())),
+        });
+        let result = match subject {
+            Some(subject) => result.and_then(|()| self.quota(subject, permission, charge)),
+            None => result,
+        };
+        self.record_check_with(subject, permission, roles, result.is_ok(), roles.is_empty() && !input.anonymous, None);
+        result
+    }
+
+    /// Evaluates check against given roles (or fallback roles, if there are none and subject isn't anonymous) with feature gates and quota applied,
+    /// returning its decision along with result. Subject quota is charged, if `charge` is set.
+    fn evaluate<T: AsRef<str>>(
+        &self,
+        subject: Option<&str>,
+        input: &ConditionInput,
+        roles: &[T],
+        permission: PermissionKey,
+        charge: bool,
+    ) -> (RbacDecision, Result<(), RbacError>) {
+        self.with_policy(|policy| self.evaluate_in(policy, subject, input, &self.checked_roles(roles, input), permission, charge))
+    }
+
+    /// Calls `f` with current service roles, so all checks it makes are evaluated against the same policy
+    pub(crate) fn with_policy<T>(&self, f: impl FnOnce(PolicyView) -> T) -> T {
+        loop {
+            let finished = self.swaps.finished.load(Ordering::SeqCst);
+            let generation = self.generation.load(Ordering::SeqCst);
+            let roles = self.roles.load();
+            // Swap started meanwhile may have replaced roles before its generation was counted, so they'd be stamped with previous one
+            if self.swaps.started.load(Ordering::SeqCst) == finished {
+                return f(PolicyView { generation, roles: &roles });
+            }
+            std::thread::yield_now();
+        }
+    }
+
+    /// Roles check is evaluated against: given ones, or fallback roles, if there are none and subject isn't anonymous
+    pub(crate) fn checked_roles<T: AsRef<str>>(&self, roles: &[T], input: &ConditionInput) -> CheckedRoles {
+        let fallback_used = roles.is_empty() && !input.anonymous;
+        CheckedRoles {
+            names: match fallback_used {
+                true => self.fallback_roles.clone(),
+                false => roles.iter().map(|role| role.as_ref().to_string()).collect(),
+            },
+            fallback_used,
+        }
+    }
+
+    /// [Evaluates][RbacService#method.evaluate] check against given roles snapshot, so several checks may be made against the same policy
+    pub(crate) fn evaluate_in(
+        &self,
+        policy: PolicyView,
+        subject: Option<&str>,
+        input: &ConditionInput,
+        roles: &CheckedRoles,
+        permission: PermissionKey,
+        charge: bool,
+    ) -> (RbacDecision, Result<(), RbacError>) {
+        let PermissionKey { domain, object_type, action, .. } = permission;
+        let matched = self.winning_role(policy.roles, &roles.names, input, domain, object_type, action);
+        let result = self.gates.check(permission).and_then(|()| match matched.is_some_and(|matched| !matched.deny) {
+            true => Ok(()),
+            false => Err(self.denial(policy.roles, &roles.names, permission)),
+        });
+        let result = match subject {
+            Some(subject) => result.and_then(|()| self.quota(subject, permission, charge)),
+            None => result,
+        };
+
+        let decision = RbacDecision {
+            allowed: result.is_ok(),
+            permission: permission.to_string(),
+            subject: subject.map(str::to_string),
+            matched_role: matched.map(|matched| matched.role.to_string()),
+            matched_pattern: self.matched_pattern(policy.roles, matched, input, permission),
+            fallback_used: roles.fallback_used,
+            generation: policy.generation,
+            time: SystemTime::now(),
+            blocked_by: match &result {
+                Err(e @ (RbacError::FeatureDisabled { .. } | RbacError::QuotaExceeded { .. })) => Some(e.to_string()),
+                _ => None,
+            },
+            correlation_id: input.correlation_id.clone(),
+            roles: roles.names.clone(),
+        };
+        (decision, result)
+    }
+
+    /// Canonical pattern of matched role covering permission
+    fn matched_pattern(&self, inner_roles: &RoleMap, matched: Option<RoleMatch>, input: &ConditionInput, permission: PermissionKey) -> Option<String> {
+        let PermissionKey { domain, object_type, action, .. } = permission;
+        let pattern = |role: &Role| role.compiled_permissions.matching_pattern_with(domain, object_type, action, input);
+        match inner_roles.get(matched?.role) {
+            Some(role) => pattern(role),
+            None => self.resolve_role(matched?.role).and_then(|role| pattern(&role)),
+        }
+    }
+
+    fn decide<T: AsRef<str>>(&self, roles: &[T], input: &ConditionInput, permission: PermissionKey) -> Result<(), RbacError> {
+        if let Some(combinations) = &self.combinations {
+            return match combinations.get(self, roles).matches(permission, input) {
+                true => Ok(()),
+                false => Err(self.denial_for(roles, input, permission)),
+            };
+        }
+
         let inner_roles = self.roles.load();
 
-        // Collect all permissions from user's roles
-        for role_name in subject_roles {
-            let role = match inner_roles.get(role_name) {
-                Some(role) => role,
-                None => continue,
+        match self.roles_match(&inner_roles, roles, input, permission.domain, permission.object_type, permission.action) {
+            true => Ok(()),
+            false => Err(self.denial(&inner_roles, roles, permission)),
+        }
+    }
+
+    /// Explains check of subject permission: which role and pattern decided it, and whether fallback roles were used.
+    /// Doesn't count as a check, so it isn't reported to usage stats or audit sinks. Feature gates and quotas apply as on checks, but quota isn't consumed.
+    pub fn explain<P: PermissionCore + ?Sized>(&self, subject: &impl RbacSubject, permission: impl AsRef<P>) -> RbacDecision {
+        self.decision(Some(subject.name()), &ConditionInput::of(subject), self.subject_roles(subject), PermissionKey::of(permission.as_ref()))
+    }
+
+    /// [.explain()][RbacService#method.explain] of permission string, which is parsed like in [.has_permission_str()][RbacService#method.has_permission_str]
+    pub fn explain_str(&self, subject: &impl RbacSubject, permission: &str) -> Result<RbacDecision, RbacError> {
+        Ok(self.decision(Some(subject.name()), &ConditionInput::of(subject), self.subject_roles(subject), self.parse_permission(permission)?))
+    }
+
+    /// Explains check of hypothetical subject with given roles ("would user with roles X and Y be able to do Z"), without any real subject.
+    /// Like [.explain()][RbacService#method.explain] it doesn't count as a check.
+    pub fn explain_for_roles<P: PermissionCore + ?Sized>(&self, roles: &[&str], permission: impl AsRef<P>) -> RbacDecision {
+        self.decision(None, &ConditionInput::default(), roles, PermissionKey::of(permission.as_ref()))
+    }
+
+    /// Decision for given roles (or fallback roles, if there are none), with feature gates and quota applied (but not charged)
+    pub(crate) fn decision<T: AsRef<str>>(
+        &self,
+        subject: Option<&str>,
+        input: &ConditionInput,
+        roles: &[T],
+        permission: PermissionKey,
+    ) -> RbacDecision {
+        self.evaluate(subject, input, roles, permission, false).0
+    }
+
+    /// Error for denied check: lists unknown roles, if they are reported, or plain [RbacError::PermissionDenied] otherwise
+    fn denial<T: AsRef<str>>(&self, inner_roles: &RoleMap, roles: &[T], permission: PermissionKey) -> RbacError {
+        if self.report_unknown_roles {
+            let unknown: Vec<String> = roles
+                .iter()
+                .map(AsRef::as_ref)
+                .filter(|role_name| !inner_roles.contains_key(*role_name) && self.resolve_role(role_name).is_none())
+                .map(str::to_string)
+                .collect();
+            if !unknown.is_empty() {
+                return RbacError::UnknownRoles {
+                    permission: permission.to_string(),
+                    roles: unknown,
+                };
+            }
+        }
+
+        RbacError::PermissionDenied(permission.to_string())
+    }
+
+    /// Fails, if permission is gated by disabled feature flag
+    pub(crate) fn check_gates(&self, permission: PermissionKey) -> Result<(), RbacError> {
+        self.gates.check(permission)
+    }
+
+    /// Fails, if subject used up quota of permission, counting the check against it if `charge` is set
+    pub(crate) fn quota(&self, subject: &str, permission: PermissionKey, charge: bool) -> Result<(), RbacError> {
+        match charge {
+            true => self.charge_quota(subject, permission),
+            false => self.check_quota(subject, permission),
+        }
+    }
+
+    /// Counts granted permission against subject quota, failing if it's used up
+    fn charge_quota(&self, subject: &str, permission: PermissionKey) -> Result<(), RbacError> {
+        if self.quotas.is_empty() {
+            return Ok(());
+        }
+        match self.quotas.get(permission.full_name) {
+            Some(quota) if self.quota_store.increment(subject, permission.full_name, quota.window) > quota.limit => {
+                Err(RbacError::QuotaExceeded {
+                    permission: permission.full_name.to_string(),
+                    limit: quota.limit,
+                })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Fails, if subject used up quota of permission, without counting the check
+    fn check_quota(&self, subject: &str, permission: PermissionKey) -> Result<(), RbacError> {
+        match self.quotas.get(permission.full_name) {
+            Some(quota) if self.quota_store.uses(subject, permission.full_name, quota.window) >= quota.limit => Err(RbacError::QuotaExceeded {
+                permission: permission.full_name.to_string(),
+                limit: quota.limit,
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Same error denied check of given roles (or fallback roles, if there are none and subject isn't anonymous) would fail with
+    pub(crate) fn denial_for<T: AsRef<str>>(&self, roles: &[T], input: &ConditionInput, permission: PermissionKey) -> RbacError {
+        let inner_roles = self.roles.load();
+        match roles.is_empty() && !input.anonymous {
+            true => self.denial(&inner_roles, &self.fallback_roles, permission),
+            false => self.denial(&inner_roles, roles, permission),
+        }
+    }
+
+    /// Computes allow/deny matrix for every subject and permission pair: one row per subject, one column per permission.
+    /// All checks are evaluated against the same roles snapshot. With `rayon` feature enabled rows are computed in parallel.
+    /// Audience and feature gates apply as on checks, quotas aren't consumed.
+    pub fn evaluate_matrix<S, P>(&self, subjects: &[S], permissions: &[P]) -> Vec<Vec<bool>>
+    where
+        R: Sync,
+        S: RbacSubject + Sync,
+        P: Permission + Sync,
+    {
+        let inner_roles = self.roles.load_full();
+        let row = |subject: &S| -> Vec<bool> {
+            let input = ConditionInput::of(subject);
+            if self.check_audience(subject).is_err() {
+                return vec![false; permissions.len()];
+            }
+            let subject_roles = self.subject_roles(subject);
+            let subject_roles = if subject_roles.is_empty() && !input.anonymous {
+                &self.fallback_roles
+            } else {
+                subject_roles
             };
+            permissions
+                .iter()
+                .map(|perm| {
+                    let key = PermissionKey::of(perm);
+                    self.gates.check(key).is_ok()
+                        && self.roles_match(&inner_roles, subject_roles, &input, key.domain, key.object_type, key.action)
+                })
+                .collect()
+        };
+
+        #[cfg(feature = "rayon")]
+        {
+            use rayon::prelude::*;
+            subjects.par_iter().map(row).collect()
+        }
+        #[cfg(not(feature = "rayon"))]
+        {
+            subjects.iter().map(row).collect()
+        }
+    }
 
-            if role.compiled_permissions.matches(domain, object_type, action) {
-                return Ok(());
+    /// Checks if given roles grant permission, asking resolver (if any) for roles missing in map
+    #[inline]
+    pub(crate) fn roles_match<T: AsRef<str>>(
+        &self,
+        inner_roles: &RoleMap,
+        subject_roles: &[T],
+        input: &ConditionInput,
+        domain: &str,
+        object_type: &str,
+        action: &str,
+    ) -> bool {
+        self.winning_role(inner_roles, subject_roles, input, domain, object_type, action)
+            .is_some_and(|matched| !matched.deny)
+    }
+
+    /// The highest priority role matching permission (on equal priority deny wins, then the first role listed)
+    fn winning_role<'r, T: AsRef<str>>(
+        &self,
+        inner_roles: &RoleMap,
+        subject_roles: &'r [T],
+        input: &ConditionInput,
+        domain: &str,
+        object_type: &str,
+        action: &str,
+    ) -> Option<RoleMatch<'r>> {
+        let mut winner: Option<RoleMatch> = None;
+        for role_name in subject_roles.iter().map(AsRef::as_ref) {
+            let role_match = |role: &Role| {
+                (role.is_active() && role.compiled_permissions.matches_with(domain, object_type, action, input)).then_some(RoleMatch {
+                    role: role_name,
+                    priority: role.priority,
+                    deny: role.deny,
+                })
+            };
+            let matched = match inner_roles.get(role_name) {
+                Some(role) => role_match(role),
+                None => self.resolve_role(role_name).and_then(|role| role_match(&role)),
+            };
+            if let Some(matched) = matched
+                && winner.is_none_or(|winner| (matched.priority, matched.deny) > (winner.priority, winner.deny))
+            {
+                winner = Some(matched);
             }
         }
+        winner
+    }
+
+    fn resolve_role(&self, role_name: &str) -> Option<Arc<Role>> {
+        // Generation is read before resolving, so role resolved while roles are updated is cached for previous policy only
+        self.resolver.as_ref()?.get(role_name, self.generation(), &self.interner)
+    }
+
+    /// Union of permissions of given roles (or fallback roles, if there are none), merged separately per priority and grant/denial
+    pub(crate) fn merged_permissions<T: AsRef<str>>(&self, roles: &[T]) -> MergedPermissions {
+        let inner_roles = self.roles.load();
+        // priority → (allow patterns, deny patterns)
+        let mut tiers: BTreeMap<i32, (Vec<String>, Vec<String>)> = BTreeMap::new();
+        let mut add = |role: &Role| {
+            if !role.is_active() {
+                return;
+            }
+            let (allow, deny) = tiers.entry(role.priority).or_default();
+            match role.deny {
+                true => deny.extend(role.compiled_permissions.to_patterns()),
+                false => allow.extend(role.compiled_permissions.to_patterns()),
+            }
+        };
+
+        let names: Vec<&str> = match roles.is_empty() {
+            true => self.fallback_roles.iter().map(String::as_str).collect(),
+            false => roles.iter().map(AsRef::as_ref).collect(),
+        };
+        for name in names {
+            match inner_roles.get(name) {
+                Some(role) => add(role),
+                None => {
+                    if let Some(role) = self.resolve_role(name) {
+                        add(&role)
+                    }
+                }
+            }
+        }
+
+        let compile = |patterns: &Vec<String>| {
+            let mut compiled = CompiledPermissions::compile(patterns);
+            self.interner.intern(&mut compiled);
+            compiled
+        };
+        MergedPermissions {
+            tiers: tiers
+                .iter()
+                .rev()
+                .map(|(_, (allow, deny))| (compile(allow), compile(deny)))
+                .collect(),
+        }
+    }
+
+    /// Reports check to usage counters, audit sinks and (with `otel` feature) to current OpenTelemetry span.
+    /// Roles are the ones check was asked for, so they are empty for checks of fallback roles.
+    /// `decision` is reported to audit sinks, so it has to be given when there are any.
+    #[track_caller]
+    pub(crate) fn record_check<T: AsRef<str>>(&self, subject: Option<&str>, permission: PermissionKey, roles: &[T], allowed: bool, decision: Option<RbacDecision>) {
+        self.record_check_with(subject, permission, roles, allowed, roles.is_empty(), decision);
+    }
+
+    /// [.record_check()][RbacService#method.record_check] for checks, which may have no roles without falling back (e.g. of tokens granted by scopes)
+    #[track_caller]
+    #[cfg_attr(not(feature = "otel"), allow(unused_variables))]
+    pub(crate) fn record_check_with<T: AsRef<str>>(
+        &self,
+        subject: Option<&str>,
+        permission: PermissionKey,
+        roles: &[T],
+        allowed: bool,
+        fallback_used: bool,
+        decision: Option<RbacDecision>,
+    ) {
+        if self.deprecation_warnings {
+            self.report_deprecated(subject, permission);
+        }
+        if let Some(usage) = &self.usage {
+            usage.record(permission, allowed, fallback_used);
+        }
+        if let Some(decision) = decision {
+            for sink in &self.audit_sinks {
+                sink.on_decision(&decision);
+            }
+        }
+        #[cfg(feature = "otel")]
+        crate::otel::record_check(permission, roles, allowed, fallback_used);
+    }
 
-        Err(RbacError::PermissionDenied(permission.to_permission_string()))
+    /// Whether checks are reported to audit sinks, so they have to be evaluated with [.check_roles()][RbacService#method.check_roles]
+    pub(crate) fn is_audited(&self) -> bool {
+        !self.audit_sinks.is_empty()
     }
 
-    pub fn get_all_permissions(&self) -> Vec<&PermissionInfo> {
-        self.all_permissions.values().collect()
+    #[track_caller]
+    fn report_deprecated(&self, subject: Option<&str>, permission: PermissionKey) {
+        let Some(note) = self.all_permissions.load().get(permission.full_name).and_then(|info| info.deprecated.clone()) else {
+            return;
+        };
+        let warning = DeprecationWarning {
+            permission: permission.to_string(),
+            note,
+            subject: subject.map(str::to_string),
+            caller: std::panic::Location::caller(),
+        };
+        for sink in &self.audit_sinks {
+            sink.on_deprecated(&warning);
+        }
+        #[cfg(feature = "otel")]
+        crate::otel::record_deprecated(&warning);
+    }
+
+    /// Check counts of every permission checked so far (and registered ones, even if never checked).
+    /// Empty unless enabled by [.set_usage_stats()][RbacServiceBuilder#method.set_usage_stats].
+    pub fn usage_stats(&self) -> BTreeMap<String, PermissionUsage> {
+        self.usage.as_ref().map(UsageCounters::snapshot).unwrap_or_default()
+    }
+
+    /// Generation of roles, incremented on every [updater.update()][RbacServiceUpdater#method.update]
+    pub fn generation(&self) -> u64 {
+        self.generation.load(Ordering::Acquire)
+    }
+
+    /// Receiver of role generations, changed on every update (`tokio` feature). May be forwarded to replicas,
+    /// so they know which generation the source has.
+    #[cfg(feature = "tokio")]
+    pub fn watch_generation(&self) -> tokio::sync::watch::Receiver<u64> {
+        self.generation_watch.subscribe()
+    }
+
+    /// Waits until service roles reach `generation` (e.g. returned by [.applied_generation()][RbacService#method.applied_generation] of
+    /// the service change was pushed to), so caller may be sure following checks see the change (`tokio` feature).
+    /// Returns generation observed, or [RbacError::GenerationTimeout] if it wasn't reached in time.
+    #[cfg(feature = "tokio")]
+    pub async fn wait_for_generation(&self, generation: u64, timeout: std::time::Duration) -> Result<u64, RbacError> {
+        let mut receiver = self.watch_generation();
+        match tokio::time::timeout(timeout, receiver.wait_for(|current| *current >= generation)).await {
+            Ok(Ok(current)) => Ok(*current),
+            _ => Err(RbacError::GenerationTimeout {
+                expected: generation,
+                actual: self.generation(),
+            }),
+        }
+    }
+
+    /// Generation produced by update with given [idempotency key][RbacServiceUpdater#method.set_idempotency_key], `None` if no such update
+    /// was applied (or key is no longer remembered)
+    pub fn applied_generation(&self, key: &str) -> Option<u64> {
+        let applied_keys = self.applied_keys.lock().unwrap_or_else(PoisonError::into_inner);
+        applied_keys.iter().find(|(applied, _)| applied == key).map(|(_, generation)| *generation)
+    }
+
+    /// Registered permissions, sorted by full name
+    pub fn get_all_permissions(&self) -> Vec<PermissionInfo> {
+        self.all_permissions.load().values().cloned().collect()
+    }
+
+    /// Registers permissions of domain after service is built, e.g. for domain behind feature flag, which isn't known upfront.
+    /// Same as [.register_domains()][RbacServiceBuilder#method.register_domains], checks may use strings of domain permissions right after.
+    pub fn register_domain<D: PermissionDomain>(&self, _domain: D) {
+        self.all_permissions.rcu(|all_permissions| {
+            let mut all_permissions = BTreeMap::clone(all_permissions);
+            for info in D::all() {
+                all_permissions.insert(info.full_name.clone(), info);
+            }
+            all_permissions
+        });
     }
 
     /// Returns a snapshot of all currently configured roles.
@@ -171,7 +1683,26 @@ impl RbacService {
         self.roles.load().values().cloned().collect()
     }
 
-    pub fn get(&self, perm: &str) -> Option<&PermissionInfo> {
-        self.all_permissions.get(perm)
+    pub fn get(&self, perm: &str) -> Option<PermissionInfo> {
+        self.all_permissions.load().get(perm).cloned()
+    }
+
+    /// Problems found in configuration, when service was built
+    pub fn build_warnings(&self) -> &[BuildWarning] {
+        &self.build_warnings
+    }
+
+    /// Serializable snapshot of current roles (sorted by name) and fallback roles (sorted)
+    pub fn snapshot(&self) -> RbacSnapshot {
+        let mut roles = self.get_roles();
+        roles.sort_by(|a, b| a.name.cmp(&b.name));
+        let mut fallback_roles = self.fallback_roles.clone();
+        fallback_roles.sort();
+        RbacSnapshot { roles, fallback_roles }
+    }
+
+    /// Reports approximate memory used by roles, their compiled permission sets and permissions registry, with per role breakdown.
+    pub fn memory_stats(&self) -> MemoryStats {
+        MemoryStats::collect(&self.roles.load(), &self.all_permissions.load(), &self.interner)
     }
 }
@@ -0,0 +1,117 @@
+use std::cell::{Ref, RefCell};
+
+use crate::{AtomicRoles, CompiledPermissions, ConditionInput, PermissionCore, RbacError, RbacService, RbacSubject, RoleStorage, service::PermissionKey};
+
+/// Permissions of single subject, merged once for the lifetime of request or session.
+///
+/// Checks are answered from merged permissions instead of walking subject roles every time.
+/// When service roles are updated (its [generation][RbacService#method.generation] changes), permissions are merged again on next check.
+pub struct RbacSession<'a, R: RoleStorage = AtomicRoles> {
+    rbac_service: &'a RbacService<R>,
+    subject: String,
+    input: ConditionInput,
+    roles: Vec<String>,
+    /// Result of subject audience check, failing every check of session
+    audience: Result<(), RbacError>,
+    merged: RefCell<(u64, MergedPermissions)>,
+}
+
+/// Permissions of subject roles, with grants and denials kept apart
+#[derive(Default)]
+pub(crate) struct MergedPermissions {
+    /// `(allow, deny)` per role priority, highest priority first
+    pub(crate) tiers: Vec<(CompiledPermissions, CompiledPermissions)>,
+}
+
+impl MergedPermissions {
+    pub(crate) fn matches(&self, permission: PermissionKey, input: &ConditionInput) -> bool {
+        let PermissionKey { domain, object_type, action, .. } = permission;
+        for (allow, deny) in &self.tiers {
+            if deny.matches_with(domain, object_type, action, input) {
+                return false;
+            }
+            if allow.matches_with(domain, object_type, action, input) {
+                return true;
+            }
+        }
+        false
+    }
+}
+
+impl<'a, R: RoleStorage> RbacSession<'a, R> {
+    pub fn new(rbac_service: &'a RbacService<R>, subject: &impl RbacSubject) -> Self {
+        // Generation is read before roles, so concurrent update only makes merged permissions look stale
+        let generation = rbac_service.generation();
+        let session = RbacSession {
+            rbac_service,
+            subject: subject.name().to_string(),
+            input: ConditionInput::of(subject),
+            roles: rbac_service.subject_roles(subject).clone(),
+            audience: rbac_service.check_audience(subject),
+            merged: RefCell::default(),
+        };
+        *session.merged.borrow_mut() = (generation, session.merge());
+        session
+    }
+
+    /// Merges permissions of subject roles. Anonymous subject without roles gets none, it isn't given fallback roles.
+    fn merge(&self) -> MergedPermissions {
+        match self.roles.is_empty() && self.input.anonymous {
+            true => MergedPermissions::default(),
+            false => self.rbac_service.merged_permissions(&self.roles),
+        }
+    }
+
+    /// Merged permissions, merging them again if service roles were updated
+    fn permissions(&self) -> Ref<'_, MergedPermissions> {
+        let generation = self.rbac_service.generation();
+        if self.merged.borrow().0 != generation {
+            *self.merged.borrow_mut() = (generation, self.merge());
+        }
+        Ref::map(self.merged.borrow(), |(_, permissions)| permissions)
+    }
+
+    /// Checks permission, charging subject quota only if `charge` is set
+    #[track_caller]
+    fn check(&self, permission: PermissionKey, charge: bool) -> Result<(), RbacError> {
+        self.audience.clone()?;
+        // Audited checks need decision of service roles, merged permissions don't tell which role decided
+        if self.rbac_service.is_audited() {
+            return self.rbac_service.check_roles_with(Some(&self.subject), &self.input, &self.roles, permission, charge);
+        }
+        let result = self.rbac_service.check_gates(permission).and_then(|()| match self.permissions().matches(permission, &self.input) {
+            true => self.rbac_service.quota(&self.subject, permission, charge),
+            false => Err(self.rbac_service.denial_for(&self.roles, &self.input, permission)),
+        });
+        let fallback_used = self.roles.is_empty() && !self.input.anonymous;
+        self.rbac_service.record_check_with(Some(&self.subject), permission, &self.roles, result.is_ok(), fallback_used, None);
+        result
+    }
+
+    /// Whether subject has permission, e.g. to decide what to show. Doesn't consume [quota][crate::Quota], though exceeded quota denies permission.
+    #[track_caller]
+    pub fn can<P: PermissionCore + ?Sized>(&self, permission: impl AsRef<P>) -> bool {
+        self.check(PermissionKey::of(permission.as_ref()), false).is_ok()
+    }
+
+    /// Same as [.can()][RbacSession#method.can], but fails with the same error [.has_permission()][RbacService#method.has_permission] would.
+    /// Enforcing check, so granted permission is counted against subject [quota][crate::Quota].
+    #[track_caller]
+    pub fn require<P: PermissionCore + ?Sized>(&self, permission: impl AsRef<P>) -> Result<(), RbacError> {
+        self.check(PermissionKey::of(permission.as_ref()), true)
+    }
+
+    /// Canonical patterns of all permissions granted to subject, conditional ones listed with their conditions.
+    /// Deny roles are applied on checks only, so permissions they take away may still be covered by listed patterns.
+    pub fn list(&self) -> Vec<String> {
+        let mut patterns: Vec<String> = self
+            .permissions()
+            .tiers
+            .iter()
+            .flat_map(|(allow, _)| allow.to_patterns())
+            .collect();
+        patterns.sort_unstable();
+        patterns.dedup();
+        patterns
+    }
+}
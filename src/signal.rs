@@ -0,0 +1,63 @@
+use std::{
+    sync::Arc,
+    thread::{self, JoinHandle},
+};
+
+use signal_hook::iterator::{Handle, Signals};
+
+use crate::{MutableRoleStorage, RbacError, RbacService, Role, RoleSyncReport};
+
+/// Handle of background thread reloading roles on signal, started by [.reload_on_signal()][RbacService#method.reload_on_signal]
+pub struct SignalReload {
+    signals: Handle,
+    handle: JoinHandle<()>,
+}
+
+impl<R: MutableRoleStorage + Send + Sync + 'static> RbacService<R> {
+    /// Reloads roles from `loader` every time process receives `signal` (e.g. `signal_hook::consts::SIGHUP`), as ops reload other configs.
+    ///
+    /// Loaded roles are applied with [.sync_roles()][crate::RbacServiceUpdater#method.sync_roles], so unchanged roles keep their revisions.
+    /// If loader fails or loaded roles fail [validation][crate::RbacServiceUpdater#method.validate], current roles stay in place.
+    /// Outcome of every reload is passed to `report`: what was changed, or errors reload failed with.
+    pub fn reload_on_signal<F, C>(self: &Arc<Self>, signal: i32, loader: F, report: C) -> std::io::Result<SignalReload>
+    where
+        F: Fn() -> Result<Vec<Role>, RbacError> + Send + 'static,
+        C: Fn(Result<&RoleSyncReport, &[RbacError]>) + Send + 'static,
+    {
+        let mut signals = Signals::new([signal])?;
+        let handle = signals.handle();
+        let rbac_service = self.clone();
+
+        let thread = thread::spawn(move || {
+            for _ in signals.forever() {
+                match loader() {
+                    Ok(roles) => {
+                        let mut updater = rbac_service.updater_copy();
+                        match updater.sync_roles(roles) {
+                            Ok(sync) if sync.is_empty() => report(Ok(&sync)),
+                            Ok(sync) => match updater.try_update(&rbac_service) {
+                                Ok(()) => report(Ok(&sync)),
+                                Err(errors) => report(Err(&errors)),
+                            },
+                            Err(e) => report(Err(&[e])),
+                        }
+                    }
+                    Err(e) => report(Err(&[e])),
+                }
+            }
+        });
+
+        Ok(SignalReload {
+            signals: handle,
+            handle: thread,
+        })
+    }
+}
+
+impl SignalReload {
+    /// Stops reloading and waits for background thread to finish
+    pub fn stop(self) {
+        self.signals.close();
+        let _ = self.handle.join();
+    }
+}
@@ -0,0 +1,79 @@
+use serde::{Deserialize, Serialize};
+
+use crate::Role;
+
+/// Current version of snapshot format ([RbacSnapshot])
+pub const SNAPSHOT_FORMAT_VERSION: u32 = 1;
+
+/// Serializable snapshot of service roles configuration, created by [.snapshot()][crate::RbacService#method.snapshot]
+/// and loaded back by [.load_snapshot()][crate::RbacServiceBuilder#method.load_snapshot].
+///
+/// As with roles, unknown fields are ignored and missing ones get defaults, so snapshots stay loadable across format versions.
+///
+/// Snapshot is serialized canonically: roles sorted by name with their patterns normalized (see [Role::normalized])
+/// and fallback roles sorted, so identical policies always produce byte-identical output, fit for signing and diffing.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(from = "RbacSnapshotS")]
+#[serde(into = "RbacSnapshotS")]
+pub struct RbacSnapshot {
+    pub roles: Vec<Role>,
+    pub fallback_roles: Vec<String>,
+}
+
+impl RbacSnapshot {
+    /// Canonical pretty-printed JSON, ending with newline
+    #[cfg(feature = "json")]
+    pub fn to_canonical_json(&self) -> String {
+        let mut json = serde_json::to_string_pretty(self).unwrap_or_default();
+        json.push('\n');
+        json
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct RbacSnapshotS {
+    /// Format version, `0` for data written before versioning was introduced
+    #[serde(default)]
+    version: u32,
+    #[serde(default)]
+    roles: Vec<Role>,
+    #[serde(default)]
+    fallback_roles: Vec<String>,
+}
+
+impl RbacSnapshotS {
+    /// Upgrades snapshot written by older versions of format to [SNAPSHOT_FORMAT_VERSION]
+    fn migrate(mut self) -> Self {
+        // Every step upgrades data by one version: add new arm here, when format changes
+        while self.version < SNAPSHOT_FORMAT_VERSION {
+            self.version = match self.version {
+                // v1 only introduced `version` field itself, data is the same
+                0 => 1,
+                version => version + 1,
+            };
+        }
+        self
+    }
+}
+
+impl From<RbacSnapshotS> for RbacSnapshot {
+    fn from(value: RbacSnapshotS) -> Self {
+        let value = value.migrate();
+        RbacSnapshot {
+            roles: value.roles,
+            fallback_roles: value.fallback_roles,
+        }
+    }
+}
+
+impl From<RbacSnapshot> for RbacSnapshotS {
+    fn from(mut value: RbacSnapshot) -> Self {
+        value.roles.sort_by(|a, b| a.name.cmp(&b.name));
+        value.fallback_roles.sort();
+        RbacSnapshotS {
+            version: SNAPSHOT_FORMAT_VERSION,
+            roles: value.roles,
+            fallback_roles: value.fallback_roles,
+        }
+    }
+}
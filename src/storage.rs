@@ -0,0 +1,145 @@
+use std::{ops::Deref, sync::Arc};
+
+use arc_swap::ArcSwap;
+
+use crate::service::RoleMap;
+
+mod private {
+    pub trait Sealed {}
+}
+
+/// Storage of current roles inside [RbacService][crate::RbacService].
+///
+/// Implemented by [AtomicRoles] (lock-free, used by default), `LockedRoles` (`parking_lot` feature) and [FrozenRoles] (immutable).
+/// Trait is sealed: service API is the same for all of them (except for updates of frozen roles),
+/// so switching between them is a matter of `build()` vs `build_sync()` vs `freeze()`.
+pub trait RoleStorage: private::Sealed {
+    #[doc(hidden)]
+    type Guard<'a>: Deref<Target = Arc<RoleMap>>
+    where
+        Self: 'a;
+
+    #[doc(hidden)]
+    fn new(roles: RoleMap) -> Self;
+
+    /// Borrows current roles for a single check
+    #[doc(hidden)]
+    fn load(&self) -> Self::Guard<'_>;
+
+    /// Current roles, owned (can be held across checks and sent between threads)
+    #[doc(hidden)]
+    fn load_full(&self) -> Arc<RoleMap>;
+}
+
+/// [RoleStorage] roles of which may be replaced at runtime, which are all of them except for [FrozenRoles]
+pub trait MutableRoleStorage: RoleStorage {
+    /// Replaces current roles, returning previous ones
+    #[doc(hidden)]
+    fn swap(&self, roles: RoleMap) -> Arc<RoleMap>;
+
+    /// Atomically replaces current roles with ones made of them by `f` (called again, if roles were replaced meanwhile), returning previous and new ones.
+    /// When `f` returns `None`, roles are left as they are.
+    #[doc(hidden)]
+    fn update<F: FnMut(&RoleMap) -> Option<RoleMap>>(&self, f: F) -> Option<(Arc<RoleMap>, Arc<RoleMap>)>;
+}
+
+/// Lock-free roles storage based on `arc-swap`, readers never block
+pub struct AtomicRoles(ArcSwap<RoleMap>);
+
+impl private::Sealed for AtomicRoles {}
+
+impl RoleStorage for AtomicRoles {
+    type Guard<'a> = arc_swap::Guard<Arc<RoleMap>>;
+
+    fn new(roles: RoleMap) -> Self {
+        AtomicRoles(ArcSwap::new(Arc::new(roles)))
+    }
+
+    #[inline]
+    fn load(&self) -> Self::Guard<'_> {
+        self.0.load()
+    }
+
+    fn load_full(&self) -> Arc<RoleMap> {
+        self.0.load_full()
+    }
+}
+
+impl MutableRoleStorage for AtomicRoles {
+    fn swap(&self, roles: RoleMap) -> Arc<RoleMap> {
+        self.0.swap(Arc::new(roles))
+    }
+
+    fn update<F: FnMut(&RoleMap) -> Option<RoleMap>>(&self, mut f: F) -> Option<(Arc<RoleMap>, Arc<RoleMap>)> {
+        let mut current = self.0.load_full();
+        loop {
+            let updated = Arc::new(f(&current)?);
+            let previous = self.0.compare_and_swap(&current, updated.clone());
+            if Arc::ptr_eq(&previous, &current) {
+                return Some((current, updated));
+            }
+            current = arc_swap::Guard::into_inner(previous);
+        }
+    }
+}
+
+/// Roles storage based on `parking_lot::RwLock`, for targets where `arc-swap` isn't desirable.
+/// Checks take a read lock for their duration, updates take a write lock just to swap the map.
+#[cfg(feature = "parking_lot")]
+pub struct LockedRoles(parking_lot::RwLock<Arc<RoleMap>>);
+
+#[cfg(feature = "parking_lot")]
+impl private::Sealed for LockedRoles {}
+
+#[cfg(feature = "parking_lot")]
+impl RoleStorage for LockedRoles {
+    type Guard<'a> = parking_lot::RwLockReadGuard<'a, Arc<RoleMap>>;
+
+    fn new(roles: RoleMap) -> Self {
+        LockedRoles(parking_lot::RwLock::new(Arc::new(roles)))
+    }
+
+    #[inline]
+    fn load(&self) -> Self::Guard<'_> {
+        self.0.read()
+    }
+
+    fn load_full(&self) -> Arc<RoleMap> {
+        self.0.read().clone()
+    }
+}
+
+#[cfg(feature = "parking_lot")]
+impl MutableRoleStorage for LockedRoles {
+    fn swap(&self, roles: RoleMap) -> Arc<RoleMap> {
+        std::mem::replace(&mut *self.0.write(), Arc::new(roles))
+    }
+
+    fn update<F: FnMut(&RoleMap) -> Option<RoleMap>>(&self, mut f: F) -> Option<(Arc<RoleMap>, Arc<RoleMap>)> {
+        let mut roles = self.0.write();
+        let updated = Arc::new(f(&roles)?);
+        Some((std::mem::replace(&mut *roles, updated.clone()), updated))
+    }
+}
+
+/// Immutable roles storage of [FrozenRbacService][crate::FrozenRbacService]: plain map behind `Arc`, loaded without any atomic operation
+pub struct FrozenRoles(Arc<RoleMap>);
+
+impl private::Sealed for FrozenRoles {}
+
+impl RoleStorage for FrozenRoles {
+    type Guard<'a> = &'a Arc<RoleMap>;
+
+    fn new(roles: RoleMap) -> Self {
+        FrozenRoles(Arc::new(roles))
+    }
+
+    #[inline]
+    fn load(&self) -> Self::Guard<'_> {
+        &self.0
+    }
+
+    fn load_full(&self) -> Arc<RoleMap> {
+        self.0.clone()
+    }
+}
@@ -0,0 +1,69 @@
+use crate::{Attributes, RbacSubject};
+
+/// Name of [anonymous][Subject::anonymous] subject
+pub const ANONYMOUS_SUBJECT: &str = "anonymous";
+
+/// Ready-made [RbacSubject] for callers without subject type of their own (e.g. subjects built from token claims),
+/// including [anonymous][Subject::anonymous] subject of public endpoints.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Subject {
+    pub name: String,
+    pub roles: Vec<String>,
+    pub attributes: Attributes,
+    pub correlation_id: Option<String>,
+    anonymous: bool,
+}
+
+impl Subject {
+    pub fn new(name: &str, roles: Vec<String>) -> Self {
+        Subject {
+            name: name.to_string(),
+            roles,
+            ..Default::default()
+        }
+    }
+
+    /// Guest subject of public endpoints: it's checked against anonymous roles set by
+    /// [.set_anonymous_roles()][crate::RbacServiceBuilder#method.set_anonymous_roles] instead of fallback roles
+    pub fn anonymous() -> Self {
+        Subject {
+            name: ANONYMOUS_SUBJECT.to_string(),
+            anonymous: true,
+            ..Default::default()
+        }
+    }
+
+    /// Sets attributes conditional patterns are evaluated against
+    pub fn with_attributes(mut self, attributes: Attributes) -> Self {
+        self.attributes = attributes;
+        self
+    }
+
+    /// Sets correlation (request) ID attached to decisions of subject checks
+    pub fn with_correlation_id(mut self, correlation_id: &str) -> Self {
+        self.correlation_id = Some(correlation_id.to_string());
+        self
+    }
+}
+
+impl RbacSubject for Subject {
+    fn get_roles(&self) -> &Vec<String> {
+        &self.roles
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn attributes(&self) -> Attributes {
+        self.attributes.clone()
+    }
+
+    fn is_anonymous(&self) -> bool {
+        self.anonymous
+    }
+
+    fn correlation_id(&self) -> Option<&str> {
+        self.correlation_id.as_deref()
+    }
+}
@@ -0,0 +1,58 @@
+use std::{collections::BTreeSet, fmt};
+
+use crate::{RbacError, Role, service::RoleMap};
+
+/// Changes made by [.sync_roles()][crate::RbacServiceUpdater#method.sync_roles], role names sorted
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RoleSyncReport {
+    pub added: Vec<String>,
+    pub updated: Vec<String>,
+    pub removed: Vec<String>,
+}
+
+impl RoleSyncReport {
+    /// Desired state matched current roles, so there is nothing to update
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.updated.is_empty() && self.removed.is_empty()
+    }
+}
+
+impl fmt::Display for RoleSyncReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} added, {} updated, {} removed", self.added.len(), self.updated.len(), self.removed.len())
+    }
+}
+
+/// Roles of desired state, which differ from current ones, and names of current roles missing in desired state.
+/// Fails on role named twice in desired state, as it's unclear which definition is desired.
+pub(crate) fn plan(current: &RoleMap, desired: Vec<Role>) -> Result<(RoleSyncReport, Vec<Role>), RbacError> {
+    let mut report = RoleSyncReport::default();
+    let mut desired_names = BTreeSet::new();
+    let mut changed = Vec::new();
+    for role in desired {
+        if !desired_names.insert(role.name.clone()) {
+            return Err(RbacError::DuplicateRole(role.name));
+        }
+        match current.get(&role.name) {
+            None => report.added.push(role.name.clone()),
+            Some(old) if !same_definition(old, &role) => report.updated.push(role.name.clone()),
+            Some(_) => continue,
+        }
+        changed.push(role);
+    }
+    report.removed = current.keys().filter(|name| !desired_names.contains(*name)).cloned().collect();
+
+    report.added.sort_unstable();
+    report.updated.sort_unstable();
+    report.removed.sort_unstable();
+    Ok((report, changed))
+}
+
+/// Roles grant the same permissions the same way, regardless of how patterns are written and of revisions
+fn same_definition(a: &Role, b: &Role) -> bool {
+    a.deny == b.deny
+        && a.priority == b.priority
+        && a.enabled == b.enabled
+        && a.deleted_at.is_some() == b.deleted_at.is_some()
+        && a.compiled_permissions.to_patterns() == b.compiled_permissions.to_patterns()
+}
@@ -272,3 +272,3456 @@ fn test_update_roles() {
             .is_ok()
     );
 }
+
+#[test]
+fn test_evaluate_matrix() {
+    let rbac_service = setup_rbac();
+
+    let subjects = vec![
+        User {
+            name: "creator".to_string(),
+            roles: vec!["TemplateCreator".to_string()],
+        },
+        User {
+            name: "admin".to_string(),
+            roles: vec!["Admin".to_string()],
+        },
+    ];
+
+    let matrix = rbac_service.evaluate_matrix(
+        &subjects,
+        &[
+            Templates::Template::Create,
+            Templates::Template::Delete,
+        ],
+    );
+
+    assert_eq!(matrix, vec![vec![true, false], vec![true, true]]);
+
+    // Gates and audience apply as on checks
+    let mut builder = RbacService::builder();
+    builder
+        .add_role(Role::new("Admin", vec!["*".to_string()]))
+        .gate_permissions("Orders::Invoice::*", "invoices")
+        .set_audience("orders");
+    let rbac_service = builder.build();
+    let permissions = [Orders::Invoice::Send, Orders::Invoice::Read];
+    assert_eq!(rbac_service.evaluate_matrix(&subjects[1..], &permissions), vec![vec![false, false]]);
+    assert!(matches!(rbac_service.has_permission(&subjects[1], Orders::Invoice::Send), Err(RbacError::FeatureDisabled { .. })));
+    let orders = [Orders::Order::Read];
+    assert_eq!(rbac_service.evaluate_matrix(&subjects[1..], &orders), vec![vec![true]]);
+    let machines = [
+        MachineSubject::new("worker", vec!["Admin".to_string()], vec!["orders".to_string()]),
+        MachineSubject::new("reports", vec!["Admin".to_string()], vec!["reports".to_string()]),
+    ];
+    assert_eq!(rbac_service.evaluate_matrix(&machines, &orders), vec![vec![true], vec![false]]);
+}
+
+#[cfg(feature = "json")]
+#[test]
+fn test_load_roles_from_reader() {
+    let ndjson = r#"{"name":"Reader","permissions":["Orders::Order::Read"]}
+{"name":"Writer","permissions":["Orders::Order::{Create,Update}"]}
+"#;
+
+    let mut builder = RbacService::builder();
+    builder.load_roles_from_reader(ndjson.as_bytes()).unwrap();
+    let rbac_service = builder.build();
+
+    let writer = User {
+        name: "writer".to_string(),
+        roles: vec!["Writer".to_string()],
+    };
+    assert!(rbac_service.has_permission(&writer, Orders::Order::Update).is_ok());
+    assert!(rbac_service.has_permission(&writer, Orders::Order::Read).is_err());
+    assert_eq!(rbac_service.get_roles().len(), 2);
+
+    let mut builder = RbacService::builder();
+    assert!(matches!(
+        builder.load_roles_from_reader(r#"{"name":"Broken""#.as_bytes()),
+        Err(RbacError::InvalidRoleData(_))
+    ));
+}
+
+#[test]
+fn test_memory_stats() {
+    let rbac_service = setup_rbac();
+    let stats = rbac_service.memory_stats();
+
+    assert_eq!(stats.per_role.len(), 4);
+    assert!(stats.registry_bytes > 0);
+    assert_eq!(
+        stats.per_role.values().map(|r| r.compiled_bytes).sum::<usize>(),
+        stats.compiled_bytes
+    );
+
+    // Global wildcard compiles into a single flag
+    let admin = stats.per_role["Admin"];
+    let order_manager = stats.per_role["OrderManager"];
+    assert_eq!(admin.compiled_bytes, 0);
+    assert!(order_manager.compiled_bytes > 0);
+    assert!(stats.total_bytes() > order_manager.total_bytes());
+}
+
+#[test]
+fn test_roles_share_interned_names() {
+    let mut builder = RbacService::builder();
+    for tenant in 0..100 {
+        builder.add_role(Role::new(
+            &format!("Tenant{}Manager", tenant),
+            vec!["Orders::Order::*".to_string(), "Orders::Invoice::{Read,Generate}".to_string()],
+        ));
+    }
+    let rbac_service = builder.build();
+    let stats = rbac_service.memory_stats();
+
+    // "Orders", "Order", "Invoice", "Read" and "Generate" stored once for all roles
+    assert!(stats.interned_bytes < 1024);
+
+    let manager = User {
+        name: "manager".to_string(),
+        roles: vec!["Tenant42Manager".to_string()],
+    };
+    assert!(rbac_service.has_permission(&manager, Orders::Order::Cancel).is_ok());
+    assert!(rbac_service.has_permission(&manager, Orders::Invoice::Generate).is_ok());
+    assert!(rbac_service.has_permission(&manager, Orders::Invoice::Send).is_err());
+
+    // Names of replaced roles are released on next swap after their last holder (here updater based on them) is gone
+    let replace = |roles: Vec<Role>| {
+        let mut updater = rbac_service.updater_clean();
+        for role in roles {
+            updater.add_role(role);
+        }
+        updater.update(&rbac_service);
+    };
+    replace((0..100).map(|tenant| Role::new(&format!("Tenant{}Manager", tenant), vec![format!("Tenant{}::Order::Read", tenant)])).collect());
+    let interned_bytes = rbac_service.memory_stats().interned_bytes;
+    replace(vec![Role::new("Manager", vec!["Orders::Order::*".to_string()])]);
+    replace(vec![Role::new("Manager", vec!["Orders::Order::*".to_string()])]);
+    assert!(rbac_service.memory_stats().interned_bytes < interned_bytes / 10);
+}
+
+#[test]
+fn test_update_roles_from_copy() {
+    let rbac_service = setup_rbac();
+
+    let admin = User {
+        name: "admin".to_string(),
+        roles: vec!["Admin".to_string()],
+    };
+    let order_mgr = User {
+        name: "order_manager".to_string(),
+        roles: vec!["OrderManager".to_string()],
+    };
+
+    let mut updater = rbac_service.updater_copy();
+    updater.remove_role("Admin");
+    updater.update(&rbac_service);
+
+    assert!(rbac_service.has_permission(&admin, Users::User::Delete).is_err());
+    assert!(rbac_service.has_permission(&order_mgr, Orders::Order::Create).is_ok());
+    assert_eq!(rbac_service.get_roles().len(), 3);
+
+    // Copy taken before update is not affected by later changes in updater
+    let mut updater = rbac_service.updater_copy();
+    updater.remove_role("OrderManager");
+    assert!(rbac_service.has_permission(&order_mgr, Orders::Order::Create).is_ok());
+}
+
+#[cfg(feature = "parking_lot")]
+#[test]
+fn test_sync_service() {
+    let mut builder = RbacService::builder();
+    builder.add_role(Role::new("OrderReader", vec!["Orders::Order::Read".to_string()]));
+    let rbac_service: RbacServiceSync = builder.build_sync();
+
+    let reader = User {
+        name: "reader".to_string(),
+        roles: vec!["OrderReader".to_string()],
+    };
+    assert!(rbac_service.has_permission(&reader, Orders::Order::Read).is_ok());
+    assert!(rbac_service.has_permission(&reader, Orders::Order::Cancel).is_err());
+
+    let mut updater = rbac_service.updater_copy();
+    updater.add_role(Role::new("OrderReader", vec!["Orders::Order::*".to_string()]));
+    updater.update(&rbac_service);
+
+    assert!(rbac_service.has_permission(&reader, Orders::Order::Cancel).is_ok());
+}
+
+#[test]
+fn test_duplicate_roles() {
+    let reader = || Role::new("Orders", vec!["Orders::Order::Read".to_string()]);
+    let writer = || Role::new("Orders", vec!["Orders::Order::{Create,Update}".to_string()]);
+
+    let mut builder = RbacService::builder();
+    builder.try_add_role(reader()).unwrap();
+    assert_eq!(
+        builder.try_add_role(writer()).err(),
+        Some(RbacError::DuplicateRole("Orders".to_string()))
+    );
+
+    builder.set_duplicate_policy(DuplicateRolePolicy::Merge);
+    builder.try_add_role(writer()).unwrap();
+    let rbac_service = builder.build();
+
+    let user = User {
+        name: "user".to_string(),
+        roles: vec!["Orders".to_string()],
+    };
+    assert!(rbac_service.has_permission(&user, Orders::Order::Read).is_ok());
+    assert!(rbac_service.has_permission(&user, Orders::Order::Update).is_ok());
+
+    let mut builder = RbacService::builder();
+    builder
+        .set_duplicate_policy(DuplicateRolePolicy::Replace)
+        .try_load_roles(vec![reader(), writer()])
+        .unwrap();
+    let rbac_service = builder.build();
+    assert!(rbac_service.has_permission(&user, Orders::Order::Read).is_err());
+    assert!(rbac_service.has_permission(&user, Orders::Order::Update).is_ok());
+}
+
+#[test]
+fn test_role_normalized() {
+    let role = Role::new(
+        "Messy",
+        vec![
+            "Orders::Order::Read".to_string(),
+            "Users::*".to_string(),
+            "Users::User::Read".to_string(),
+            "Orders::Invoice::Send".to_string(),
+            "Orders::Invoice::{Read, Send}".to_string(),
+            "Orders::Order::Read".to_string(),
+            "Broken".to_string(),
+        ],
+    );
+
+    let expected = vec![
+        "Orders::Invoice::{Read,Send}".to_string(),
+        "Orders::Order::Read".to_string(),
+        "Users::*".to_string(),
+    ];
+    assert_eq!(role.normalized().permissions, expected);
+    assert_eq!(role.normalized().normalized().permissions, expected);
+
+    // Serde output is normalized as well
+    let serialized: RoleS = role.into();
+    assert_eq!(serialized.permissions, expected);
+
+    assert_eq!(
+        Role::new("Admin", vec!["Users::*".to_string(), "*".to_string()]).normalized().permissions,
+        vec!["*".to_string()]
+    );
+}
+
+#[test]
+fn test_versioned_formats() {
+    // Role written before versioning, with field from some future format version
+    let role: Role = serde_json::from_str(
+        r#"{"name":"Reader","permissions":["Orders::Order::Read"],"priority":10}"#,
+    )
+    .unwrap();
+    assert!(role.compiled_permissions.matches("Orders", "Order", "Read"));
+
+    let serialized = serde_json::to_value(&role).unwrap();
+    assert_eq!(serialized["version"], ROLE_FORMAT_VERSION);
+
+    let rbac_service = setup_rbac();
+    let json = serde_json::to_string(&rbac_service.snapshot()).unwrap();
+    let snapshot: RbacSnapshot = serde_json::from_str(&json).unwrap();
+    assert_eq!(snapshot.roles.len(), 4);
+
+    let mut builder = RbacService::builder();
+    builder.load_snapshot(snapshot);
+    let restored = builder.build();
+    let admin = User {
+        name: "admin".to_string(),
+        roles: vec!["Admin".to_string()],
+    };
+    assert!(restored.has_permission(&admin, Users::User::Delete).is_ok());
+
+    let legacy: RbacSnapshot = serde_json::from_str(r#"{"roles":[]}"#).unwrap();
+    assert!(legacy.fallback_roles.is_empty());
+}
+
+#[test]
+fn test_role_revisions() {
+    let rbac_service = setup_rbac();
+
+    let mut updater = rbac_service.updater_copy();
+    assert_eq!(updater.revision("OrderManager"), 0);
+
+    updater
+        .upsert_if_match(Role::new("OrderManager", vec!["Orders::*".to_string()]), 0)
+        .unwrap();
+    assert_eq!(updater.revision("OrderManager"), 1);
+
+    // Somebody else edited role based on stale revision
+    assert_eq!(
+        updater
+            .upsert_if_match(Role::new("OrderManager", vec![]), 0)
+            .err(),
+        Some(RbacError::RevisionMismatch {
+            role: "OrderManager".to_string(),
+            expected: 0,
+            actual: 1,
+        })
+    );
+    updater.update(&rbac_service);
+
+    let stored = rbac_service
+        .get_roles()
+        .into_iter()
+        .find(|role| role.name == "OrderManager")
+        .unwrap();
+    let json = serde_json::to_string(&stored).unwrap();
+    let restored: Role = serde_json::from_str(&json).unwrap();
+    assert_eq!(restored.revision, 1);
+
+    let mut updater = rbac_service.updater_copy();
+    updater.upsert_if_match(restored, 1).unwrap();
+    assert_eq!(updater.revision("OrderManager"), 2);
+}
+
+#[test]
+fn test_role_change_events() {
+    use std::sync::{Arc, Mutex};
+
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let sink_events = events.clone();
+
+    let mut builder = RbacService::builder();
+    builder
+        .add_role(Role::new("Reader", vec!["Orders::Order::Read".to_string()]))
+        .add_role(Role::new("Legacy", vec!["Orders::*".to_string()]))
+        .add_role_change_sink(move |event: &RoleChangeEvent| {
+            sink_events.lock().unwrap().push(event.clone());
+        });
+    let rbac_service = builder.build();
+
+    let mut updater = rbac_service.updater_copy();
+    updater
+        .set_actor("alice")
+        .remove_role("Legacy")
+        .add_role(Role::new("Reader", vec!["Orders::Order::{Read,Create}".to_string()]))
+        .add_role(Role::new("Writer", vec!["Orders::Order::Update".to_string()]));
+    updater.update(&rbac_service);
+
+    let events = events.lock().unwrap();
+    let summary: Vec<(RoleChangeKind, &str)> = events
+        .iter()
+        .map(|event| (event.kind, event.role.as_str()))
+        .collect();
+    assert_eq!(
+        summary,
+        vec![
+            (RoleChangeKind::Removed, "Legacy"),
+            (RoleChangeKind::Updated, "Reader"),
+            (RoleChangeKind::Added, "Writer"),
+        ]
+    );
+    assert_eq!(events[1].before.as_ref().unwrap().permissions, vec!["Orders::Order::Read".to_string()]);
+    assert!(events.iter().all(|event| event.actor.as_deref() == Some("alice")));
+}
+
+#[test]
+fn test_idempotent_update() {
+    use std::sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    };
+
+    let notified = Arc::new(AtomicUsize::new(0));
+    let counter = notified.clone();
+    let mut builder = RbacService::builder();
+    builder
+        .add_role(Role::new("Reader", vec!["Orders::Order::Read".to_string()]))
+        .add_role_change_sink(move |_: &RoleChangeEvent| {
+            counter.fetch_add(1, Ordering::Relaxed);
+        });
+    let rbac_service = builder.build();
+
+    // The same message delivered twice by pipeline
+    for _ in 0..2 {
+        let mut updater = rbac_service.updater_copy();
+        updater
+            .set_idempotency_key("policy-msg-42")
+            .add_role(Role::new("Writer", vec!["Orders::Order::Update".to_string()]));
+        updater.update(&rbac_service);
+    }
+    assert_eq!(rbac_service.generation(), 1);
+    assert_eq!(notified.load(Ordering::Relaxed), 1);
+    assert_eq!(rbac_service.applied_generation("policy-msg-42"), Some(1));
+    assert_eq!(rbac_service.applied_generation("policy-msg-43"), None);
+
+    let mut updater = rbac_service.updater_copy();
+    updater.set_idempotency_key("policy-msg-43").remove_role("Writer");
+    updater.update(&rbac_service);
+    assert_eq!(rbac_service.generation(), 2);
+    assert_eq!(rbac_service.applied_generation("policy-msg-43"), Some(2));
+}
+
+#[cfg(feature = "tokio")]
+#[tokio::test]
+async fn test_wait_for_generation() {
+    use std::{sync::Arc, time::Duration};
+
+    let mut builder = RbacService::builder();
+    builder.add_role(Role::new("Reader", vec!["Orders::Order::Read".to_string()]));
+    let rbac_service = Arc::new(builder.build());
+    assert_eq!(rbac_service.wait_for_generation(0, Duration::ZERO).await, Ok(0));
+
+    let updated = rbac_service.clone();
+    tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        let mut updater = updated.updater_copy();
+        updater.add_role(Role::new("Writer", vec!["Orders::Order::Update".to_string()]));
+        updater.update(&updated);
+    });
+    assert_eq!(rbac_service.wait_for_generation(1, Duration::from_secs(5)).await, Ok(1));
+    assert_eq!(*rbac_service.watch_generation().borrow(), 1);
+
+    assert_eq!(
+        rbac_service.wait_for_generation(2, Duration::from_millis(10)).await,
+        Err(RbacError::GenerationTimeout { expected: 2, actual: 1 })
+    );
+}
+
+#[test]
+fn test_scheduled_update() {
+    use std::{
+        sync::Arc,
+        time::{Duration, SystemTime},
+    };
+
+    let rbac_service = Arc::new(setup_rbac());
+    let admin = User {
+        name: "admin".to_string(),
+        roles: vec!["Admin".to_string()],
+    };
+
+    let mut updater = rbac_service.updater_copy();
+    updater.remove_role("Admin");
+    let scheduled = updater.apply_at(rbac_service.clone(), SystemTime::now() + Duration::from_millis(50));
+    assert!(rbac_service.has_permission(&admin, Users::User::Delete).is_ok());
+    assert!(scheduled.join());
+    assert!(rbac_service.has_permission(&admin, Users::User::Delete).is_err());
+
+    // Changes made after updater was created aren't reverted when it fires
+    let mut updater = rbac_service.updater_copy();
+    updater.add_role(Role::new("Admin", vec!["Users::*".to_string()]));
+    let scheduled = updater.apply_at(rbac_service.clone(), SystemTime::now() + Duration::from_millis(50));
+    let mut meanwhile = rbac_service.updater_copy();
+    meanwhile.add_role(Role::new("Auditor", vec!["Orders::*".to_string()]));
+    meanwhile.update(&rbac_service);
+    assert!(scheduled.join());
+    assert!(rbac_service.has_permission(&admin, Users::User::Delete).is_ok());
+    assert!(rbac_service.has_permission_with_roles(&["Auditor"], Orders::Invoice::Generate).is_ok());
+    rbac_service.updater_copy().remove_role("Admin").update(&rbac_service);
+
+    let mut updater = rbac_service.updater_copy();
+    updater.add_role(Role::new("Admin", vec!["*".to_string()]));
+    let scheduled = updater.apply_at(rbac_service.clone(), SystemTime::now() + Duration::from_secs(3600));
+    assert!(scheduled.cancel());
+    assert!(!scheduled.cancel());
+    assert!(!scheduled.join());
+    assert!(rbac_service.has_permission(&admin, Users::User::Delete).is_err());
+}
+
+#[test]
+fn test_role_resolver() {
+    use std::sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    };
+
+    let calls = Arc::new(AtomicUsize::new(0));
+    let resolver_calls = calls.clone();
+
+    let mut builder = RbacService::builder();
+    builder.set_role_resolver(move |role_name: &str| {
+        resolver_calls.fetch_add(1, Ordering::SeqCst);
+        (role_name == "Tenant42Reader")
+            .then(|| Role::new(role_name, vec!["Orders::Order::Read".to_string()]))
+    });
+    let rbac_service = builder.build();
+
+    let tenant_user = User {
+        name: "tenant_user".to_string(),
+        roles: vec!["Tenant42Reader".to_string()],
+    };
+    assert!(rbac_service.has_permission(&tenant_user, Orders::Order::Read).is_ok());
+    assert!(rbac_service.has_permission(&tenant_user, Orders::Order::Cancel).is_err());
+    assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+    let stranger = User {
+        name: "stranger".to_string(),
+        roles: vec!["Unknown".to_string()],
+    };
+    assert!(rbac_service.has_permission(&stranger, Orders::Order::Read).is_err());
+    assert_eq!(calls.load(Ordering::SeqCst), 2);
+    // Unknown roles are cached too
+    assert!(rbac_service.has_permission(&stranger, Orders::Order::Read).is_err());
+    assert_eq!(calls.load(Ordering::SeqCst), 2);
+
+    // Cache is dropped on update
+    rbac_service.updater_copy().update(&rbac_service);
+    assert!(rbac_service.has_permission(&tenant_user, Orders::Order::Read).is_ok());
+    assert_eq!(calls.load(Ordering::SeqCst), 3);
+
+    // Denied check reporting unknown roles resolves them once
+    builder.set_report_unknown_roles(true);
+    let rbac_service = builder.build();
+    assert!(matches!(rbac_service.has_permission(&stranger, Orders::Order::Read), Err(RbacError::UnknownRoles { .. })));
+    assert_eq!(calls.load(Ordering::SeqCst), 4);
+
+    // Cache is bounded, so most of 2000 unknown roles are resolved again
+    let check_all = || {
+        for i in 0..2000 {
+            rbac_service.has_permission_with_roles(&[format!("Unknown{}", i).as_str()], Orders::Order::Read).unwrap_err();
+        }
+    };
+    check_all();
+    let resolved = calls.load(Ordering::SeqCst);
+    check_all();
+    assert!(calls.load(Ordering::SeqCst) - resolved >= 2000 - 1024);
+}
+
+#[test]
+fn test_role_resolver_during_update() {
+    use std::sync::{
+        Arc, Barrier, Mutex,
+        atomic::{AtomicBool, Ordering},
+    };
+
+    // Resolver reads role from backend, then waits while roles are updated (and backend changed)
+    let backend = Arc::new(Mutex::new(vec!["Orders::Order::Read".to_string()]));
+    let (pause, barrier) = (Arc::new(AtomicBool::new(true)), Arc::new(Barrier::new(2)));
+    let mut builder = RbacService::builder();
+    let (resolver_backend, resolver_pause, resolver_barrier) = (backend.clone(), pause.clone(), barrier.clone());
+    builder.set_role_resolver(move |role_name: &str| {
+        let role = Role::new(role_name, resolver_backend.lock().unwrap().clone());
+        if resolver_pause.swap(false, Ordering::SeqCst) {
+            resolver_barrier.wait();
+            resolver_barrier.wait();
+        }
+        Some(role)
+    });
+    let rbac_service = Arc::new(builder.build());
+
+    let tenant_user = User {
+        name: "tenant_user".to_string(),
+        roles: vec!["Tenant42Reader".to_string()],
+    };
+    let check = {
+        let (rbac_service, tenant_user) = (rbac_service.clone(), tenant_user.clone());
+        std::thread::spawn(move || rbac_service.has_permission(&tenant_user, Orders::Order::Read))
+    };
+    barrier.wait();
+    backend.lock().unwrap().clear();
+    rbac_service.updater_copy().update(&rbac_service);
+    barrier.wait();
+    // Check started before update is evaluated against role resolved then
+    assert!(check.join().unwrap().is_ok());
+
+    // But role it resolved isn't cached for updated policy
+    assert!(rbac_service.has_permission(&tenant_user, Orders::Order::Read).is_err());
+}
+
+#[test]
+fn test_report_unknown_roles() {
+    let mut builder = RbacService::builder();
+    builder
+        .add_role(Role::new("Reader", vec!["Orders::Order::Read".to_string()]))
+        .set_report_unknown_roles(true);
+    let rbac_service = builder.build();
+
+    let user = User {
+        name: "user".to_string(),
+        roles: vec!["Reader".to_string(), "Writer".to_string()],
+    };
+    assert!(rbac_service.has_permission(&user, Orders::Order::Read).is_ok());
+    assert_eq!(
+        rbac_service.has_permission(&user, Orders::Order::Update),
+        Err(RbacError::UnknownRoles {
+            permission: "Orders::Order::Update".to_string(),
+            roles: vec!["Writer".to_string()],
+        })
+    );
+
+    let reader = User {
+        name: "reader".to_string(),
+        roles: vec!["Reader".to_string()],
+    };
+    assert_eq!(
+        rbac_service.has_permission(&reader, Orders::Order::Update),
+        Err(RbacError::PermissionDenied("Orders::Order::Update".to_string()))
+    );
+}
+
+#[test]
+fn test_fallback_roles_validation() {
+    let mut builder = RbacService::builder();
+    builder.add_role(Role::new("Guest", vec!["Templates::Template::Read".to_string()]));
+
+    // Default "Default" fallback role doesn't exist
+    assert_eq!(
+        builder.build().build_warnings(),
+        &[BuildWarning::MissingFallbackRole("Default".to_string())]
+    );
+
+    builder.set_fallback_check(Severity::Error);
+    assert_eq!(
+        builder.try_build().err(),
+        Some(RbacError::MissingFallbackRoles(vec!["Default".to_string()]))
+    );
+
+    builder.set_fallback_roles(vec!["Guest".to_string()]);
+    let rbac_service = builder.try_build().unwrap();
+    assert!(rbac_service.build_warnings().is_empty());
+
+    let anonymous = User {
+        name: "anonymous".to_string(),
+        roles: vec![],
+    };
+    assert!(rbac_service.has_permission(&anonymous, Templates::Template::Read).is_ok());
+}
+
+#[test]
+fn test_compile_warnings() {
+    let patterns = vec![
+        "Orders::Order::Read".to_string(),
+        "Orders::Order::*".to_string(),
+        "Orders::Invoice::{}".to_string(),
+        "Orders::*::Read".to_string(),
+        "Users::User::Read if tenant ==".to_string(),
+        "Users::User::{Read,Update}".to_string(),
+    ];
+    let (compiled, warnings) = CompiledPermissions::compile_with_warnings(&patterns);
+    assert_eq!(compiled.to_patterns(), CompiledPermissions::compile(&patterns).to_patterns());
+    assert_eq!(
+        warnings,
+        vec![
+            CompileWarning::ShadowedPattern {
+                pattern: "Orders::Order::Read".to_string(),
+                by: "Orders::Order::*".to_string(),
+            },
+            CompileWarning::EmptyActionSet("Orders::Invoice::{}".to_string()),
+            CompileWarning::MalformedPattern("Orders::*::Read".to_string()),
+            CompileWarning::MalformedPattern("Users::User::Read if tenant ==".to_string()),
+        ]
+    );
+
+    let mut builder = RbacService::builder();
+    builder
+        .add_role(Role::new("Manager", patterns))
+        .add_role(Role::new("Default", vec![]))
+        .set_compile_check(Severity::Error);
+    assert!(matches!(builder.try_build(), Err(RbacError::CompileWarning { role, .. }) if role == "Manager"));
+    builder.set_compile_check(Severity::Warn);
+    let rbac_service = builder.try_build().unwrap();
+    assert_eq!(rbac_service.build_warnings().len(), 4);
+    assert_eq!(
+        rbac_service.build_warnings()[1].to_string(),
+        "Role Manager: empty action set Orders::Invoice::{}"
+    );
+}
+
+#[test]
+fn test_roles_csv() {
+    let csv = "Role,Permission\r\nOrderManager,Orders::Order::*\n\nOrderManager,\"Orders::Invoice::{Read,Generate}\"\nAuditor,\"Orders::Order::Read if subject.tenant == \"\"acme\"\"\"\nEmpty,\n";
+    let mut builder = RbacService::builder();
+    builder.load_roles_csv(csv).unwrap();
+    let rbac_service = builder.build();
+    assert!(rbac_service.has_permission_with_roles(&["OrderManager"], Orders::Invoice::Generate).is_ok());
+    let roles = rbac_service.snapshot().roles;
+    let patterns: Vec<(&str, &[String])> = roles.iter().map(|role| (role.name.as_str(), role.permissions.as_slice())).collect();
+    assert_eq!(
+        patterns,
+        vec![
+            ("Auditor", &["Orders::Order::Read if subject.tenant == \"acme\"".to_string()][..]),
+            ("Empty", &[][..]),
+            ("OrderManager", &["Orders::Order::*".to_string(), "Orders::Invoice::{Read,Generate}".to_string()][..]),
+        ]
+    );
+
+    let exported = rbac_service.export_roles_csv().unwrap();
+    assert!(exported.starts_with("role,permission\nAuditor,\"Orders::Order::Read if subject.tenant == \"\"acme\"\"\"\nEmpty,\n"));
+    let mut builder = RbacService::builder();
+    builder.load_roles_csv(&exported).unwrap();
+    assert_eq!(builder.build().export_roles_csv(), Ok(exported));
+
+    // Deny role would be loaded back as granting one
+    let mut builder = RbacService::builder();
+    builder.add_role(Role::new_deny("NoOrders", vec!["Orders::*".to_string()]));
+    assert!(matches!(builder.build().export_roles_csv(), Err(RbacError::InvalidRoleData(e)) if e.contains("NoOrders")));
+
+    // Rejected role adds no roles
+    let mut builder = RbacService::builder();
+    builder.set_duplicate_policy(DuplicateRolePolicy::Error).add_role(Role::new("Admin", vec!["*".to_string()]));
+    assert!(matches!(builder.load_roles_csv("Clerk,Orders::Order::Read\nAdmin,Users::*\n"), Err(RbacError::DuplicateRole(_))));
+    assert_eq!(builder.build().get_roles().len(), 1);
+
+    assert!(matches!(RbacService::builder().load_roles_csv("Admin,*,extra"), Err(RbacError::InvalidRoleData(e)) if e.contains("line 1")));
+    assert!(RbacService::builder().load_roles_csv("Admin,\"*").is_err());
+}
+
+#[test]
+fn test_roles_matrix() {
+    let matrix = "role,Orders::Order::Read,Orders::Order::Cancel,Orders::Invoice::Send\nClerk,X,,\nSupervisor,x,X,X\nNobody,,\n";
+    let mut builder = RbacService::builder();
+    builder.register_domains((Orders,));
+    builder.load_roles_matrix(matrix).unwrap();
+    let rbac_service = builder.build();
+    assert!(rbac_service.has_permission_with_roles(&["Clerk"], Orders::Order::Read).is_ok());
+    assert!(rbac_service.has_permission_with_roles(&["Clerk"], Orders::Order::Cancel).is_err());
+    assert!(rbac_service.has_permission_with_roles(&["Supervisor"], Orders::Invoice::Send).is_ok());
+    assert_eq!(rbac_service.get_roles().len(), 3);
+
+    let mut builder = RbacService::builder();
+    builder.register_domains((Orders,));
+    let typo = builder.load_roles_matrix("role,Orders::Order::Raed\nClerk,X\n").err();
+    assert!(matches!(typo, Some(RbacError::UnknownPermission { suggestions, .. }) if suggestions == vec!["Orders::Order::Read"]));
+    assert!(matches!(builder.load_roles_matrix("role,Orders::*\n"), Err(RbacError::InvalidPermission(_))));
+    assert!(matches!(builder.load_roles_matrix("role,Orders::Order::Read\nClerk,yes\n"), Err(RbacError::InvalidRoleData(e)) if e.contains("line 2")));
+    assert!(builder.build().get_roles().is_empty());
+}
+
+#[test]
+fn test_from_config() {
+    let config: RbacConfig = serde_json::from_value(serde_json::json!({
+        "roles": [
+            {"name": "Guest", "permissions": ["Orders::Order::View"]},
+            {"name": "Admin", "permissions": ["*"]},
+        ],
+        "fallback": ["Guest"],
+        "options": {
+            "usage_stats": true,
+            "protected_roles": ["Admin"],
+            "quotas": {"Orders::Order::Cancel": "1/day"},
+            "permission_aliases": {"Orders::Order::View": "Orders::Order::Read"},
+        },
+    }))
+    .unwrap();
+    let rbac_service = RbacServiceBuilder::from_config(&config).unwrap().build();
+    let nobody = User {
+        name: "nobody".to_string(),
+        roles: vec![],
+    };
+    let admin = User {
+        name: "admin".to_string(),
+        roles: vec!["Admin".to_string()],
+    };
+    assert!(rbac_service.has_permission(&nobody, Orders::Order::Read).is_ok());
+    assert!(rbac_service.has_permission(&admin, Orders::Order::Cancel).is_ok());
+    assert!(matches!(rbac_service.has_permission(&admin, Orders::Order::Cancel), Err(RbacError::QuotaExceeded { .. })));
+    assert_eq!(rbac_service.usage_stats()["Orders::Order::Read"].allowed, 1);
+    assert!(matches!(rbac_service.patch_role("Admin", |_| {}), Err(RbacError::ProtectedRole(_))));
+
+    // Empty config is valid
+    let config: RbacConfig = serde_json::from_str("{}").unwrap();
+    assert!(RbacServiceBuilder::from_config(&config).unwrap().build().get_roles().is_empty());
+    let config: RbacConfig = serde_json::from_str(r#"{"options": {"quotas": {"Orders::Order::Read": "many"}}}"#).unwrap();
+    assert!(matches!(RbacServiceBuilder::from_config(&config), Err(RbacError::InvalidQuota(_))));
+}
+
+#[cfg(feature = "clap")]
+#[test]
+fn test_clap_parsers() {
+    use clap::{Arg, Command};
+
+    let command = Command::new("rbac-admin")
+        .arg(Arg::new("permission").long("permission").value_parser(permission_parser::<Orders::Order>()))
+        .arg(Arg::new("any").long("any").value_parser(CatalogPermissionParser::new(setup_rbac().catalog())))
+        .arg(Arg::new("role").long("role").value_parser(clap::value_parser!(RoleArg)));
+
+    let matches = command
+        .clone()
+        .try_get_matches_from(["rbac-admin", "--permission", "Orders::Order::Read", "--any", "Users::User::Read", "--role", "billing:Admin"])
+        .unwrap();
+    assert_eq!(matches.get_one::<Orders::Order>("permission"), Some(&Orders::Order::Read));
+    assert_eq!(matches.get_one::<String>("any").map(String::as_str), Some("Users::User::Read"));
+    assert_eq!(matches.get_one::<RoleArg>("role"), Some(&RoleArg("billing:Admin".to_string())));
+
+    let error = command.clone().try_get_matches_from(["rbac-admin", "--permission", "Orders::Order::Raed"]).unwrap_err();
+    assert!(error.to_string().contains("did you mean Orders::Order::Read?"));
+    assert!(command.clone().try_get_matches_from(["rbac-admin", "--any", "Orders::Order::Delete"]).is_err());
+    assert!(command.clone().try_get_matches_from(["rbac-admin", "--role", "bad role"]).is_err());
+
+    let permission = command.get_arguments().find(|arg| arg.get_id() == "permission").unwrap();
+    let values: Vec<String> = permission.get_possible_values().iter().map(|value| value.get_name().to_string()).collect();
+    assert_eq!(values, vec!["Orders::Order::Read", "Orders::Order::Create", "Orders::Order::Update", "Orders::Order::Cancel"]);
+}
+
+#[test]
+fn test_compile_order_independent() {
+    let narrow_first = CompiledPermissions::compile(&vec!["Orders::Order::Read".to_string(), "Orders::*".to_string()]);
+    let wildcard_first = CompiledPermissions::compile(&vec!["Orders::*".to_string(), "Orders::Order::Read".to_string()]);
+    assert_eq!(narrow_first.to_patterns(), vec!["Orders::*"]);
+    assert_eq!(narrow_first.to_patterns(), wildcard_first.to_patterns());
+}
+
+fn pattern_strategy() -> impl proptest::strategy::Strategy<Value = String> {
+    use proptest::prelude::*;
+
+    let name = |names: &'static [&'static str]| proptest::sample::select(names);
+    prop_oneof![
+        Just("*".to_string()),
+        name(&["Orders", "Users"]).prop_map(|domain| format!("{domain}::*")),
+        (name(&["Orders", "Users"]), name(&["Order", "Invoice"])).prop_map(|(domain, object)| format!("{domain}::{object}::*")),
+        (name(&["Orders", "Users"]), name(&["Order", "Invoice"]), name(&["Read", "Update", "Cancel"]))
+            .prop_map(|(domain, object, action)| format!("{domain}::{object}::{action}")),
+        (name(&["Orders", "Users"]), name(&["Order", "Invoice"]), proptest::sample::subsequence(vec!["Read", "Update", "Cancel"], 1..=3))
+            .prop_map(|(domain, object, actions)| format!("{domain}::{object}::{{{}}}", actions.join(","))),
+    ]
+}
+
+/// Patterns and the same patterns in random order
+fn shuffled_patterns() -> impl proptest::strategy::Strategy<Value = (Vec<String>, Vec<String>)> {
+    use proptest::prelude::*;
+
+    proptest::collection::vec(pattern_strategy(), 0..8).prop_flat_map(|patterns| (Just(patterns.clone()), Just(patterns).prop_shuffle()))
+}
+
+proptest::proptest! {
+    #[test]
+    fn prop_compile_order_independent((patterns, shuffled) in shuffled_patterns()) {
+        let compiled = CompiledPermissions::compile(&patterns);
+        let reordered = CompiledPermissions::compile(&shuffled);
+        proptest::prop_assert_eq!(compiled.to_patterns(), reordered.to_patterns());
+        for domain in ["Orders", "Users"] {
+            for object in ["Order", "Invoice"] {
+                for action in ["Read", "Update", "Cancel"] {
+                    proptest::prop_assert_eq!(compiled.matches(domain, object, action), reordered.matches(domain, object, action));
+                }
+            }
+        }
+        // Canonical patterns compile back to the same permissions
+        proptest::prop_assert_eq!(CompiledPermissions::compile(&compiled.to_patterns()).to_patterns(), compiled.to_patterns());
+    }
+}
+
+#[test]
+fn test_has_permission_with_roles() {
+    let rbac_service = setup_rbac();
+
+    assert!(
+        rbac_service
+            .has_permission_with_roles(&["TemplateCreator", "OrderManager"], Orders::Invoice::Generate)
+            .is_ok()
+    );
+    assert!(
+        rbac_service
+            .has_permission_with_roles(&["TemplateCreator"], Orders::Invoice::Generate)
+            .is_err()
+    );
+    // No roles, no "Default" fallback role
+    assert!(
+        rbac_service
+            .has_permission_with_roles(&[], Templates::Template::Read)
+            .is_err()
+    );
+}
+
+#[test]
+fn test_has_permission_str() {
+    let rbac_service = setup_rbac();
+
+    let order_mgr = User {
+        name: "order_manager".to_string(),
+        roles: vec!["OrderManager".to_string()],
+    };
+    assert!(rbac_service.has_permission_str(&order_mgr, "Orders::Invoice::Read").is_ok());
+    assert_eq!(
+        rbac_service.has_permission_str(&order_mgr, "Orders::Invoice::Send"),
+        Err(RbacError::PermissionDenied("Orders::Invoice::Send".to_string()))
+    );
+    assert_eq!(
+        rbac_service.has_permission_str(&order_mgr, "Orders::Invoice::Print"),
+        Err(RbacError::UnknownPermission {
+            permission: "Orders::Invoice::Print".to_string(),
+            suggestions: vec![],
+        })
+    );
+    let typo = rbac_service.has_permission_str(&order_mgr, "Orders::Invoice::Raed").unwrap_err();
+    assert_eq!(
+        typo,
+        RbacError::UnknownPermission {
+            permission: "Orders::Invoice::Raed".to_string(),
+            suggestions: vec!["Orders::Invoice::Read".to_string()],
+        }
+    );
+    assert_eq!(typo.to_string(), "Unknown permission: Orders::Invoice::Raed (did you mean Orders::Invoice::Read?)");
+    assert_eq!(
+        rbac_service.has_permission_str(&order_mgr, "Orders::*"),
+        Err(RbacError::InvalidPermission("Orders::*".to_string()))
+    );
+
+    // Without registry any well-formed permission is checked
+    let mut builder = RbacService::builder();
+    builder.add_role(Role::new("Printer", vec!["Orders::Invoice::Print".to_string()]));
+    let unregistered = builder.build();
+    let printer = User {
+        name: "printer".to_string(),
+        roles: vec!["Printer".to_string()],
+    };
+    assert!(unregistered.has_permission_str(&printer, "Orders::Invoice::Print").is_ok());
+}
+
+#[test]
+fn test_route_map() {
+    let rbac_service = setup_rbac();
+    let route_map: RouteMap = serde_json::from_str(
+        r#"[
+            {"method": "GET", "path": "/orders/{id}/invoice", "permission": "Orders::Invoice::Read"},
+            {"method": "POST", "path": "/orders/*/invoice/send", "permission": "Orders::Invoice::Send"},
+            {"method": "*", "path": "/users/**", "permission": "Users::User::Read"}
+        ]"#,
+    )
+    .unwrap();
+
+    assert_eq!(route_map.lookup("get", "/orders/42/invoice"), Some("Orders::Invoice::Read"));
+    assert_eq!(route_map.lookup("DELETE", "/users/42/profile"), Some("Users::User::Read"));
+    assert_eq!(route_map.lookup("GET", "/orders/42"), None);
+
+    let order_mgr = User {
+        name: "order_manager".to_string(),
+        roles: vec!["OrderManager".to_string()],
+    };
+    assert!(route_map.check(&rbac_service, &order_mgr, "GET", "/orders/42/invoice").is_ok());
+    assert_eq!(
+        route_map.check(&rbac_service, &order_mgr, "POST", "/orders/42/invoice/send"),
+        Err(RbacError::PermissionDenied("Orders::Invoice::Send".to_string()))
+    );
+    assert_eq!(
+        route_map.check(&rbac_service, &order_mgr, "GET", "/orders/42"),
+        Err(RbacError::UnmappedRoute("GET /orders/42".to_string()))
+    );
+}
+
+#[cfg(feature = "warp")]
+#[tokio::test]
+async fn test_warp_with_permission() {
+    use ::warp::Filter;
+    use std::sync::Arc;
+
+    let rbac_service = Arc::new(setup_rbac());
+    let subject = ::warp::header::<String>("x-roles").map(|roles: String| User {
+        name: "user".to_string(),
+        roles: roles.split(',').map(str::to_string).collect(),
+    });
+    let route = subject
+        .and_then(crate::warp::with_permission(rbac_service, Orders::Invoice::Generate))
+        .map(|user: User| user.name)
+        .recover(crate::warp::handle_rejection);
+
+    let allowed = ::warp::test::request().header("x-roles", "OrderManager").reply(&route).await;
+    assert_eq!(allowed.status(), 200);
+
+    let denied = ::warp::test::request().header("x-roles", "UserManager").reply(&route).await;
+    assert_eq!(denied.status(), 403);
+}
+
+#[cfg(feature = "rocket")]
+mod rocket_support {
+    use super::User;
+    use crate::{
+        example::test::Orders,
+        rocket::{Permit, RequiredPermission},
+    };
+    use ::rocket::{
+        http::Status,
+        request::{FromRequest, Outcome},
+    };
+
+    #[::rocket::async_trait]
+    impl<'r> FromRequest<'r> for User {
+        type Error = ();
+
+        async fn from_request(request: &'r ::rocket::Request<'_>) -> Outcome<Self, ()> {
+            match request.headers().get_one("x-roles") {
+                Some(roles) => Outcome::Success(User {
+                    name: "user".to_string(),
+                    roles: roles.split(',').map(str::to_string).collect(),
+                }),
+                None => Outcome::Error((Status::Unauthorized, ())),
+            }
+        }
+    }
+
+    pub struct GenerateInvoice;
+
+    impl RequiredPermission for GenerateInvoice {
+        type Subject = User;
+        type Permission = Orders::Invoice;
+        const PERMISSION: Orders::Invoice = Orders::Invoice::Generate;
+    }
+
+    #[::rocket::get("/invoice")]
+    pub fn invoice(permit: Permit<GenerateInvoice>) -> String {
+        permit.into_subject().name
+    }
+
+    #[::rocket::get("/frozen/invoice")]
+    pub fn frozen_invoice(permit: Permit<GenerateInvoice, crate::FrozenRoles>) -> String {
+        permit.into_subject().name
+    }
+}
+
+#[cfg(feature = "rocket")]
+#[test]
+fn test_rocket_permit() {
+    use ::rocket::{
+        http::{Header, Status},
+        local::blocking::Client,
+    };
+    use rocket_support::*;
+
+    let rocket = ::rocket::build()
+        .manage(setup_rbac())
+        .mount("/", ::rocket::routes![invoice]);
+    let client = Client::untracked(rocket).unwrap();
+
+    let allowed = client.get("/invoice").header(Header::new("x-roles", "OrderManager")).dispatch();
+    assert_eq!(allowed.status(), Status::Ok);
+
+    let denied = client.get("/invoice").header(Header::new("x-roles", "UserManager")).dispatch();
+    assert_eq!(denied.status(), Status::Forbidden);
+
+    assert_eq!(client.get("/invoice").dispatch().status(), Status::Unauthorized);
+
+    // Frozen service is found by its storage
+    let rocket = ::rocket::build()
+        .manage(setup_rbac().freeze())
+        .mount("/", ::rocket::routes![frozen_invoice]);
+    let client = Client::untracked(rocket).unwrap();
+    let allowed = client.get("/frozen/invoice").header(Header::new("x-roles", "OrderManager")).dispatch();
+    assert_eq!(allowed.status(), Status::Ok);
+    let denied = client.get("/frozen/invoice").header(Header::new("x-roles", "UserManager")).dispatch();
+    assert_eq!(denied.status(), Status::Forbidden);
+}
+
+#[cfg(feature = "tower")]
+#[tokio::test]
+async fn test_tower_layer() {
+    use crate::tower::RbacLayer;
+    use std::{
+        future::{Ready, poll_fn, ready},
+        sync::{
+            Arc,
+            atomic::{AtomicUsize, Ordering},
+        },
+        task::{Context, Poll},
+    };
+    use tower_layer::Layer;
+    use tower_service::Service;
+
+    /// Reserves slot of shared counter when made ready, like concurrency limit does
+    struct Echo {
+        reserved: Arc<AtomicUsize>,
+        ready: bool,
+    }
+
+    impl Clone for Echo {
+        fn clone(&self) -> Self {
+            Echo {
+                reserved: self.reserved.clone(),
+                ready: false,
+            }
+        }
+    }
+
+    impl Drop for Echo {
+        fn drop(&mut self) {
+            if self.ready {
+                self.reserved.fetch_sub(1, Ordering::SeqCst);
+            }
+        }
+    }
+
+    impl Service<(Vec<String>, &'static str)> for Echo {
+        type Response = &'static str;
+        type Error = RbacError;
+        type Future = Ready<Result<&'static str, RbacError>>;
+
+        fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), RbacError>> {
+            if !self.ready {
+                self.ready = true;
+                self.reserved.fetch_add(1, Ordering::SeqCst);
+            }
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, (_, path): (Vec<String>, &'static str)) -> Self::Future {
+            if std::mem::take(&mut self.ready) {
+                self.reserved.fetch_sub(1, Ordering::SeqCst);
+            }
+            ready(Ok(path))
+        }
+    }
+
+    let layer = RbacLayer::new(Arc::new(setup_rbac()), |(roles, path): &(Vec<String>, &'static str)| {
+        let permission = match *path {
+            "/public" => return None,
+            _ => "Orders::Invoice::Generate".to_string(),
+        };
+        let subject = User {
+            name: "user".to_string(),
+            roles: roles.clone(),
+        };
+        Some((subject, permission))
+    });
+    let reserved = Arc::new(AtomicUsize::new(0));
+    let mut service = layer.layer(Echo {
+        reserved: reserved.clone(),
+        ready: false,
+    });
+
+    let order_mgr = vec!["OrderManager".to_string()];
+    let user_mgr = vec!["UserManager".to_string()];
+    assert_eq!(service.call((order_mgr, "/invoice")).await, Ok("/invoice"));
+    assert_eq!(
+        service.call((user_mgr.clone(), "/invoice")).await,
+        Err(RbacError::PermissionDenied("Orders::Invoice::Generate".to_string()))
+    );
+    assert_eq!(service.call((user_mgr.clone(), "/public")).await, Ok("/public"));
+
+    // Readiness reserved for denied request is released
+    poll_fn(|cx| service.poll_ready(cx)).await.unwrap();
+    assert_eq!(reserved.load(Ordering::SeqCst), 1);
+    assert!(service.call((user_mgr, "/invoice")).await.is_err());
+    assert_eq!(reserved.load(Ordering::SeqCst), 0);
+}
+
+#[test]
+fn test_authorize_message() {
+    let rbac_service = setup_rbac();
+
+    assert!(authorize_message(&rbac_service, "UserManager, OrderManager", Orders::Invoice::Read).is_ok());
+    assert!(authorize_message(&rbac_service, "UserManager", Orders::Invoice::Read).is_err());
+    // No roles in headers, checked against (missing) fallback roles
+    assert!(authorize_message(&rbac_service, " , ", Orders::Invoice::Read).is_err());
+}
+
+#[test]
+fn test_session() {
+    let rbac_service = setup_rbac();
+    let user = User {
+        name: "user".to_string(),
+        roles: vec!["UserManager".to_string(), "TemplateCreator".to_string()],
+    };
+
+    let session = RbacSession::new(&rbac_service, &user);
+    assert!(session.can(Users::User::Create));
+    assert!(!session.can(Orders::Order::Read));
+    assert_eq!(
+        session.require(Orders::Order::Read),
+        Err(RbacError::PermissionDenied("Orders::Order::Read".to_string()))
+    );
+    assert!(session.list().contains(&"Users::User::*".to_string()));
+
+    // Session notices roles update
+    let generation = rbac_service.generation();
+    let mut updater = rbac_service.updater_copy();
+    updater.add_role(Role::new("UserManager", vec!["Orders::Order::Read".to_string()]));
+    updater.update(&rbac_service);
+    assert_eq!(rbac_service.generation(), generation + 1);
+
+    assert!(session.can(Orders::Order::Read));
+    assert!(!session.can(Users::User::Create));
+}
+
+#[test]
+fn test_deny_roles() {
+    let mut builder = RbacService::builder();
+    builder
+        .add_role(Role::new("OrderManager", vec!["Orders::*".to_string()]))
+        .add_role(Role::new_deny("Suspended", vec!["*".to_string()]))
+        .add_role(Role::new_deny("NoInvoices", vec!["Orders::Invoice::*".to_string()]));
+    let rbac_service = builder.build();
+
+    let user = User {
+        name: "user".to_string(),
+        roles: vec!["OrderManager".to_string(), "NoInvoices".to_string()],
+    };
+    assert!(rbac_service.has_permission(&user, Orders::Order::Read).is_ok());
+    assert!(rbac_service.has_permission(&user, Orders::Invoice::Read).is_err());
+
+    let suspended = User {
+        name: "suspended".to_string(),
+        roles: vec!["OrderManager".to_string(), "Suspended".to_string()],
+    };
+    assert!(rbac_service.has_permission(&suspended, Orders::Order::Read).is_err());
+    assert!(!RbacSession::new(&rbac_service, &suspended).can(Orders::Order::Read));
+
+    // Deny flag survives serialization
+    let json = serde_json::to_string(&Role::new_deny("Suspended", vec!["*".to_string()])).unwrap();
+    assert!(serde_json::from_str::<Role>(&json).unwrap().deny);
+}
+
+#[test]
+fn test_role_priority() {
+    let mut builder = RbacService::builder();
+    builder
+        .add_role(Role::new("OrderManager", vec!["Orders::*".to_string()]))
+        .add_role(Role::new("InvoiceOverride", vec!["Orders::Invoice::Read".to_string()]).with_priority(10))
+        .add_role(Role::new_deny("Suspended", vec!["*".to_string()]).with_priority(5));
+    let rbac_service = builder.build();
+
+    let user = User {
+        name: "user".to_string(),
+        roles: vec!["OrderManager".to_string(), "Suspended".to_string(), "InvoiceOverride".to_string()],
+    };
+    // Higher priority grant beats deny, deny beats lower priority grants
+    assert!(rbac_service.has_permission(&user, Orders::Invoice::Read).is_ok());
+    assert!(rbac_service.has_permission(&user, Orders::Order::Read).is_err());
+
+    let session = RbacSession::new(&rbac_service, &user);
+    assert!(session.can(Orders::Invoice::Read));
+    assert!(!session.can(Orders::Order::Read));
+}
+
+#[test]
+fn test_analyze_conflicts() {
+    let mut builder = RbacService::builder();
+    builder
+        .add_role(Role::new("OrderManager", vec!["Orders::*".to_string()]))
+        .add_role(Role::new("InvoiceReader", vec!["Orders::Invoice::Read".to_string()]).with_priority(10))
+        .add_role(Role::new_deny("NoInvoices", vec!["Orders::Invoice::{Read,Send}".to_string()]))
+        .register_permissions::<Orders::Invoice>();
+    let rbac_service = builder.build();
+
+    let conflicts = rbac_service.analyze_conflicts();
+    assert_eq!(conflicts.len(), 2);
+    assert_eq!(
+        conflicts[0],
+        PolicyConflict {
+            permission: "Orders::Invoice::Read".to_string(),
+            allowed_by: vec!["InvoiceReader".to_string(), "OrderManager".to_string()],
+            denied_by: vec!["NoInvoices".to_string()],
+            denied: false,
+        }
+    );
+    assert_eq!(conflicts[1].permission, "Orders::Invoice::Send");
+    assert!(conflicts[1].denied);
+}
+
+#[test]
+fn test_usage_stats() {
+    let mut builder = RbacService::builder();
+    builder
+        .add_role(Role::new("OrderManager", vec!["Orders::Order::*".to_string()]))
+        .add_role(Role::new("Guest", vec!["Orders::Order::Read".to_string()]))
+        .set_fallback_roles(vec!["Guest".to_string()])
+        .set_usage_stats(true)
+        .register_permissions::<Orders::Order>();
+    let rbac_service = builder.build();
+    let user = User {
+        name: "user".to_string(),
+        roles: vec!["OrderManager".to_string()],
+    };
+    let guest = User {
+        name: "guest".to_string(),
+        roles: vec![],
+    };
+
+    assert!(rbac_service.has_permission(&user, Orders::Order::Read).is_ok());
+    assert!(RbacSession::new(&rbac_service, &user).can(Orders::Order::Read));
+    assert!(rbac_service.has_permission(&user, Orders::Invoice::Read).is_err());
+    assert!(rbac_service.has_permission(&guest, Orders::Order::Read).is_ok());
+    assert!(rbac_service.has_permission(&guest, Orders::Order::Cancel).is_err());
+
+    let stats = rbac_service.usage_stats();
+    let read = &stats["Orders::Order::Read"];
+    assert_eq!((read.checks, read.allowed, read.fallback_allowed), (3, 3, 1));
+    assert_eq!(stats["Orders::Order::Cancel"].checks, 1);
+    assert_eq!(
+        rbac_service.explain(&guest, Orders::Order::Read).to_string(),
+        "allow Orders::Order::Read subject=guest roles=Guest fallback matched=Guest(Orders::Order::Read)"
+    );
+    assert!(read.last_checked.is_some());
+    // Registered, but never checked
+    assert_eq!(stats["Orders::Order::Update"], PermissionUsage::default());
+    assert_eq!(stats["Orders::Invoice::Read"].allowed, 0);
+
+    // Permission strings may come from outside, so counters of unregistered ones are bounded
+    let mut builder = RbacService::builder();
+    builder.add_role(Role::new("OrderManager", vec!["Orders::Order::*".to_string()])).set_usage_stats(true);
+    let rbac_service = builder.build();
+    for i in 0..MAX_UNREGISTERED_PERMISSIONS + 10 {
+        assert!(rbac_service.has_permission_str(&user, &format!("Orders::Order::Action{}", i)).is_ok());
+    }
+    assert!(rbac_service.has_permission_str(&user, "Orders::Order::Action0").is_ok());
+    let stats = rbac_service.usage_stats();
+    assert_eq!(stats.len(), MAX_UNREGISTERED_PERMISSIONS);
+    // Nor do maps of domains and object types of uncounted ones
+    let mut builder = RbacService::builder();
+    builder.add_role(Role::new("Admin", vec!["*".to_string()])).set_usage_stats(true);
+    let rbac_service = builder.build();
+    let admin = User {
+        name: "admin".to_string(),
+        roles: vec!["Admin".to_string()],
+    };
+    for i in 0..MAX_UNREGISTERED_PERMISSIONS + 10 {
+        assert!(rbac_service.has_permission_str(&admin, &format!("Domain{}::Object{}::Read", i, i)).is_ok());
+    }
+    let usage = rbac_service.usage.as_ref().unwrap();
+    assert_eq!(usage.domains(), MAX_UNREGISTERED_PERMISSIONS);
+    assert_eq!(rbac_service.usage_stats().len(), MAX_UNREGISTERED_PERMISSIONS);
+    assert_eq!(stats["Orders::Order::Action0"].checks, 2);
+    assert!(!stats.contains_key(&format!("Orders::Order::Action{}", MAX_UNREGISTERED_PERMISSIONS + 5)));
+
+    assert!(setup_rbac().usage_stats().is_empty());
+}
+
+#[test]
+fn test_mine_roles() {
+    let mut builder = RbacService::builder();
+    builder
+        .add_role(Role::new("OrderManager", vec!["Orders::Order::*".to_string()]))
+        .add_role(Role::new("Billing", vec!["Orders::Invoice::{Read,Generate}".to_string()]))
+        .register_domains((Orders,));
+    let rbac_service = builder.build();
+    let alice = User {
+        name: "alice".to_string(),
+        roles: vec!["OrderManager".to_string(), "Billing".to_string()],
+    };
+    let bob = User {
+        name: "bob".to_string(),
+        roles: vec!["OrderManager".to_string()],
+    };
+
+    let decisions = vec![
+        rbac_service.explain(&alice, Orders::Order::Read),
+        rbac_service.explain(&alice, Orders::Order::Update),
+        rbac_service.explain(&alice, Orders::Invoice::Read),
+        rbac_service.explain(&alice, Orders::Invoice::Send),
+        rbac_service.explain(&bob, Orders::Order::Read),
+        rbac_service.explain(&bob, Orders::Order::Update),
+    ];
+    let usage = std::collections::BTreeMap::from([(
+        "Orders::Order::Cancel".to_string(),
+        PermissionUsage {
+            checks: 1,
+            allowed: 1,
+            ..Default::default()
+        },
+    )]);
+    let report = rbac_service.mine_roles(&decisions, &usage);
+
+    assert_eq!(report.candidate_roles.len(), 1);
+    assert_eq!(report.candidate_roles[0].permissions, vec!["Orders::Order::Read", "Orders::Order::Update"]);
+    assert_eq!(report.candidate_roles[0].subjects, vec!["alice", "bob"]);
+    let unused: Vec<(&str, &str)> = report
+        .unused_grants
+        .iter()
+        .map(|grant| (grant.role.as_str(), grant.permission.as_str()))
+        .collect();
+    assert_eq!(unused, vec![("Billing", "Orders::Invoice::Generate"), ("OrderManager", "Orders::Order::Create")]);
+}
+
+#[test]
+fn test_impact_of_removing() {
+    let mut builder = RbacService::builder();
+    builder
+        .add_role(Role::new("OrderManager", vec!["Orders::Order::*".to_string()]))
+        .add_role(Role::new("LegacyOrders", vec!["Orders::Order::Read".to_string(), "Orders::Invoice::Read".to_string()]))
+        .add_role(Role::new("Guest", vec!["Orders::Order::Read".to_string()]))
+        .set_fallback_roles(vec!["Guest".to_string()]);
+    let rbac_service = builder.build();
+    let alice = User {
+        name: "alice".to_string(),
+        roles: vec!["OrderManager".to_string(), "LegacyOrders".to_string()],
+    };
+    let bob = User {
+        name: "bob".to_string(),
+        roles: vec!["LegacyOrders".to_string()],
+    };
+    let recorded = vec![
+        rbac_service.explain(&alice, Orders::Order::Read),
+        rbac_service.explain(&alice, Orders::Invoice::Read),
+        rbac_service.explain(&bob, Orders::Order::Read),
+        rbac_service.explain(&bob, Orders::Invoice::Read),
+        rbac_service.explain(&bob, Orders::Order::Cancel),
+    ];
+
+    let impact = rbac_service.impact_of_removing("LegacyOrders", &recorded);
+    assert_eq!(impact.allowed, 4);
+    // bob falls back to Guest, still reading orders
+    let lost: Vec<(&str, &str)> = impact
+        .lost
+        .iter()
+        .map(|decision| (decision.subject.as_deref().unwrap(), decision.permission.as_str()))
+        .collect();
+    assert_eq!(lost, vec![("alice", "Orders::Invoice::Read"), ("bob", "Orders::Invoice::Read")]);
+    assert_eq!(impact.affected_subjects(), vec!["alice", "bob"]);
+    assert!(!impact.is_safe());
+
+    assert!(rbac_service.impact_of_removing("OrderManager", &recorded[..1]).is_safe());
+    let carol = User {
+        name: "carol".to_string(),
+        roles: vec![],
+    };
+    assert!(!rbac_service.impact_of_removing("Guest", &[rbac_service.explain(&carol, Orders::Order::Read)]).is_safe());
+
+    // Quota used up (or flag turned off) since the check isn't loss caused by removal
+    let mut builder = RbacService::builder();
+    builder
+        .add_role(Role::new("A", vec!["Orders::Invoice::Generate".to_string()]))
+        .add_role(Role::new("B", vec!["Orders::Invoice::{Generate,Send}".to_string()]))
+        .add_quota("Orders::Invoice::Generate", Quota::parse("1/day").unwrap())
+        .gate_permissions("Orders::Invoice::Send", "invoices")
+        .set_flag_provider(|_: &str| true);
+    let rbac_service = builder.build();
+    let dave = User {
+        name: "dave".to_string(),
+        roles: vec!["A".to_string(), "B".to_string()],
+    };
+    let recorded = vec![rbac_service.explain(&dave, Orders::Invoice::Generate)];
+    assert!(rbac_service.has_permission(&dave, Orders::Invoice::Generate).is_ok());
+    assert!(rbac_service.has_permission(&dave, Orders::Invoice::Generate).is_err());
+    assert!(rbac_service.impact_of_removing("A", &recorded).is_safe());
+    let recorded = rbac_service.explain(&dave, Orders::Invoice::Send);
+    assert!(recorded.allowed);
+    builder.set_flag_provider(|_: &str| false);
+    let rbac_service = builder.build();
+    assert!(rbac_service.impact_of_removing("A", &[recorded]).is_safe());
+}
+
+#[test]
+fn test_permission_aliases() {
+    let mut builder = RbacService::builder();
+    builder
+        .add_role(Role::new("Viewer", vec!["Orders::Order::{View,Update}".to_string(), "Legacy::Ticket::Close".to_string()]))
+        .add_permission_alias("Orders::Order::View", "Orders::Order::Read")
+        .add_permission_alias("Legacy::Ticket::Close", "Orders::Order::Cancel")
+        .register_domains((Orders,));
+    let rbac_service = builder.build();
+    let user = User {
+        name: "user".to_string(),
+        roles: vec!["Viewer".to_string()],
+    };
+
+    assert!(rbac_service.has_permission(&user, Orders::Order::Read).is_ok());
+    assert!(rbac_service.has_permission(&user, Orders::Order::Update).is_ok());
+    assert!(rbac_service.has_permission(&user, Orders::Order::Cancel).is_ok());
+    assert!(rbac_service.has_permission(&user, Orders::Order::Create).is_err());
+    assert!(rbac_service.has_permission_str(&user, "Orders::Order::View").is_ok());
+    assert!(rbac_service.has_permission_str(&user, "Legacy::Ticket::Close").is_ok());
+    let viewer = rbac_service.get_roles().into_iter().find(|role| role.name == "Viewer").unwrap();
+    assert_eq!(viewer.permissions[0], "Orders::Order::{View,Update}");
+
+    let mut updater = rbac_service.updater_copy();
+    updater.add_role(Role::new("Closer", vec!["Legacy::Ticket::Close".to_string()]));
+    assert!(updater.try_update(&rbac_service).is_ok());
+    assert!(rbac_service.has_permission_with_roles(&["Closer"], Orders::Order::Cancel).is_ok());
+}
+
+#[cfg(feature = "otel")]
+#[test]
+fn test_otel_events() {
+    use opentelemetry::trace::{Tracer, TracerProvider};
+    use opentelemetry_sdk::trace::{InMemorySpanExporter, SdkTracerProvider};
+
+    let exporter = InMemorySpanExporter::default();
+    let provider = SdkTracerProvider::builder().with_simple_exporter(exporter.clone()).build();
+    let tracer = provider.tracer("rbacrab");
+
+    let rbac_service = setup_rbac();
+    let user = User {
+        name: "user".to_string(),
+        roles: vec!["OrderManager".to_string()],
+    };
+    tracer.in_span("request", |_| {
+        assert!(rbac_service.has_permission(&user, Orders::Order::Read).is_ok());
+        rbac_service.updater_copy().update(&rbac_service);
+    });
+
+    let spans = exporter.get_finished_spans().unwrap();
+    let events: Vec<&str> = spans[0].events.iter().map(|event| event.name.as_ref()).collect();
+    assert_eq!(events, ["rbac.check", "rbac.roles_swap"]);
+    let check = &spans[0].events[0];
+    assert!(check.attributes.contains(&opentelemetry::KeyValue::new("rbac.allowed", true)));
+    assert!(check.attributes.contains(&opentelemetry::KeyValue::new("rbac.fallback_used", false)));
+}
+
+#[cfg(feature = "json")]
+#[test]
+fn test_audit_sinks() {
+    use std::sync::{Arc, Mutex};
+
+    let dir = std::env::temp_dir().join(format!("rbacrab-audit-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("audit.log");
+    let _ = std::fs::remove_file(&path);
+
+    let records = Arc::new(Mutex::new(Vec::new()));
+    let collected = records.clone();
+    let mut builder = RbacService::builder();
+    builder
+        .add_role(Role::new("OrderManager", vec!["Orders::*".to_string()]))
+        .add_audit_sink(move |decision: &RbacDecision| collected.lock().unwrap().push(decision.clone()))
+        .add_audit_sink(JsonLinesAuditSink::new(&path, 250, 1).unwrap());
+    let rbac_service = builder.build();
+
+    let user = User {
+        name: "alice".to_string(),
+        roles: vec!["OrderManager".to_string()],
+    };
+    assert!(rbac_service.has_permission(&user, Orders::Order::Read).is_ok());
+    assert!(rbac_service.has_permission(&user, Users::User::Read).is_err());
+
+    let records = records.lock().unwrap();
+    assert_eq!(records.len(), 2);
+    assert_eq!(records[1].to_string(), "deny Users::User::Read subject=alice roles=OrderManager");
+
+    // Second line didn't fit into size limit, so first one was rotated
+    let current = std::fs::read_to_string(&path).unwrap();
+    let rotated = std::fs::read_to_string(dir.join("audit.log.1")).unwrap();
+    let line: serde_json::Value = serde_json::from_str(rotated.trim()).unwrap();
+    assert_eq!(line["permission"], "Orders::Order::Read");
+    assert_eq!(line["allowed"], true);
+    assert!(current.contains("\"allowed\":false"));
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_audited_checks_agree() {
+    use std::sync::{Arc, Mutex};
+
+    // Audited checks are evaluated by other code path, which has to decide the same
+    let decisions = Arc::new(Mutex::new(Vec::new()));
+    let build = |audited: bool| {
+        let mut builder = RbacService::builder();
+        builder
+            .add_role(Role::new("Clerk", vec!["Orders::Order::*".to_string(), "Orders::Invoice::{Read,Generate,Send}".to_string()]))
+            .add_role(Role::new_deny("NoCancel", vec!["Orders::Order::Cancel".to_string()]))
+            .add_role(Role::new("Acme", vec!["Orders::Invoice::Read if subject.tenant == \"acme\"".to_string()]))
+            .add_role(Role::new("Guest", vec!["Orders::Order::Read".to_string()]))
+            .set_fallback_roles(vec!["Guest".to_string()])
+            .set_report_unknown_roles(true)
+            .add_quota("Orders::Invoice::Generate", Quota::parse("1/day").unwrap())
+            .gate_permissions("Orders::Invoice::Send", "invoices");
+        if audited {
+            let sink = decisions.clone();
+            builder.add_audit_sink(move |decision: &RbacDecision| sink.lock().unwrap().push(decision.clone()));
+        }
+        builder.build()
+    };
+    let (plain, audited) = (build(false), build(true));
+    let subject = |roles: &[&str], tenant: &str| {
+        Subject::new("alice", roles.iter().map(|role| role.to_string()).collect())
+            .with_attributes(Attributes::from([("tenant".to_string(), tenant.to_string())]))
+    };
+    let subjects = [
+        subject(&["Clerk"], "acme"),
+        subject(&["Clerk", "NoCancel"], "acme"),
+        subject(&["Acme"], "acme"),
+        subject(&["Acme"], "globex"),
+        subject(&["Missing"], "acme"),
+        subject(&[], "acme"),
+        Subject::anonymous(),
+    ];
+    let permissions: Vec<Box<dyn PermissionCore>> = vec![
+        Box::new(Orders::Order::Read),
+        Box::new(Orders::Order::Cancel),
+        Box::new(Orders::Invoice::Read),
+        Box::new(Orders::Invoice::Generate),
+        Box::new(Orders::Invoice::Generate),
+        Box::new(Orders::Invoice::Send),
+    ];
+    for subject in &subjects {
+        for permission in &permissions {
+            let result = audited.has_permission(subject, permission);
+            assert_eq!(plain.has_permission(subject, permission), result, "{} {}", subject.name, permission.full_name());
+            assert_eq!(decisions.lock().unwrap().last().unwrap().allowed, result.is_ok());
+        }
+    }
+    assert_eq!(decisions.lock().unwrap().len(), subjects.len() * permissions.len());
+}
+
+#[test]
+fn test_decision_generation_matches_roles() {
+    use std::sync::Arc;
+
+    // Generation stamped on decision is the one of roles it was evaluated against, even under concurrent updates
+    let mut builder = RbacService::builder();
+    builder.add_role(Role::new("Reader", vec!["Orders::Order::Read".to_string()]));
+    let rbac_service = Arc::new(builder.build());
+    let updates = {
+        let rbac_service = rbac_service.clone();
+        std::thread::spawn(move || {
+            for generation in 1..=200 {
+                // Odd generations take permission away
+                let permission = if generation % 2 == 1 { "Orders::Order::Update" } else { "Orders::Order::Read" };
+                let mut updater = rbac_service.updater_clean();
+                updater.add_role(Role::new("Reader", vec![permission.to_string()]));
+                updater.update(&rbac_service);
+            }
+        })
+    };
+    let reader = User {
+        name: "reader".to_string(),
+        roles: vec!["Reader".to_string()],
+    };
+    while !updates.is_finished() {
+        let decision = rbac_service.explain(&reader, Orders::Order::Read);
+        assert_eq!(decision.allowed, decision.generation.is_multiple_of(2), "generation {}", decision.generation);
+    }
+    updates.join().unwrap();
+}
+
+#[test]
+fn test_render_allow_matrix() {
+    let mut builder = RbacService::builder();
+    builder
+        .register_domains((Templates,))
+        .add_role(Role::new("Editor", vec!["Templates::Template::{Read,Write}".to_string()]))
+        .add_role(Role::new("Admin", vec!["Templates::*".to_string()]));
+    let rbac_service = builder.build();
+
+    let expected = "\
+role Admin
+  allow Templates::Template::Create  Templates::*
+  allow Templates::Template::Delete  Templates::*
+  allow Templates::Template::Read    Templates::*
+  allow Templates::Template::Write   Templates::*
+
+role Editor
+  deny  Templates::Template::Create
+  deny  Templates::Template::Delete
+  allow Templates::Template::Read    Templates::Template::Read
+  allow Templates::Template::Write   Templates::Template::Write
+";
+    assert_eq!(rbac_service.render_allow_matrix(), expected);
+
+    // Updates change rendering only through roles
+    let mut updater = rbac_service.updater_copy();
+    updater.add_role(Role::new("Admin", vec!["Templates::*".to_string()]));
+    updater.update(&rbac_service);
+    assert_eq!(rbac_service.render_allow_matrix(), expected);
+
+    let mut updater = rbac_service.updater_copy();
+    updater.add_role(Role::new_deny("NoDelete", vec!["Templates::Template::Delete".to_string()]));
+    updater.update(&rbac_service);
+    rbac_service.patch_role("Editor", |role| role.enabled = false).unwrap();
+    let rendered = rbac_service.render_allow_matrix();
+    assert!(rendered.contains("role Editor (inactive)\n  deny  Templates::Template::Create\n"));
+    assert!(rendered.contains("\
+role NoDelete (deny)
+  deny  Templates::Template::Create
+  denies Templates::Template::Delete  Templates::Template::Delete
+"));
+}
+
+#[test]
+fn test_check_mutations() {
+    let mut builder = RbacService::builder();
+    builder
+        .register_domains((Orders,))
+        .add_role(Role::new("OrderManager", vec!["Orders::Order::*".to_string()]))
+        .add_role(Role::new("Viewer", vec!["Orders::Order::Read".to_string(), "Orders::Invoice::Read".to_string()]));
+    let rbac_service = builder.build();
+    let alice = Subject::new("alice", vec!["OrderManager".to_string()]);
+    let bob = Subject::new("bob", vec!["Viewer".to_string()]);
+
+    let expectations = [
+        PolicyExpectation::allow(&alice, "Orders::Order::Cancel"),
+        PolicyExpectation::deny(&alice, "Orders::Invoice::Read"),
+        PolicyExpectation::allow(&bob, "Orders::Order::Read"),
+        PolicyExpectation::deny(&bob, "Orders::Order::Cancel"),
+        PolicyExpectation::allow(&bob, "Orders::Invoice::Send"),
+    ];
+    let report = rbac_service.check_mutations(&expectations);
+    assert_eq!(report.mutations, 6);
+    assert_eq!(report.baseline_failures.len(), 1);
+    assert!(report.baseline_failures[0].contains("expected bob to be allowed Orders::Invoice::Send"));
+    // Nothing tests Viewer's invoice access
+    assert_eq!(
+        report.survived,
+        vec![
+            PolicyMutation::DropPattern {
+                role: "Viewer".to_string(),
+                pattern: "Orders::Invoice::Read".to_string(),
+            },
+            PolicyMutation::WidenPattern {
+                role: "Viewer".to_string(),
+                pattern: "Orders::Invoice::Read".to_string(),
+                widened: "Orders::Invoice::*".to_string(),
+            },
+        ]
+    );
+    assert_eq!(report.survived[1].to_string(), "widen Orders::Invoice::Read to Orders::Invoice::* in role Viewer");
+    assert!((report.score() - 4.0 / 6.0).abs() < 1e-9);
+}
+
+rbac_tests! {
+    mod order_policy {
+        roles {
+            "OrderManager" => ["Orders::*"],
+            "Viewer" => ["Orders::Order::Read", "Orders::Invoice::Read"],
+            "Default" => [],
+        }
+        subjects {
+            alice => ["OrderManager"],
+            bob => ["Viewer"],
+            guest => [],
+        }
+        tests {
+            manager_cancels_orders: alice allow Orders::Order::Cancel,
+            manager_cant_read_users: alice deny Users::User::Read,
+            viewer_reads_invoices: bob allow "Orders::Invoice::Read",
+            viewer_cant_send_invoices: bob deny Orders::Invoice::Send,
+            guest_denied: guest deny Orders::Order::Read,
+        }
+    }
+}
+
+#[test]
+fn test_check_policy() {
+    let mut builder = RbacService::builder();
+    builder.add_role(Role::new("Viewer", vec!["Orders::Order::Read".to_string()]));
+    let rbac_service = builder.build();
+    let bob = Subject::new("bob", vec!["Viewer".to_string()]);
+
+    assert_eq!(__private::check_policy(&rbac_service, &bob, "Orders::Order::Read", true), Ok(()));
+    assert_eq!(
+        __private::check_policy(&rbac_service, &bob, "Orders::Order::Cancel", true),
+        Err("policy test failed: expected bob to be allowed Orders::Order::Cancel, got deny Orders::Order::Cancel subject=bob roles=Viewer".to_string())
+    );
+    assert_eq!(
+        __private::check_policy(&rbac_service, &bob, "Orders::Order", false),
+        Err("policy test failed: Invalid permission: Orders::Order".to_string())
+    );
+}
+
+#[test]
+fn test_correlation_id() {
+    use std::sync::{Arc, Mutex};
+
+    let records = Arc::new(Mutex::new(Vec::new()));
+    let collected = records.clone();
+    let mut builder = RbacService::builder();
+    builder
+        .add_role(Role::new("OrderManager", vec!["Orders::*".to_string()]))
+        .add_audit_sink(move |decision: &RbacDecision| collected.lock().unwrap().push(decision.clone()));
+    let rbac_service = builder.build();
+
+    let user = Subject::new("alice", vec!["OrderManager".to_string()]);
+    assert!(rbac_service.has_permission(&user.clone().with_correlation_id("req-42"), Orders::Order::Read).is_ok());
+    assert!(rbac_service.has_permission(&user, Orders::Order::Read).is_ok());
+    // Correlation ID is carried by subject, so every check entry point reports it
+    assert!(RbacSession::new(&rbac_service, &user.clone().with_correlation_id("req-44")).can(Orders::Order::Cancel));
+
+    let records = records.lock().unwrap();
+    assert_eq!(records[0].correlation_id.as_deref(), Some("req-42"));
+    assert_eq!(records[0].to_string(), "allow Orders::Order::Read subject=alice roles=OrderManager matched=OrderManager(Orders::*) correlation=req-42");
+    assert_eq!(records[1].correlation_id, None);
+    assert_eq!(records[2].correlation_id.as_deref(), Some("req-44"));
+    assert_eq!(RbacDecision::from(DecisionDto::from(&records[0])).correlation_id.as_deref(), Some("req-42"));
+
+    let decision = rbac_service.explain(&user.with_correlation_id("req-43"), Users::User::Read);
+    assert!(!decision.allowed);
+    assert_eq!(decision.correlation_id.as_deref(), Some("req-43"));
+}
+
+#[cfg(feature = "reqwest")]
+#[tokio::test]
+async fn test_webhook_sink() {
+    use std::{
+        io::{BufRead, BufReader, Read, Write},
+        net::TcpListener,
+        sync::{Arc, Mutex},
+        time::Duration,
+    };
+
+    // Webhook failing first POST, so batch is delivered on retry
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}/audit", listener.local_addr().unwrap());
+    let bodies = Arc::new(Mutex::new(Vec::new()));
+    let received = bodies.clone();
+    std::thread::spawn(move || {
+        for (i, stream) in listener.incoming().enumerate() {
+            let mut reader = BufReader::new(stream.unwrap());
+            let mut length = 0;
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if line.trim().is_empty() {
+                    break;
+                }
+                if let Some(value) = line.to_ascii_lowercase().strip_prefix("content-length:") {
+                    length = value.trim().parse().unwrap();
+                }
+            }
+            let mut body = vec![0; length];
+            reader.read_exact(&mut body).unwrap();
+            received.lock().unwrap().push(String::from_utf8(body).unwrap());
+            let status = if i == 0 { "500 Internal Server Error" } else { "200 OK" };
+            write!(reader.get_mut(), "HTTP/1.1 {}\r\ncontent-length: 0\r\nconnection: close\r\n\r\n", status).unwrap();
+        }
+    });
+
+    let sink = WebhookSink::new(&url, WebhookOptions {
+        flush_interval: Duration::from_millis(50),
+        retry_backoff: Duration::from_millis(10),
+        ..WebhookOptions::default()
+    });
+    let mut builder = RbacService::builder();
+    builder
+        .add_role(Role::new("OrderManager", vec!["Orders::*".to_string()]))
+        .add_audit_sink(sink.clone())
+        .add_role_change_sink(sink.clone());
+    let rbac_service = builder.build();
+
+    let user = User {
+        name: "alice".to_string(),
+        roles: vec!["OrderManager".to_string()],
+    };
+    assert!(rbac_service.has_permission(&user, Orders::Order::Read).is_ok());
+    assert!(rbac_service.has_permission(&user, Users::User::Read).is_err());
+    let mut updater = rbac_service.updater_copy();
+    updater.add_role(Role::new("Viewer", vec!["Orders::Order::Read".to_string()]));
+    updater.update(&rbac_service);
+
+    for _ in 0..200 {
+        if sink.delivered() == 3 {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    assert_eq!((sink.delivered(), sink.dropped()), (3, 0));
+
+    let bodies = bodies.lock().unwrap();
+    assert_eq!(bodies.len(), 2);
+    assert_eq!(bodies[0], bodies[1]);
+    let events: serde_json::Value = serde_json::from_str(&bodies[1]).unwrap();
+    assert_eq!(events[0]["event"], "decision");
+    assert_eq!(events[0]["permission"], "Orders::Order::Read");
+    assert_eq!(events[1]["allowed"], false);
+    assert_eq!(events[2]["event"], "role_change");
+    assert_eq!(events[2]["kind"], "added");
+    assert_eq!(events[2]["role"], "Viewer");
+}
+
+#[cfg(feature = "json")]
+#[test]
+fn test_publisher_sink() {
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Clone, Default)]
+    struct Bus(Arc<Mutex<Vec<(String, serde_json::Value)>>>);
+
+    impl EventPublisher for Bus {
+        fn publish(&self, subject: &str, payload: Vec<u8>) {
+            self.0.lock().unwrap().push((subject.to_string(), serde_json::from_slice(&payload).unwrap()));
+        }
+    }
+
+    let bus = Bus::default();
+    let mut builder = RbacService::builder();
+    builder
+        .add_role(Role::new("OrderManager", vec!["Orders::*".to_string()]))
+        .add_audit_sink(PublisherSink::new(bus.clone(), "rbac"))
+        .add_role_change_sink(PublisherSink::new(bus.clone(), "rbac"));
+    let rbac_service = builder.build();
+
+    let user = User {
+        name: "alice".to_string(),
+        roles: vec!["OrderManager".to_string()],
+    };
+    assert!(rbac_service.has_permission(&user, Orders::Order::Read).is_ok());
+    assert!(rbac_service.has_permission(&user, Users::User::Read).is_err());
+    let mut updater = rbac_service.updater_copy();
+    updater.remove_role("OrderManager");
+    updater.update(&rbac_service);
+
+    let messages = bus.0.lock().unwrap();
+    let subjects: Vec<&str> = messages.iter().map(|(subject, _)| subject.as_str()).collect();
+    assert_eq!(subjects, ["rbac.decision.allowed", "rbac.decision.denied", "rbac.role.removed"]);
+    assert_eq!(messages[0].1["permission"], "Orders::Order::Read");
+    assert_eq!(messages[1].1["subject"], "alice");
+    assert_eq!(messages[2].1["role"], "OrderManager");
+    assert_eq!(messages[2].1["after"], serde_json::Value::Null);
+}
+
+#[test]
+fn test_explain() {
+    let mut builder = RbacService::builder();
+    builder
+        .add_role(Role::new("Default", vec!["Orders::Order::Read".to_string()]))
+        .add_role(Role::new("OrderManager", vec!["Orders::*".to_string()]))
+        .add_role(Role::new_deny("NoInvoices", vec!["Orders::Invoice::{Send}".to_string()]));
+    let rbac_service = builder.build();
+
+    let user = User {
+        name: "alice".to_string(),
+        roles: vec!["OrderManager".to_string(), "NoInvoices".to_string()],
+    };
+    let decision = rbac_service.explain(&user, Orders::Order::Cancel);
+    assert!(decision.allowed);
+    assert_eq!(decision.subject.as_deref(), Some("alice"));
+    assert_eq!(decision.matched_role.as_deref(), Some("OrderManager"));
+    assert_eq!(decision.matched_pattern.as_deref(), Some("Orders::*"));
+    assert!(!decision.fallback_used);
+
+    let decision = rbac_service.explain(&user, Orders::Invoice::Send);
+    assert!(!decision.allowed);
+    assert_eq!(decision.matched_role.as_deref(), Some("NoInvoices"));
+    assert_eq!(decision.matched_pattern.as_deref(), Some("Orders::Invoice::Send"));
+
+    let anonymous = User {
+        name: "anonymous".to_string(),
+        roles: vec![],
+    };
+    let decision = rbac_service.explain(&anonymous, Orders::Order::Read);
+    assert!(decision.allowed && decision.fallback_used);
+    assert_eq!(decision.roles, ["Default"]);
+    assert_eq!(decision.generation, rbac_service.generation());
+
+    let decision = rbac_service.explain_for_roles(&["OrderManager", "NoInvoices"], Orders::Invoice::Send);
+    assert!(!decision.allowed && decision.subject.is_none());
+    assert_eq!(decision.matched_role.as_deref(), Some("NoInvoices"));
+    assert!(rbac_service.explain_for_roles(&["OrderManager"], Orders::Invoice::Send).allowed);
+}
+
+#[test]
+fn test_permission_by_reference() {
+    use std::collections::HashMap;
+
+    let rbac_service = setup_rbac();
+    let order_mgr = User {
+        name: "order_manager".to_string(),
+        roles: vec!["OrderManager".to_string()],
+    };
+
+    // Permissions held in config map are checked without cloning
+    let routes = HashMap::from([("/invoices", Orders::Invoice::Read), ("/invoices/send", Orders::Invoice::Send)]);
+    assert!(rbac_service.has_permission(&order_mgr, &routes["/invoices"]).is_ok());
+    assert!(rbac_service.has_permission(&order_mgr, &routes["/invoices/send"]).is_err());
+    assert!(RbacSession::new(&rbac_service, &order_mgr).can(&routes["/invoices"]));
+    assert!(rbac_service.has_permission(&order_mgr, Orders::Order::Read).is_ok());
+}
+
+#[test]
+fn test_dyn_permissions() {
+    let rbac_service = setup_rbac();
+    let order_mgr = User {
+        name: "order_manager".to_string(),
+        roles: vec!["OrderManager".to_string()],
+    };
+
+    // Permissions of different domains in one table
+    let table: Vec<Box<dyn PermissionCore>> = vec![Box::new(Orders::Invoice::Read), Box::new(Users::User::Read)];
+    assert_eq!(table[1].to_permission_string(), "Users::User::Read");
+    assert!(rbac_service.has_permission(&order_mgr, &table[0]).is_ok());
+    assert!(rbac_service.has_permission(&order_mgr, &table[1]).is_err());
+}
+
+#[test]
+fn test_permission_domains() {
+    fn permission_count<D: PermissionDomain>() -> (&'static str, usize) {
+        (D::name(), D::all().len())
+    }
+
+    assert_eq!(permission_count::<Orders::Domain>(), ("Orders", 10));
+    assert!(Orders::Domain::all().iter().any(|info| info.full_name == "Orders::Invoice::Send"));
+
+    let mut builder = RbacService::builder();
+    Users::Domain::register(&mut builder);
+    let rbac_service = builder.build();
+    assert_eq!(rbac_service.get_all_permissions().len(), Users::Domain::all().len());
+}
+
+#[test]
+fn test_register_domains() {
+    let mut builder = RbacService::builder();
+    Users::register_all(&mut builder);
+    Orders::register_all(&mut builder);
+    let by_module = builder.build();
+
+    let mut builder = RbacService::builder();
+    builder.register_domains((Users, Orders));
+    let by_domains = builder.build();
+
+    let names = |service: &RbacService| -> Vec<String> {
+        service.get_all_permissions().iter().map(|info| info.full_name.clone()).collect()
+    };
+    assert_eq!(names(&by_module), names(&by_domains));
+    assert_eq!(setup_rbac().get_all_permissions().len(), names(&by_domains).len() + Templates::Domain::all().len());
+}
+
+#[test]
+fn test_catalog_diff() {
+    let old = setup_rbac().catalog();
+    assert!(PermissionCatalog::diff(&old, &old).is_empty());
+
+    // Next deployment: Invoice::Send renamed to Invoice::Deliver, OrderItem::Remove dropped, Invoice::Void added
+    let new: PermissionCatalog = old
+        .permissions()
+        .filter(|info| info.full_name != "Orders::OrderItem::Remove")
+        .cloned()
+        .map(|mut info| {
+            if info.full_name == "Orders::Invoice::Send" {
+                info.action = "Deliver".to_string();
+                info.full_name = "Orders::Invoice::Deliver".to_string();
+            }
+            info
+        })
+        .chain([PermissionInfo::of(&Orders::Invoice::Read)].map(|mut info| {
+            info.action = "Void".to_string();
+            info.full_name = "Orders::Invoice::Void".to_string();
+            info.description = "Void invoices".to_string();
+            info
+        }))
+        .collect();
+
+    let diff = PermissionCatalog::diff(&old, &new);
+    assert_eq!(diff.added, vec!["Orders::Invoice::Void"]);
+    assert_eq!(diff.removed, vec!["Orders::OrderItem::Remove"]);
+    assert_eq!(
+        diff.renamed,
+        vec![("Orders::Invoice::Send".to_string(), "Orders::Invoice::Deliver".to_string())]
+    );
+    assert_eq!(
+        diff.to_string(),
+        "+ Orders::Invoice::Void\n- Orders::OrderItem::Remove\n~ Orders::Invoice::Send -> Orders::Invoice::Deliver\n"
+    );
+
+    let roles = vec![
+        Role::new("Billing", vec!["Orders::Invoice::{Read,Send}".to_string()]),
+        Role::new("OrderAdmin", vec!["Orders::*::*".to_string()]),
+    ];
+    let affected = diff.affected_roles(&roles);
+    assert_eq!(affected.len(), 1);
+    assert_eq!(affected["Billing"], vec!["Orders::Invoice::Send"]);
+}
+
+#[test]
+fn test_role_migrator() {
+    let mut migrator = RoleMigrator::from_diff(&CatalogDiff {
+        removed: vec!["Orders::OrderItem::Remove".to_string()],
+        renamed: vec![("Orders::Invoice::Send".to_string(), "Orders::Invoice::Deliver".to_string())],
+        ..CatalogDiff::default()
+    });
+    migrator.rename("Users::User::Read", "Users::Account::Read");
+
+    let mut builder = RbacService::builder();
+    builder.register_domains((Users, Orders));
+    builder.load_roles(vec![
+        Role::new(
+            "Billing",
+            vec!["Orders::Invoice::{Read,Send}".to_string(), "Orders::OrderItem::Remove".to_string()],
+        ),
+        Role::new("OrderAdmin", vec!["Orders::*::*".to_string()]),
+    ]);
+
+    let mut roles = builder.build().get_roles();
+    roles.sort_by(|a, b| a.name.cmp(&b.name));
+    let rewrites = migrator.migrate(&mut roles);
+    assert_eq!(rewrites.len(), 2);
+    assert_eq!(rewrites[0].to_string(), "role Billing: Orders::Invoice::Send renamed to Orders::Invoice::Deliver");
+    assert_eq!(rewrites[1].to, None);
+    assert_eq!(roles[0].permissions, vec!["Orders::Invoice::{Deliver,Read}"]);
+    assert_eq!(roles[1].permissions, vec!["Orders::*::*"]);
+
+    let rbac_service = builder.migrate_roles(&migrator).build();
+    assert_eq!(
+        rbac_service.build_warnings().last().map(ToString::to_string).as_deref(),
+        Some("Migrated role Billing: Orders::OrderItem::Remove removed")
+    );
+    let billing = User {
+        name: "billing".to_string(),
+        roles: vec!["Billing".to_string()],
+    };
+    assert!(rbac_service.has_permission(&billing, Orders::Invoice::Read).is_ok());
+    assert!(rbac_service.has_permission(&billing, Orders::Invoice::Send).is_err());
+    assert!(rbac_service.has_permission(&billing, Orders::OrderItem::Remove).is_err());
+}
+
+#[test]
+fn test_policy_hash() {
+    let rbac_service = setup_rbac();
+    let hash = rbac_service.policy_hash();
+    assert_eq!(setup_rbac().policy_hash(), hash);
+
+    // Same policy, written differently and loaded in other order
+    let mut roles = rbac_service.get_roles();
+    roles.reverse();
+    let roles = roles
+        .into_iter()
+        .map(|role| {
+            let mut permissions = role.permissions.clone();
+            permissions.push(role.permissions[0].clone());
+            Role {
+                revision: role.revision + 1,
+                ..Role::new(&role.name, permissions)
+            }
+        })
+        .collect();
+    let mut builder = RbacService::builder();
+    builder.load_roles(roles);
+    assert_eq!(builder.build().policy_hash(), hash);
+
+    let mut updater = rbac_service.updater_copy();
+    updater.add_role(Role::new("Auditor", vec!["Orders::*::Read".to_string()]));
+    updater.update(&rbac_service);
+    assert_ne!(rbac_service.policy_hash(), hash);
+}
+
+#[test]
+fn test_policy_agreement() {
+    let fleet: Vec<RbacService> = (0..3).map(|_| setup_rbac()).collect();
+    let statuses = |fleet: &[RbacService]| -> Vec<PolicyStatus> {
+        fleet.iter().enumerate().map(|(i, svc)| svc.policy_status(&format!("node-{}", i))).collect()
+    };
+    assert!(PolicyStatus::agreement(&statuses(&fleet)).is_agreed());
+
+    // node-1 and node-2 got update, node-0 missed it
+    let mut updater = fleet[0].updater_copy();
+    updater.remove_role("UserManager");
+    updater.update(&fleet[1]);
+    updater.update(&fleet[2]);
+
+    let agreement = PolicyStatus::agreement(&statuses(&fleet));
+    assert_eq!(agreement.policy_hash, Some(fleet[1].policy_hash()));
+    assert_eq!(agreement.stale.len(), 1);
+    assert_eq!(agreement.stale[0].instance, "node-0");
+    assert_eq!(agreement.stale[0].generation, 0);
+    assert_eq!(PolicyStatus::agreement(&[]).policy_hash, None);
+}
+
+#[test]
+fn test_role_enabled() {
+    let rbac_service = setup_rbac();
+    let user = User {
+        name: "alice".to_string(),
+        roles: vec!["UserManager".to_string()],
+    };
+    assert!(rbac_service.has_permission(&user, Users::User::Create).is_ok());
+
+    let mut updater = rbac_service.updater_copy();
+    updater.set_role_enabled("UserManager", false);
+    updater.update(&rbac_service);
+    assert!(rbac_service.has_permission(&user, Users::User::Create).is_err());
+    assert!(RbacSession::new(&rbac_service, &user).list().is_empty());
+    assert_eq!(rbac_service.get_roles().iter().find(|role| role.name == "UserManager").unwrap().revision, 1);
+
+    // Disabled flag survives serialization, and roles stored before it existed load enabled
+    let role: Role = serde_json::from_str(r#"{"name":"Old","permissions":["Users::*::*"]}"#).unwrap();
+    assert!(role.enabled);
+    let stored = serde_json::to_string(&rbac_service.get_roles()).unwrap();
+    let roles: Vec<Role> = serde_json::from_str(&stored).unwrap();
+    assert!(roles.iter().any(|role| role.name == "UserManager" && !role.enabled));
+
+    let mut updater = rbac_service.updater_copy();
+    updater.set_role_enabled("UserManager", true);
+    updater.update(&rbac_service);
+    assert!(rbac_service.has_permission(&user, Users::User::Create).is_ok());
+}
+
+#[test]
+fn test_subject_conditions() {
+    struct Employee {
+        name: String,
+        roles: Vec<String>,
+        department: &'static str,
+    }
+
+    impl RbacSubject for Employee {
+        fn get_roles(&self) -> &Vec<String> {
+            &self.roles
+        }
+
+        fn name(&self) -> &str {
+            &self.name
+        }
+
+        fn attributes(&self) -> Attributes {
+            Attributes::from([("department".to_string(), self.department.to_string())])
+        }
+    }
+
+    let mut builder = RbacService::builder();
+    builder.add_role(Role::new(
+        "Approver",
+        vec![
+            "Orders::Order::Read".to_string(),
+            "Orders::Order::Update if subject.department == 'finance' && subject.department != 'audit'".to_string(),
+            "Orders::Order::Cancel if subject.level == \"senior\"".to_string(),
+            "Orders::Order::Create if department == 'finance'".to_string(),
+        ],
+    ));
+    let rbac_service = builder.build();
+    let employee = |department| Employee {
+        name: "bob".to_string(),
+        roles: vec!["Approver".to_string()],
+        department,
+    };
+    let (finance, sales) = (employee("finance"), employee("sales"));
+
+    assert!(rbac_service.has_permission(&finance, Orders::Order::Update).is_ok());
+    assert!(rbac_service.has_permission(&sales, Orders::Order::Update).is_err());
+    assert!(rbac_service.has_permission(&sales, Orders::Order::Read).is_ok());
+    // Missing attribute and malformed condition grant nothing
+    assert!(rbac_service.has_permission(&finance, Orders::Order::Cancel).is_err());
+    assert!(rbac_service.has_permission(&finance, Orders::Order::Create).is_err());
+    assert!(rbac_service.has_permission_with_roles(&["Approver"], Orders::Order::Update).is_err());
+
+    assert!(RbacSession::new(&rbac_service, &finance).can(Orders::Order::Update));
+    assert!(!RbacSession::new(&rbac_service, &sales).can(Orders::Order::Update));
+    assert_eq!(
+        rbac_service.explain(&finance, Orders::Order::Update).matched_pattern.as_deref(),
+        Some("Orders::Order::Update if subject.department == 'finance' && subject.department != 'audit'")
+    );
+
+    // Conditions survive serialization in canonical form
+    let role = rbac_service.get_roles().remove(0);
+    let role: Role = serde_json::from_str(&serde_json::to_string(&role).unwrap()).unwrap();
+    assert!(role.permissions.contains(&"Orders::Order::Cancel if subject.level == 'senior'".to_string()));
+    assert!(Condition::parse("subject.a = 'b'").is_err());
+}
+
+#[cfg(feature = "expressions")]
+#[test]
+fn test_condition_expressions() {
+    let mut builder = RbacService::builder();
+    // Attributes can only be compared with literals
+    let invalid = Role::new("Owner", vec!["Orders::Order::Cancel if context.owner == subject.name".to_string()]);
+    assert!(matches!(builder.try_add_role(invalid), Err(RbacError::InvalidCondition(_))));
+    builder
+        .try_add_role(Role::new(
+            "Approver",
+            vec![
+                "Orders::Order::Update if (subject.department in ['finance', 'audit'] || subject.level >= 3) && !context.region == 'eu'"
+                    .to_string(),
+            ],
+        ))
+        .unwrap();
+    let rbac_service = builder.build();
+
+    let user = User {
+        name: "carol".to_string(),
+        roles: vec!["Approver".to_string()],
+    };
+    let context = |region: &str| Attributes::from([("region".to_string(), region.to_string())]);
+    // Plain user has no attributes, so only context may be checked
+    assert!(rbac_service.has_permission_in_context(&user, Orders::Order::Update, context("us")).is_err());
+
+    let input = |department: &str, level: &str, region: &str| ConditionInput {
+        subject: Attributes::from([
+            ("department".to_string(), department.to_string()),
+            ("level".to_string(), level.to_string()),
+        ]),
+        context: context(region),
+        ..Default::default()
+    };
+    let condition = Condition::parse(
+        "(subject.department in ['finance', 'audit'] || subject.level >= 3) && !context.region == 'eu'",
+    )
+    .unwrap();
+    assert!(condition.evaluate(&input("audit", "1", "us")));
+    assert!(condition.evaluate(&input("sales", "3", "us")));
+    assert!(!condition.evaluate(&input("sales", "senior", "us")));
+    assert!(!condition.evaluate(&input("finance", "1", "eu")));
+    assert_eq!(Condition::parse(&condition.to_string()).unwrap(), condition);
+
+    assert!(Condition::parse(&format!("{}subject.a == 'b'{}", "(".repeat(100), ")".repeat(100))).is_err());
+    assert!(Condition::parse("subject.a in ['b',]").is_err());
+
+    // Missing attribute satisfies neither comparison nor its negation
+    let no_team = ConditionInput::default();
+    for condition in ["subject.team != 'a'", "!subject.team == 'a'", "!(subject.team in ['a'])", "!(subject.team == 'a' && subject.level >= 3)"] {
+        assert!(!Condition::parse(condition).unwrap().evaluate(&no_team), "{condition}");
+    }
+    // Unless outcome doesn't depend on it
+    assert!(Condition::parse("subject.team == 'a' || !context.region == 'eu'").unwrap().evaluate(&input("sales", "1", "us")));
+    assert!(Condition::parse("!(subject.team == 'a' && context.region == 'eu')").unwrap().evaluate(&input("sales", "1", "us")));
+}
+
+#[test]
+fn test_quotas() {
+    assert_eq!(Quota::parse("10/day"), Ok(Quota::new(10, std::time::Duration::from_secs(86400))));
+    assert!(Quota::parse("10 per day").is_err());
+
+    let mut builder = RbacService::builder();
+    builder
+        .add_role(Role::new("Billing", vec!["Orders::Invoice::*".to_string()]))
+        .add_quota("Orders::Invoice::Generate", Quota::parse("2/day").unwrap());
+    let rbac_service = builder.build();
+    let user = |name: &str| User {
+        name: name.to_string(),
+        roles: vec!["Billing".to_string()],
+    };
+    let (alice, bob) = (user("alice"), user("bob"));
+
+    assert!(rbac_service.has_permission(&alice, Orders::Invoice::Generate).is_ok());
+    // Only enforcing checks consume quota
+    let session = RbacSession::new(&rbac_service, &alice);
+    assert!(session.can(Orders::Invoice::Generate) && session.can(Orders::Invoice::Generate));
+    assert!(session.require(Orders::Invoice::Generate).is_ok());
+    assert!(!session.can(Orders::Invoice::Generate));
+    assert_eq!(
+        rbac_service.has_permission(&alice, Orders::Invoice::Generate),
+        Err(RbacError::QuotaExceeded {
+            permission: "Orders::Invoice::Generate".to_string(),
+            limit: 2
+        })
+    );
+    let decision = rbac_service.explain(&alice, Orders::Invoice::Generate);
+    assert!(!decision.allowed);
+    assert_eq!(decision.blocked_by.as_deref(), Some("Quota exceeded: Orders::Invoice::Generate (limit 2)"));
+    // Other subjects and permissions aren't affected
+    assert!(rbac_service.explain(&bob, Orders::Invoice::Generate).allowed);
+    assert!(rbac_service.has_permission(&bob, Orders::Invoice::Generate).is_ok());
+    assert!(rbac_service.has_permission(&alice, Orders::Invoice::Read).is_ok());
+    assert!(rbac_service.has_permission_with_roles(&["Billing"], Orders::Invoice::Generate).is_ok());
+
+    // Counters of expired windows are evicted
+    let store = MemoryQuotaStore::default();
+    for i in 0..1000 {
+        store.increment(&format!("user{}", i), "Orders::Invoice::Generate", std::time::Duration::from_millis(1));
+    }
+    std::thread::sleep(std::time::Duration::from_millis(5));
+    for i in 0..100 {
+        store.increment(&format!("guest{}", i), "Orders::Invoice::Generate", std::time::Duration::from_secs(60));
+    }
+    assert_eq!(store.len(), 100);
+    assert_eq!(store.uses("guest1", "Orders::Invoice::Generate", std::time::Duration::from_secs(60)), 1);
+}
+
+#[test]
+fn test_combination_cache() {
+    let mut builder = RbacService::builder();
+    builder
+        .add_role(Role::new("Reader", vec!["Orders::Order::Read".to_string()]))
+        .add_role(Role::new("Billing", vec!["Orders::Invoice::*".to_string()]))
+        .add_role(Role::new_deny("Frozen", vec!["Orders::Invoice::Send".to_string()]))
+        .set_combination_cache(2);
+    let rbac_service = builder.build();
+    let user = |roles: &[&str]| User {
+        name: "dave".to_string(),
+        roles: roles.iter().map(|role| role.to_string()).collect(),
+    };
+
+    // Same combination in other order hits the same entry
+    for roles in [["Reader", "Billing"], ["Billing", "Reader"]] {
+        assert!(rbac_service.has_permission(&user(&roles), Orders::Invoice::Send).is_ok());
+        assert!(rbac_service.has_permission(&user(&roles), Orders::Order::Create).is_err());
+    }
+    assert!(rbac_service.has_permission(&user(&["Billing", "Frozen"]), Orders::Invoice::Send).is_err());
+    assert!(rbac_service.has_permission(&user(&["Reader"]), Orders::Order::Read).is_ok());
+
+    let mut updater = rbac_service.updater_copy();
+    updater.add_role(Role::new("Reader", vec!["Orders::*".to_string()]));
+    updater.update(&rbac_service);
+    assert!(rbac_service.has_permission(&user(&["Billing", "Reader"]), Orders::Order::Create).is_ok());
+}
+
+#[test]
+fn test_permission_str_constants() {
+    const SEND: &str = Orders::Invoice::SEND_STR;
+    const READ: &str = Orders::Invoice::Read.permission_str();
+    assert_eq!(SEND, "Orders::Invoice::Send");
+    assert_eq!(READ, Orders::Invoice::Read.to_permission_string());
+    assert_eq!(Orders::OrderItem::REMOVE_STR, "Orders::OrderItem::Remove");
+    assert!(Orders::Invoice::all_permissions().iter().all(|p| p.permission_str() == p.to_string()));
+    assert_eq!("Orders::Invoice::Send".parse::<Orders::Invoice>(), Ok(Orders::Invoice::Send));
+    assert!(matches!("Orders::Order::Send".parse::<Orders::Invoice>(), Err(RbacError::InvalidPermission(_))));
+
+    let permission: Box<dyn PermissionCore> = Box::new(Orders::Invoice::Send);
+    assert_eq!(permission.full_name(), SEND);
+}
+
+define_permissions! {
+    /// Domain of legacy service with kebab-case permissions
+    #[rbac(string_case = "kebab")]
+    pub domain LegacyOrders {
+        OrderItem {
+            Read => "View order items",
+            BulkImport => "Import order items",
+        },
+        /// Invoice named as legacy service calls it
+        #[rbac(rename = "inv")]
+        Invoice {
+            Read => "View invoices",
+        },
+    }
+}
+
+define_permissions! {
+    #[rbac(string_case = "snake")]
+    pub domain BillingApi {
+        PaymentMethod {
+            Read => "View payment methods",
+        },
+    }
+}
+
+#[test]
+fn test_permission_string_case() {
+    assert_eq!(LegacyOrders::OrderItem::BULK_IMPORT_STR, "legacy-orders::order-item::bulk-import");
+    assert_eq!(LegacyOrders::Invoice::Read.to_string(), "legacy-orders::inv::read");
+    assert_eq!(BillingApi::PaymentMethod::Read.full_name(), "billing_api::payment_method::read");
+    let read = LegacyOrders::OrderItem::Read;
+    assert_eq!((read.domain(), PermissionCore::object_type(&read), read.action()), ("legacy-orders", "order-item", "read"));
+    assert_eq!(LegacyOrders::OrderItem::object_type(), "order-item");
+    assert_eq!(<LegacyOrders::Domain as PermissionDomain>::name(), "legacy-orders");
+    assert_eq!("legacy-orders::inv::read".parse::<LegacyOrders::Invoice>(), Ok(LegacyOrders::Invoice::Read));
+    assert!("LegacyOrders::Invoice::Read".parse::<LegacyOrders::Invoice>().is_err());
+    let handlers: Vec<(&str, u8)> = map_permissions! {
+        LegacyOrders::OrderItem::Read => 1,
+        LegacyOrders::OrderItem::BulkImport => 2,
+    };
+    assert_eq!(handlers[1].0, "legacy-orders::order-item::bulk-import");
+
+    // Roles are matched against converted strings
+    let mut builder = RbacService::builder();
+    builder
+        .register_domains((LegacyOrders,))
+        .add_role(Role::new("Importer", vec!["legacy-orders::order-item::*".to_string()]))
+        .add_role(Role::new("Accountant", vec!["legacy-orders::inv::read".to_string()]));
+    let rbac_service = builder.build();
+    assert!(rbac_service.get_all_permissions().iter().any(|info| info.full_name == "legacy-orders::order-item::bulk-import"));
+
+    let user = User {
+        name: "alice".to_string(),
+        roles: vec!["Importer".to_string()],
+    };
+    assert!(rbac_service.has_permission(&user, LegacyOrders::OrderItem::BulkImport).is_ok());
+    assert!(rbac_service.has_permission(&user, LegacyOrders::Invoice::Read).is_err());
+    assert!(rbac_service.has_permission_str(&user, "legacy-orders::order-item::read").is_ok());
+
+    let mut updater = rbac_service.updater_copy();
+    updater.add_role(Role::new("Viewer", vec!["legacy-orders::{order-item}::read".to_string(), "legacy-orders::*".to_string()]));
+    assert_eq!(updater.validate(&rbac_service).unwrap_err().len(), 1);
+    let mut updater = rbac_service.updater_copy();
+    updater.add_role(Role::new("Viewer", vec!["legacy-orders::order-item::{read,bulk-import}".to_string()]));
+    assert_eq!(updater.validate(&rbac_service), Ok(()));
+}
+
+#[test]
+fn test_search_permissions() {
+    let rbac_service = setup_rbac();
+    let names = |infos: Vec<PermissionInfo>| -> Vec<String> { infos.into_iter().map(|info| info.full_name).collect() };
+
+    assert_eq!(names(rbac_service.search_permissions("users::user::lock")), vec!["Users::User::Lock"]);
+    // Equally good matches are sorted by name
+    assert_eq!(names(rbac_service.search_permissions("templates::"))[0], "Templates::Template::Create");
+    assert_eq!(names(rbac_service.search_permissions("user"))[0], "Users::Method::Activate");
+    // Description match: "Send notifications"
+    assert_eq!(names(rbac_service.search_permissions("notifications")), vec!["Users::Notify::Write"]);
+    // Typo in object name
+    assert_eq!(
+        names(rbac_service.search_permissions("Templte")),
+        names(rbac_service.search_permissions("Templates::Template::"))
+    );
+
+    let mut query = PermissionQuery::new("read");
+    query.set_domain("Orders").set_object_type("Invoice");
+    assert_eq!(names(rbac_service.search_permissions(query.clone())), vec!["Orders::Invoice::Read"]);
+    let mut all = PermissionQuery::new("");
+    all.set_domain("Orders").set_limit(2);
+    assert_eq!(rbac_service.search_permissions(all).len(), 2);
+    assert!(rbac_service.search_permissions("nothing like it").is_empty());
+}
+
+#[test]
+fn test_pattern_validator() {
+    let validator = setup_rbac().pattern_validator();
+
+    let partial = validator.check_partial("Orders::Ord");
+    assert_eq!(partial.state, PatternState::Incomplete);
+    assert_eq!(partial.completions, vec!["Orders::Order::", "Orders::OrderItem::"]);
+    assert_eq!(validator.check_partial("Ord").completions, vec!["Orders::"]);
+    assert_eq!(
+        validator.check_partial("Orders::Invoice::").completions,
+        vec!["Orders::Invoice::*", "Orders::Invoice::Generate", "Orders::Invoice::Read", "Orders::Invoice::Send"]
+    );
+    assert_eq!(
+        validator.check_partial("Orders::Invoice::{Read,").completions,
+        vec!["Orders::Invoice::{Read,Generate", "Orders::Invoice::{Read,Send"]
+    );
+    assert_eq!(validator.check_partial("@machine Orders::Invoice::G").completions, vec!["@machine Orders::Invoice::Generate"]);
+
+    let valid = validator.check_partial("Orders::Invoice::{Read,Send}");
+    assert_eq!((valid.state, valid.completions.len()), (PatternState::Valid, 0));
+    assert_eq!(validator.check_partial("Orders::*").state, PatternState::Valid);
+    assert!(matches!(
+        validator.check_partial("Orders::Invoice::Raed").state,
+        PatternState::Invalid(RbacError::UnknownPermission { .. })
+    ));
+    assert!(matches!(validator.check_partial("Orders::*::Read").state, PatternState::Invalid(RbacError::InvalidPattern { .. })));
+    assert!(matches!(
+        validator.check_partial("Orders::Order::Read if subject.tenant ==").state,
+        PatternState::Invalid(RbacError::InvalidCondition(_))
+    ));
+}
+
+#[test]
+fn test_dtos() {
+    let rbac_service = setup_rbac();
+
+    let catalog = CatalogDto::from(&rbac_service.catalog());
+    let json = serde_json::to_string(&catalog).unwrap();
+    let catalog: PermissionCatalog = serde_json::from_str::<CatalogDto>(&json).unwrap().into();
+    assert!(PermissionCatalog::diff(&catalog, &rbac_service.catalog()).is_empty());
+
+    let role = Role::new_deny("Frozen", vec!["Orders::Invoice::Send".to_string(), "Orders::Invoice::Read".to_string()])
+        .with_priority(5);
+    let dto = RoleDto::from(&role);
+    assert_eq!(dto.permissions, vec!["Orders::Invoice::{Read,Send}"]);
+    let role: Role = serde_json::from_str::<RoleDto>(&serde_json::to_string(&dto).unwrap()).unwrap().into();
+    assert!(role.deny && role.priority == 5 && role.enabled);
+
+    let user = User {
+        name: "erin".to_string(),
+        roles: vec!["OrderManager".to_string()],
+    };
+    let decision = rbac_service.explain(&user, Orders::Invoice::Read);
+    let dto = DecisionDto::from(&decision);
+    assert_eq!(dto.matched_role.as_deref(), Some("OrderManager"));
+    assert_eq!(RbacDecision::from(dto.clone()).to_string(), decision.to_string());
+    assert_eq!(serde_json::from_str::<DecisionDto>(&serde_json::to_string(&dto).unwrap()).unwrap(), dto);
+}
+
+#[cfg(feature = "json")]
+#[test]
+fn test_openapi_scopes() {
+    let catalog = setup_rbac().catalog();
+    let scopes = catalog.scopes(&ScopeFormat::default());
+    assert_eq!(scopes["orders:order_item:read"], "View order items");
+    assert_eq!(scopes.len(), catalog.len());
+
+    let scheme = catalog.openapi_security_scheme(
+        &ScopeFormat::default(),
+        "clientCredentials",
+        &[("tokenUrl", "https://auth.example.com/token")],
+    );
+    assert_eq!(scheme["type"], "oauth2");
+    assert_eq!(scheme["flows"]["clientCredentials"]["tokenUrl"], "https://auth.example.com/token");
+    assert_eq!(scheme["flows"]["clientCredentials"]["scopes"]["orders:invoice:send"], "Send invoices to customers");
+
+    let format = ScopeFormat {
+        separator: ".".to_string(),
+        snake_case: false,
+    };
+    let requirement = catalog.openapi_security_requirement(&format, "oauth2", &["Orders::Invoice::Read", "Orders::Invoice::Send"]);
+    assert_eq!(requirement, serde_json::json!({"oauth2": ["Orders.Invoice.Read", "Orders.Invoice.Send"]}));
+}
+
+#[test]
+fn test_scope_mapper() {
+    let rbac_service = setup_rbac();
+    let mut mapper = ScopeMapper::default();
+    mapper.map_scope("orders", vec!["OrderManager".to_string()]);
+
+    assert!(rbac_service.has_permission_with_scopes(&mapper, "openid orders:order_item:read", Orders::OrderItem::Read).is_ok());
+    assert!(rbac_service.has_permission_with_scopes(&mapper, "openid orders:order_item:read", Orders::OrderItem::Add).is_err());
+    assert!(rbac_service.has_permission_with_scopes(&mapper, "openid orders", Orders::Invoice::Generate).is_ok());
+    assert!(rbac_service.has_permission_with_scopes(&mapper, "openid orders", Users::User::Read).is_err());
+    assert!(rbac_service.has_permission_with_scopes(&mapper, "", Orders::Order::Read).is_err());
+    assert_eq!(mapper.roles("orders users orders"), vec!["OrderManager"]);
+}
+
+#[test]
+fn test_scope_grant_with_deny_role() {
+    let mut builder = RbacService::builder();
+    builder
+        .register_domains((Users, Templates, Orders))
+        .add_role(Role::new("OrderReader", vec!["Orders::Order::Read".to_string()]))
+        .add_role(Role::new_deny("NoOrders", vec!["Orders::Order::*".to_string()]));
+    let rbac_service = builder.build();
+    let mut mapper = ScopeMapper::default();
+    mapper.map_scope("restricted", vec!["NoOrders".to_string()]).map_scope("reader", vec!["OrderReader".to_string()]);
+
+    assert!(rbac_service.has_permission_with_scopes(&mapper, "orders:order:read", Orders::Order::Read).is_ok());
+    // Deny role mapped from one scope wins over direct grant of other scope
+    assert!(rbac_service.has_permission_with_scopes(&mapper, "orders:order:read restricted", Orders::Order::Read).is_err());
+    assert!(rbac_service.has_permission_with_scopes(&mapper, "orders:order:read reader", Orders::Order::Read).is_ok());
+    assert!(rbac_service.has_permission_with_scopes(&mapper, "orders:order:update reader", Orders::Order::Update).is_ok());
+}
+
+#[test]
+fn test_group_role_map() {
+    let groups = GroupRoleMap::new(vec![
+        GroupRule::new("CN=Order Managers, OU=Groups, DC=example, DC=com", vec!["OrderManager".to_string()]),
+        GroupRule::new("cn=*-admins,ou=groups,dc=example,dc=com", vec!["Admin".to_string(), "UserManager".to_string()]),
+    ]);
+    let roles = groups.roles_for(&["cn=order managers,ou=groups,dc=example,dc=com", "cn=eu-admins,ou=groups,dc=example,dc=com"]);
+    assert_eq!(roles, vec!["Admin", "OrderManager", "UserManager"]);
+    assert!(groups.roles_for(&["cn=admins,ou=people,dc=example,dc=com"]).is_empty());
+
+    let user = User {
+        name: "frank".to_string(),
+        roles: groups.roles_for(&["cn=Order Managers,ou=Groups,dc=example,dc=com"]),
+    };
+    assert!(setup_rbac().has_permission(&user, Orders::Order::Read).is_ok());
+
+    groups.reload(vec![GroupRule::new("cn=order managers,ou=groups,dc=example,dc=com", vec!["TemplateCreator".to_string()])]);
+    assert_eq!(groups.roles_for(&["cn=order managers,ou=groups,dc=example,dc=com"]), vec!["TemplateCreator"]);
+}
+
+#[test]
+fn test_sync_roles() {
+    let rbac_service = setup_rbac();
+    let mut desired: Vec<Role> = rbac_service.get_roles().into_iter().filter(|role| role.name != "TemplateCreator").collect();
+    // Same permissions written differently aren't a change
+    for role in &mut desired {
+        if role.name == "OrderManager" {
+            *role = Role::new("OrderManager", vec!["Orders::Invoice::Generate".to_string(), "Orders::Invoice::Read".to_string(), "Orders::Order::*".to_string(), "Orders::OrderItem::*".to_string()]);
+        }
+        if role.name == "Admin" {
+            *role = Role::new("Admin", vec!["Orders::*".to_string()]);
+        }
+    }
+    desired.push(Role::new("Auditor", vec!["Orders::Invoice::Read".to_string()]));
+
+    let mut updater = rbac_service.updater_copy();
+    let report = updater.sync_roles(desired.clone()).unwrap();
+    assert_eq!(report.added, vec!["Auditor"]);
+    assert_eq!(report.updated, vec!["Admin"]);
+    assert_eq!(report.removed, vec!["TemplateCreator"]);
+    assert_eq!(report.to_string(), "1 added, 1 updated, 1 removed");
+    updater.update(&rbac_service);
+
+    let revision = |name: &str| rbac_service.get_roles().into_iter().find(|role| role.name == name).map(|role| role.revision);
+    assert_eq!(revision("OrderManager"), Some(0));
+    assert_eq!(revision("Admin"), Some(1));
+    assert_eq!(revision("TemplateCreator"), None);
+
+    assert!(rbac_service.updater_copy().sync_roles(desired.clone()).unwrap().is_empty());
+
+    // Desired state naming role twice is rejected as a whole
+    desired.push(Role::new("Auditor", vec!["Orders::*".to_string()]));
+    let mut updater = rbac_service.updater_copy();
+    assert_eq!(updater.sync_roles(desired), Err(RbacError::DuplicateRole("Auditor".to_string())));
+    assert_eq!(updater.revision("Auditor"), 1);
+}
+
+#[test]
+fn test_tombstoned_roles() {
+    let rbac_service = setup_rbac();
+    let user = User {
+        name: "grace".to_string(),
+        roles: vec!["OrderManager".to_string()],
+    };
+
+    let mut updater = rbac_service.updater_copy();
+    updater.tombstone_role("OrderManager");
+    updater.update(&rbac_service);
+    assert!(rbac_service.has_permission(&user, Orders::Order::Read).is_err());
+    let tombstoned = rbac_service.get_roles().into_iter().find(|role| role.name == "OrderManager").unwrap();
+    assert!(tombstoned.deleted_at.is_some() && !tombstoned.is_active());
+
+    // Tombstone survives serialization, live roles are written without it
+    let stored = serde_json::to_string(&tombstoned).unwrap();
+    assert!(stored.contains("deleted_at"));
+    assert_eq!(serde_json::from_str::<Role>(&stored).unwrap().deleted_at, tombstoned.deleted_at);
+    assert!(!serde_json::to_string(&Role::new("Live", vec![])).unwrap().contains("deleted_at"));
+
+    let mut updater = rbac_service.updater_copy();
+    updater.restore_role("OrderManager");
+    updater.update(&rbac_service);
+    assert!(rbac_service.has_permission(&user, Orders::Order::Read).is_ok());
+    assert_eq!(rbac_service.get_roles().into_iter().find(|role| role.name == "OrderManager").unwrap().revision, 2);
+}
+
+#[test]
+fn test_access_review() {
+    let rbac_service = setup_rbac();
+    let users = [
+        User {
+            name: "heidi".to_string(),
+            roles: vec!["OrderManager".to_string()],
+        },
+        User {
+            name: "ivan, jr".to_string(),
+            roles: vec!["UserManager".to_string(), "Admin".to_string()],
+        },
+    ];
+    let review = rbac_service.access_review(&users);
+    assert_eq!(review.subjects.len(), 2);
+
+    let heidi = &review.subjects[0];
+    assert_eq!(heidi.roles, ["OrderManager"]);
+    assert!(heidi.permissions.iter().all(|granted| granted.role == "OrderManager"));
+    let order = heidi.permissions.iter().find(|granted| granted.permission == "Orders::Order::Read").unwrap();
+    assert_eq!(order.pattern, "Orders::Order::*");
+    assert!(!heidi.permissions.iter().any(|granted| granted.permission == "Orders::Invoice::Send"));
+    assert_eq!(review.subjects[1].permissions.len(), rbac_service.get_all_permissions().len());
+
+    let csv = review.to_csv();
+    assert!(csv.starts_with("subject,permission,role,pattern\n"));
+    assert!(csv.contains("heidi,Orders::Order::Read,OrderManager,Orders::Order::*\n"));
+    assert!(csv.contains("\"ivan, jr\",Users::User::Read,"));
+    let json = serde_json::to_string(&review).unwrap();
+    assert_eq!(serde_json::from_str::<AccessReview>(&json).unwrap(), review);
+
+    // Roles are reported even without registered permissions
+    let mut builder = RbacService::builder();
+    builder.add_role(Role::new("Default", vec!["Orders::*".to_string()]));
+    let review = builder.build().access_review(&[Subject::new("kim", vec![])]);
+    assert_eq!(review.subjects[0].roles, ["Default"]);
+    assert!(review.subjects[0].permissions.is_empty());
+}
+
+#[test]
+fn test_policy_graph() {
+    let mut builder = RbacService::builder();
+    builder
+        .register_domains((Users, Templates, Orders))
+        .add_role(Role::new("InvoiceClerk", vec!["Orders::Invoice::{Read,Send}".to_string()]))
+        .add_role(Role::new_deny("NoSend", vec!["Orders::Invoice::Send".to_string()]))
+        .add_role(Role::new("Sender", vec!["Orders::Invoice::Send".to_string()]))
+        .add_role(Role::new("Quoted", vec!["Orders::Order::Read if subject.team == 'a\"b'".to_string()]));
+    let graph = builder.build().policy_graph();
+
+    let node = |id: &str| graph.nodes.iter().find(|node| node.id == id);
+    assert_eq!(node("role:InvoiceClerk").unwrap().kind, GraphNodeKind::Role);
+    assert!(node("role:NoSend").unwrap().deny);
+    assert!(node("pattern:Orders::Invoice::{Read,Send}").is_some());
+    assert!(node("permission:Orders::Invoice::Generate").is_none());
+    let edge = |from: &str, to: &str| graph.edges.iter().any(|edge| edge.from == from && edge.to == to);
+    assert!(edge("role:InvoiceClerk", "pattern:Orders::Invoice::{Read,Send}"));
+    assert!(edge("pattern:Orders::Invoice::{Read,Send}", "permission:Orders::Invoice::Read"));
+    // Pattern of deny role isn't merged with the same pattern granting permission
+    assert!(edge("role:NoSend", "deny-pattern:Orders::Invoice::Send"));
+    assert!(edge("deny-pattern:Orders::Invoice::Send", "permission:Orders::Invoice::Send"));
+    assert!(edge("role:Sender", "pattern:Orders::Invoice::Send"));
+    assert!(!node("pattern:Orders::Invoice::Send").unwrap().deny);
+
+    let dot = graph.to_dot();
+    assert!(dot.starts_with("digraph rbac {"));
+    assert!(dot.contains("\"role:NoSend\" [label=\"NoSend\", shape=box, color=red];"));
+    assert!(dot.contains("\"role:InvoiceClerk\" -> \"pattern:Orders::Invoice::{Read,Send}\";"));
+    assert!(dot.contains(r#"[label="Orders::Order::Read if subject.team == 'a\"b'", shape=ellipse];"#));
+    assert_eq!(serde_json::from_str::<PolicyGraph>(&serde_json::to_string(&graph).unwrap()).unwrap(), graph);
+}
+
+#[cfg(feature = "wasm")]
+#[test]
+fn test_wasm_abi() {
+    use crate::wasm::*;
+
+    let roles = serde_json::to_string(&vec![
+        Role::new("OrderManager", vec!["Orders::*".to_string()]),
+        Role::new_deny("NoSend", vec!["Orders::Invoice::Send".to_string()]),
+    ])
+    .unwrap();
+    assert_eq!(check_json(&roles, "Orders::Order::Read"), Ok(true));
+    assert_eq!(check_json(&roles, "Orders::Invoice::Send"), Ok(false));
+    assert_eq!(check_json("[]", "Orders::Order::Read"), Ok(false));
+
+    let check = |roles: &str, permission: &str| {
+        let roles_ptr = rbac_alloc(roles.len());
+        let permission_ptr = rbac_alloc(permission.len());
+        unsafe {
+            std::ptr::copy_nonoverlapping(roles.as_ptr(), roles_ptr, roles.len());
+            std::ptr::copy_nonoverlapping(permission.as_ptr(), permission_ptr, permission.len());
+            let result = rbac_check(roles_ptr, roles.len(), permission_ptr, permission.len());
+            rbac_free(roles_ptr, roles.len());
+            rbac_free(permission_ptr, permission.len());
+            result
+        }
+    };
+    assert_eq!(check(&roles, "Orders::Order::Read"), RBAC_ALLOW);
+    assert_eq!(check(&roles, "Orders::Invoice::Send"), RBAC_DENY);
+    assert_eq!(check("{", "Orders::Order::Read"), RBAC_INVALID_ROLES);
+    assert_eq!(check(&roles, "Orders::*"), RBAC_INVALID_PERMISSION);
+}
+
+#[cfg(feature = "json")]
+#[test]
+fn test_canonical_snapshot() {
+    let build = |roles: Vec<Role>, fallback_roles: [&str; 2]| {
+        let mut builder = RbacService::builder();
+        builder.load_roles(roles).set_fallback_roles(fallback_roles.map(str::to_string).to_vec());
+        builder.build()
+    };
+    let first = build(vec![
+        Role::new("Viewer", vec!["Orders::Order::Read".to_string(), "Orders::Invoice::Read".to_string()]),
+        Role::new("Admin", vec!["*".to_string()]),
+        Role::new("Clerk", vec!["Orders::Invoice::Send".to_string(), "Orders::Invoice::Read".to_string()]),
+    ], ["Viewer", "Clerk"]);
+    let second = build(vec![
+        Role::new("Clerk", vec!["Orders::Invoice::{Read,Send}".to_string()]),
+        Role::new("Admin", vec!["*".to_string(), "Orders::*".to_string()]),
+        Role::new("Viewer", vec!["Orders::Invoice::Read".to_string(), "Orders::Order::Read".to_string(), "Orders::Order::Read".to_string()]),
+    ], ["Clerk", "Viewer"]);
+
+    let json = first.snapshot().to_canonical_json();
+    assert_eq!(json, second.snapshot().to_canonical_json());
+    assert!(json.ends_with("}\n"));
+    let admin = json.find("\"Admin\"").unwrap();
+    assert!(admin < json.find("\"Clerk\"").unwrap() && json.find("\"Clerk\"").unwrap() < json.find("\"Viewer\"").unwrap());
+}
+
+#[cfg(feature = "encryption")]
+#[test]
+fn test_encrypted_roles() {
+    let rbac_service = setup_rbac();
+    let key: RoleFileKey = [7; 32];
+    let encrypted = rbac_service.export_encrypted_roles(&key).unwrap();
+    assert!(!encrypted.windows(12).any(|window| window == b"OrderManager"));
+
+    let mut builder = RbacService::builder();
+    builder.load_encrypted_roles(&encrypted, &key).unwrap();
+    assert_eq!(builder.build().snapshot().to_canonical_json(), rbac_service.snapshot().to_canonical_json());
+
+    assert!(RbacService::builder().load_encrypted_roles(&encrypted, &[8; 32]).is_err());
+    assert!(RbacService::builder().load_encrypted_roles(&encrypted[..10], &key).is_err());
+
+    // Plain role files load only when migration to encryption is opted in
+    let plain = br#"{"name":"Viewer","permissions":["Orders::Order::Read"]}"#;
+    assert!(matches!(RbacService::builder().load_encrypted_roles(plain, &key), Err(RbacError::InvalidRoleData(_))));
+    let mut builder = RbacService::builder();
+    builder.load_encrypted_or_plain_roles(plain, &key).unwrap();
+    assert_eq!(builder.build().get_roles().len(), 1);
+}
+
+#[cfg(feature = "json")]
+#[test]
+fn test_policy_bundle() {
+    let source = setup_rbac();
+    let mut updater = source.updater_copy();
+    updater.add_role(Role::new("Auditor", vec!["Orders::Invoice::Read".to_string()]));
+    updater.update(&source);
+
+    let mut bundle = source.export_bundle();
+    assert_eq!(bundle.generation, 1);
+    assert!(bundle.catalog.contains("Orders::Invoice::Send"));
+    bundle.signature = Some("c2lnbmF0dXJl".to_string());
+    let payload = bundle.signing_payload();
+    let json = serde_json::to_string(&bundle).unwrap();
+    let bundle: PolicyBundle = serde_json::from_str(&json).unwrap();
+    assert_eq!(bundle.signing_payload(), payload);
+
+    let target = RbacService::builder().build();
+    target.load_bundle(&bundle).unwrap();
+    assert_eq!(target.policy_hash(), source.policy_hash());
+    assert_eq!(target.get_roles().iter().find(|role| role.name == "Auditor").unwrap().revision, 1);
+
+    let mut tampered = bundle.clone();
+    tampered.roles.push(Role::new("Backdoor", vec!["*".to_string()]));
+    assert!(target.load_bundle(&tampered).is_err());
+    assert_eq!(target.policy_hash(), source.policy_hash());
+}
+
+#[test]
+fn test_bundle_catalog_check() {
+    let mut bundle = setup_rbac().export_bundle();
+    bundle.catalog = bundle
+        .catalog
+        .permissions()
+        .filter(|info| info.full_name != "Orders::Invoice::Send")
+        .cloned()
+        .chain([PermissionInfo {
+            domain: "Orders".to_string(),
+            object_type: "Refund".to_string(),
+            action: "Issue".to_string(),
+            full_name: "Orders::Refund::Issue".to_string(),
+            description: "Issue refunds".to_string(),
+            deprecated: None,
+        }])
+        .collect();
+
+    let mut builder = RbacService::builder();
+    builder.register_domains((Users, Templates, Orders)).set_bundle_check(Severity::Error);
+    let strict = builder.build();
+    assert_eq!(
+        strict.load_bundle(&bundle),
+        Err(RbacError::IncompatibleBundle {
+            missing: vec!["Orders::Invoice::Send".to_string()],
+            extra: vec!["Orders::Refund::Issue".to_string()],
+        })
+    );
+    assert!(strict.get_roles().is_empty());
+
+    // Warnings don't stop loading, and bundles aren't checked without registered permissions
+    assert!(matches!(setup_rbac().load_bundle(&bundle), Ok(Some(RbacError::IncompatibleBundle { .. }))));
+    assert_eq!(RbacService::builder().set_bundle_check(Severity::Error).build().load_bundle(&bundle), Ok(None));
+}
+
+#[cfg(all(unix, feature = "signal"))]
+#[test]
+fn test_reload_on_signal() {
+    use std::sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    };
+
+    let rbac_service = Arc::new(setup_rbac());
+    let loads = Arc::new(AtomicUsize::new(0));
+    let counter = loads.clone();
+    let reports = Arc::new(std::sync::Mutex::new(Vec::new()));
+    let reported = reports.clone();
+    // SIGUSR2 rather than SIGHUP, so test doesn't interfere with handlers of test harness
+    let reload = rbac_service
+        .reload_on_signal(
+            signal_hook::consts::SIGUSR2,
+            move || {
+                counter.fetch_add(1, Ordering::SeqCst);
+                Ok(vec![Role::new("Viewer", vec!["Orders::Order::Read".to_string()])])
+            },
+            move |report: Result<&RoleSyncReport, &[RbacError]>| reported.lock().unwrap().push(report.is_ok()),
+        )
+        .unwrap();
+
+    signal_hook::low_level::raise(signal_hook::consts::SIGUSR2).unwrap();
+    for _ in 0..200 {
+        if rbac_service.generation() > 0 {
+            break;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(5));
+    }
+    assert_eq!(rbac_service.generation(), 1);
+    assert_eq!(loads.load(Ordering::SeqCst), 1);
+    assert_eq!(rbac_service.get_roles().len(), 1);
+    reload.stop();
+    assert_eq!(*reports.lock().unwrap(), [true]);
+}
+
+#[test]
+fn test_lazy_domain_registration() {
+    let mut builder = RbacService::builder();
+    builder
+        .register_domains((Users, Templates))
+        .add_role(Role::new("OrderManager", vec!["Orders::*".to_string()]));
+    let rbac_service = builder.build();
+    let user = User {
+        name: "judy".to_string(),
+        roles: vec!["OrderManager".to_string()],
+    };
+    assert!(matches!(
+        rbac_service.has_permission_str(&user, "Orders::Order::Read"),
+        Err(RbacError::UnknownPermission { .. })
+    ));
+
+    rbac_service.register_domain(Orders);
+    assert!(rbac_service.has_permission_str(&user, "Orders::Order::Read").is_ok());
+    assert_eq!(rbac_service.get("Orders::Invoice::Send").unwrap().description, "Send invoices to customers");
+    assert_eq!(rbac_service.get_all_permissions().len(), setup_rbac().get_all_permissions().len());
+}
+
+#[test]
+fn test_feature_gates() {
+    use std::sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    };
+
+    let invoices_enabled = Arc::new(AtomicBool::new(false));
+    let flag = invoices_enabled.clone();
+    let mut builder = RbacService::builder();
+    builder
+        .add_role(Role::new("Admin", vec!["*".to_string()]))
+        .gate_permissions("Orders::Invoice::*", "invoices")
+        .set_flag_provider(move |name: &str| name == "invoices" && flag.load(Ordering::SeqCst));
+    let rbac_service = builder.build();
+    let admin = User {
+        name: "ken".to_string(),
+        roles: vec!["Admin".to_string()],
+    };
+
+    assert_eq!(
+        rbac_service.has_permission(&admin, Orders::Invoice::Send),
+        Err(RbacError::FeatureDisabled {
+            permission: "Orders::Invoice::Send".to_string(),
+            flag: "invoices".to_string(),
+        })
+    );
+    assert!(!RbacSession::new(&rbac_service, &admin).can(Orders::Invoice::Read));
+    assert!(rbac_service.has_permission(&admin, Orders::Order::Read).is_ok());
+
+    let decision = rbac_service.explain(&admin, Orders::Invoice::Send);
+    assert!(!decision.allowed);
+    assert_eq!(decision.to_string(), "deny Orders::Invoice::Send subject=ken roles=Admin matched=Admin(*) blocked=(Feature disabled: Orders::Invoice::Send (flag invoices))");
+
+    invoices_enabled.store(true, Ordering::SeqCst);
+    assert!(rbac_service.has_permission(&admin, Orders::Invoice::Send).is_ok());
+    assert!(RbacSession::new(&rbac_service, &admin).can(Orders::Invoice::Read));
+    assert!(rbac_service.explain(&admin, Orders::Invoice::Send).allowed);
+
+    // Gate denials of tokens are audited too
+    let decisions = Arc::new(std::sync::Mutex::new(Vec::new()));
+    let sink = decisions.clone();
+    let mut builder = RbacService::builder();
+    builder
+        .gate_permissions("Orders::Invoice::*", "invoices")
+        .add_audit_sink(move |decision: &RbacDecision| sink.lock().unwrap().push(decision.clone()));
+    let rbac_service = builder.build();
+    let result = rbac_service.has_permission_with_scopes(&ScopeMapper::default(), "orders:invoice:send", Orders::Invoice::Send);
+    assert!(matches!(result, Err(RbacError::FeatureDisabled { .. })));
+    let decisions = decisions.lock().unwrap();
+    assert!(decisions.len() == 1 && !decisions[0].allowed && decisions[0].blocked_by.is_some());
+
+    // Malformed gate would gate nothing, so it's reported and fails strict build
+    let mut builder = RbacService::builder();
+    builder
+        .add_role(Role::new("Admin", vec!["*".to_string()]))
+        .set_fallback_roles(vec!["Admin".to_string()])
+        .gate_permissions("Orders:Invoice:*", "invoices")
+        .gate_permissions("Orders::Invoice::* if subject.tier == \"gold\"", "gold");
+    assert_eq!(
+        builder.build().build_warnings(),
+        [
+            BuildWarning::GatePattern {
+                pattern: "Orders:Invoice:*".to_string(),
+                flag: "invoices".to_string(),
+            },
+            BuildWarning::GatePattern {
+                pattern: "Orders::Invoice::* if subject.tier == \"gold\"".to_string(),
+                flag: "gold".to_string(),
+            },
+        ]
+    );
+    assert_eq!(
+        builder.try_build().err(),
+        Some(RbacError::InvalidGatePattern {
+            pattern: "Orders:Invoice:*".to_string(),
+            flag: "invoices".to_string(),
+        })
+    );
+}
+
+#[test]
+fn test_deprecation_warnings() {
+    use std::sync::{Arc, Mutex};
+
+    let warnings = Arc::new(Mutex::new(Vec::new()));
+    struct Collector(Arc<Mutex<Vec<DeprecationWarning>>>);
+    impl AuditSink for Collector {
+        fn on_decision(&self, _decision: &RbacDecision) {}
+        fn on_deprecated(&self, warning: &DeprecationWarning) {
+            self.0.lock().unwrap().push(warning.clone());
+        }
+    }
+
+    let mut builder = RbacService::builder();
+    builder
+        .register_domains((Users, Templates, Orders))
+        .add_role(Role::new("OrderManager", vec!["Orders::*".to_string()]))
+        .deprecate_permission("Orders::Order::Cancel", "use Orders::Order::Update")
+        .set_deprecation_warnings(true)
+        .add_audit_sink(Collector(warnings.clone()));
+    let rbac_service = builder.build();
+    let user = User {
+        name: "leo".to_string(),
+        roles: vec!["OrderManager".to_string()],
+    };
+
+    assert!(rbac_service.has_permission(&user, Orders::Order::Read).is_ok());
+    assert!(warnings.lock().unwrap().is_empty());
+    let line = line!() + 1;
+    assert!(rbac_service.has_permission(&user, Orders::Order::Cancel).is_ok());
+    assert!(RbacSession::new(&rbac_service, &user).can(Orders::Order::Cancel));
+
+    let warnings = warnings.lock().unwrap();
+    assert_eq!(warnings.len(), 2);
+    assert_eq!(warnings[0].caller.file(), file!());
+    assert_eq!(warnings[0].caller.line(), line);
+    assert_eq!(warnings[1].caller.line(), line + 1);
+    assert_eq!(
+        warnings[0].to_string(),
+        format!("deprecated permission Orders::Order::Cancel checked by leo at {}: use Orders::Order::Update", warnings[0].caller)
+    );
+    assert_eq!(rbac_service.get("Orders::Order::Cancel").unwrap().deprecated.as_deref(), Some("use Orders::Order::Update"));
+}
+
+define_permissions! {
+    pub domain Shipping {
+        Parcel {
+            Read => "View parcels",
+            Update => "Update parcels",
+            #[deprecated(note = "use Update")]
+            Edit => "Edit parcels",
+            #[deprecated(since = "2.0.0", note = "use Shipping::Parcel::Read")]
+            View => "View parcels",
+            #[deprecated]
+            Weigh => "Weigh parcels",
+            #[deprecated(note = "use Shipping::Label API instead")]
+            Print => "Print parcel labels",
+        },
+    }
+}
+
+#[test]
+#[allow(deprecated)]
+fn test_deprecated_actions() {
+    assert_eq!(Shipping::Parcel::EDIT_STR, "Shipping::Parcel::Update");
+    assert_eq!(Shipping::Parcel::Edit.to_string(), "Shipping::Parcel::Update");
+    assert_eq!(Shipping::Parcel::View.full_name(), "Shipping::Parcel::Read");
+    assert_eq!(Shipping::Parcel::Weigh.full_name(), "Shipping::Parcel::Weigh");
+    // Free-form note doesn't name replacement
+    assert_eq!(Shipping::Parcel::Print.full_name(), "Shipping::Parcel::Print");
+    assert_eq!(Shipping::Parcel::Edit.action(), "Edit");
+    assert_eq!(Shipping::Parcel::from_string("Shipping::Parcel::Edit"), Some(Shipping::Parcel::Edit));
+    assert_eq!(Shipping::Parcel::from_string("Shipping::Parcel::Update"), Some(Shipping::Parcel::Update));
+
+    let mut builder = RbacService::builder();
+    builder
+        .register_domains((Shipping,))
+        .add_role(Role::new("Clerk", vec!["Shipping::Parcel::Edit".to_string()]));
+    let rbac_service = builder.build();
+    let info = rbac_service.get("Shipping::Parcel::Edit").unwrap();
+    assert_eq!((info.action.as_str(), info.deprecated.as_deref()), ("Edit", Some("use Update")));
+    assert_eq!(rbac_service.get("Shipping::Parcel::Weigh").unwrap().deprecated.as_deref(), Some(""));
+    assert_eq!(rbac_service.get("Shipping::Parcel::Update").unwrap().deprecated, None);
+    assert_eq!(rbac_service.get_all_permissions().len(), 6);
+
+    // Role still naming deprecated action is granted its replacement
+    let user = User {
+        name: "alice".to_string(),
+        roles: vec!["Clerk".to_string()],
+    };
+    assert!(rbac_service.has_permission(&user, Shipping::Parcel::Update).is_ok());
+    assert!(rbac_service.has_permission(&user, Shipping::Parcel::Edit).is_ok());
+    assert!(rbac_service.has_permission_str(&user, "Shipping::Parcel::Edit").is_ok());
+    assert!(rbac_service.has_permission(&user, Shipping::Parcel::Read).is_err());
+
+    // Deprecated string is known only to services the domain is registered with
+    let mut builder = RbacService::builder();
+    builder.add_role(Role::new("Clerk", vec!["Shipping::Parcel::Edit".to_string()]));
+    assert!(builder.build().has_permission(&user, Shipping::Parcel::Edit).is_err());
+}
+
+#[test]
+fn test_map_permissions() {
+    let labels: Vec<(&str, &str)> = map_permissions! {
+        Orders::Invoice::Read => "read",
+        Orders::Invoice::Generate => "generate",
+        Orders::Invoice::Send => "send",
+        Orders::OrderItem::Remove => "remove",
+        Orders::OrderItem::Read => "read",
+        Orders::OrderItem::Add => "add",
+    };
+
+    assert_eq!(labels.len(), 6);
+    assert_eq!(labels[2], ("Orders::Invoice::Send", "send"));
+    assert_eq!(labels[3], ("Orders::OrderItem::Remove", "remove"));
+    assert_eq!(unmapped_permission_of_invoice(&["Orders::Invoice::Read"]), Some("Orders::Invoice::Generate"));
+    assert_eq!(unmapped_permission_of_invoice(&["Orders::Invoice::Send", "Orders::Invoice::Generate", "Orders::Invoice::Read"]), None);
+}
+
+fn unmapped_permission_of_invoice(mapped: &[&str]) -> Option<&'static str> {
+    __private::unmapped_permission(&Orders::Invoice::Read, mapped)
+}
+
+#[test]
+fn test_role_name_rules() {
+    let mut rules = RoleNameRules::new();
+    rules.reserve_prefix("system:").set_require_namespace(true);
+
+    let mut builder = RbacService::builder();
+    builder.set_role_name_rules(rules);
+    builder.add_role(Role::new("system:Admin", vec!["*".to_string()]));
+    assert!(builder.try_add_role(Role::new(&namespaced_role_name("billing", "Viewer"), vec![])).is_ok());
+    assert!(matches!(
+        builder.try_add_role(Role::new("system:Root", vec![])),
+        Err(RbacError::InvalidRoleName { reason, .. }) if reason.contains("reserved")
+    ));
+    assert!(matches!(builder.try_add_role(Role::new("Viewer", vec![])), Err(RbacError::InvalidRoleName { .. })));
+    assert!(matches!(builder.try_add_role(Role::new("billing:Big Boss", vec![])), Err(RbacError::InvalidRoleName { .. })));
+    let rbac_service = builder.build();
+    assert!(rbac_service.has_permission_with_roles(&["system:Admin"], Orders::Order::Read).is_ok());
+
+    let mut updater = rbac_service.updater_copy();
+    assert!(updater.try_add_role(Role::new("system:Backdoor", vec!["*".to_string()])).is_err());
+    assert!(updater.upsert_if_match(Role::new("Orphan", vec![]), 0).is_err());
+    assert!(updater.try_add_role(Role::new("sales:Viewer", vec![])).is_ok());
+    updater.update(&rbac_service);
+    assert_eq!(rbac_service.get_roles().len(), 3);
+
+    assert_eq!(split_role_name("sales:Viewer"), (Some("sales"), "Viewer"));
+    assert_eq!(split_role_name("Viewer"), (None, "Viewer"));
+}
+
+#[test]
+fn test_protected_roles() {
+    let mut builder = RbacService::builder();
+    builder.add_role(Role::new("Admin", vec!["*".to_string()])).protect_role("Admin");
+    let rbac_service = builder.build();
+    let admin_revision = rbac_service.updater_copy().revision("Admin");
+
+    let mut updater = rbac_service.updater_copy();
+    assert!(matches!(updater.try_remove_role("Admin"), Err(RbacError::ProtectedRole(role)) if role == "Admin"));
+    assert!(matches!(updater.try_add_role(Role::new("Admin", vec![])), Err(RbacError::ProtectedRole(_))));
+    updater.remove_role("Admin").add_role(Role::new("Admin", vec![])).tombstone_role("Admin");
+    let report = updater.sync_roles(vec![Role::new("Auditor", vec!["Orders::*".to_string()])]).unwrap();
+    assert!(!report.removed.contains(&"Admin".to_string()));
+    updater.update(&rbac_service);
+    assert!(rbac_service.has_permission_with_roles(&["Admin"], Users::User::Delete).is_ok());
+
+    let mut updater = rbac_service.updater_clean();
+    updater.add_role(Role::new("Viewer", vec!["Orders::Order::Read".to_string()]));
+    updater.update(&rbac_service);
+    let names: Vec<String> = rbac_service.get_roles().into_iter().map(|role| role.name).collect();
+    assert_eq!(names.len(), 2);
+    assert!(names.contains(&"Admin".to_string()));
+    assert_eq!(rbac_service.updater_copy().revision("Admin"), admin_revision);
+}
+
+#[test]
+fn test_updater_validation() {
+    let rbac_service = setup_rbac();
+
+    let mut updater = rbac_service.updater_copy();
+    updater
+        .add_role(Role::new("Broken", vec!["Orders::*::Read".to_string(), "Orders::Order::Raed".to_string()]))
+        .add_role(Role::new("Conditional", vec!["Orders::Order::Read if owner ==".to_string()]))
+        .add_role(Role::new("Ghost", vec!["Invoices::*".to_string(), "Orders::Order::{Read,Cancel}".to_string()]));
+    let errors = updater.try_update(&rbac_service).unwrap_err();
+    assert_eq!(errors.len(), 4);
+    assert!(matches!(&errors[0], RbacError::InvalidPattern { role, pattern } if role == "Broken" && pattern == "Orders::*::Read"));
+    assert!(matches!(&errors[1], RbacError::UnknownPermission { suggestions, .. } if suggestions == &["Orders::Order::Read".to_string()]));
+    assert!(matches!(errors[2], RbacError::InvalidCondition(_)));
+    assert!(matches!(&errors[3], RbacError::UnknownPermission { permission, .. } if permission == "Invoices::*"));
+
+    assert!(rbac_service.get_roles().iter().all(|role| role.name != "Ghost"));
+
+    updater.remove_role("Broken").remove_role("Conditional");
+    updater.add_role(Role::new("Ghost", vec!["Orders::*".to_string()]));
+    assert!(updater.validate(&rbac_service).is_ok());
+    assert_eq!(updater.try_update(&rbac_service), Ok(()));
+    assert!(rbac_service.has_permission_with_roles(&["Ghost"], Orders::Invoice::Send).is_ok());
+}
+
+#[test]
+fn test_patch_role() {
+    let events = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+    let mut builder = RbacService::builder();
+    let sink = events.clone();
+    builder
+        .add_role(Role::new("Viewer", vec!["Orders::Order::Read".to_string()]))
+        .add_role(Role::new("Admin", vec!["*".to_string()]))
+        .protect_role("Admin")
+        .add_role_change_sink(move |event: &RoleChangeEvent| sink.lock().unwrap().push(event.role.clone()));
+    let rbac_service = builder.build();
+    let admin = rbac_service.get_roles().into_iter().find(|role| role.name == "Admin").unwrap();
+
+    let revision = rbac_service
+        .patch_role("Viewer", |role| {
+            role.permissions.push("Orders::Invoice::Read".to_string());
+            role.name = "Renamed".to_string();
+        })
+        .unwrap();
+    assert_eq!(revision, 1);
+    assert_eq!(rbac_service.generation(), 1);
+    assert!(rbac_service.has_permission_with_roles(&["Viewer"], Orders::Invoice::Read).is_ok());
+    assert_eq!(*events.lock().unwrap(), vec!["Viewer".to_string()]);
+
+    assert!(matches!(rbac_service.patch_role("Nobody", |_| {}), Err(RbacError::RoleNotFound(_))));
+    assert!(matches!(rbac_service.patch_role("Admin", |_| {}), Err(RbacError::ProtectedRole(_))));
+    assert!(matches!(
+        rbac_service.patch_role("Viewer", |role| role.permissions.push("Orders::Order::Cancel if owner ==".to_string())),
+        Err(RbacError::InvalidCondition(_))
+    ));
+    assert_eq!(rbac_service.generation(), 1);
+    assert_eq!(rbac_service.get_roles().into_iter().find(|role| role.name == "Admin").unwrap().revision, admin.revision);
+}
+
+#[test]
+fn test_frozen_service() {
+    let rbac_service = setup_rbac();
+    let mut updater = rbac_service.updater_copy();
+    updater.add_role(Role::new("Viewer", vec!["Orders::Order::Read".to_string()]));
+    updater.update(&rbac_service);
+
+    let frozen: FrozenRbacService = rbac_service.freeze();
+    assert_eq!(frozen.generation(), 1);
+    assert!(frozen.has_permission_with_roles(&["Viewer"], Orders::Order::Read).is_ok());
+    assert!(frozen.has_permission_with_roles(&["Viewer"], Orders::Order::Cancel).is_err());
+    let user = User {
+        name: "Alice".to_string(),
+        roles: vec!["OrderManager".to_string()],
+    };
+    assert!(frozen.has_permission(&user, Orders::Invoice::Generate).is_ok());
+    assert_eq!(frozen.get_roles().len(), 5);
+}
+
+#[test]
+fn test_anonymous_subject() {
+    let mut builder = RbacService::builder();
+    builder
+        .add_role(Role::new("Default", vec!["Orders::*".to_string()]))
+        .add_role(Role::new("Public", vec!["Orders::Order::Read".to_string()]));
+    let rbac_service = builder.build();
+    let guest = Subject::anonymous();
+    assert_eq!(guest.name(), ANONYMOUS_SUBJECT);
+
+    // Default "Anonymous" role doesn't exist, and fallback roles don't apply to guests
+    assert!(rbac_service.has_permission(&guest, Orders::Order::Read).is_err());
+    assert!(rbac_service.has_permission(&Subject::new("bob", vec![]), Orders::Order::Read).is_ok());
+
+    builder.set_anonymous_roles(vec!["Public".to_string()]);
+    let rbac_service = builder.build();
+    assert!(rbac_service.has_permission(&guest, Orders::Order::Read).is_ok());
+    assert!(rbac_service.has_permission(&guest, Orders::Order::Cancel).is_err());
+    assert!(RbacSession::new(&rbac_service, &guest).can(Orders::Order::Read));
+    let decision = rbac_service.explain(&guest, Orders::Order::Read);
+    assert!(!decision.fallback_used);
+    assert_eq!(decision.roles, ["Public"]);
+
+    // Without anonymous roles guests are denied everything, instead of getting fallback roles
+    builder.set_anonymous_roles(vec![]).set_combination_cache(16);
+    let rbac_service = builder.build();
+    assert_eq!(
+        rbac_service.has_permission(&guest, Orders::Order::Read),
+        Err(RbacError::PermissionDenied("Orders::Order::Read".to_string()))
+    );
+    assert!(!RbacSession::new(&rbac_service, &guest).can(Orders::Order::Read));
+    assert!(RbacSession::new(&rbac_service, &guest).list().is_empty());
+    assert!(!rbac_service.evaluate_matrix(std::slice::from_ref(&guest), &[Orders::Order::Read])[0][0]);
+    let decision = rbac_service.explain(&guest, Orders::Order::Read);
+    assert!(!decision.allowed && !decision.fallback_used);
+    assert!(rbac_service.has_permission(&Subject::new("bob", vec![]), Orders::Order::Read).is_ok());
+}
+
+#[test]
+fn test_machine_subjects() {
+    let mut builder = RbacService::builder();
+    builder
+        .add_role(Role::new(
+            "Billing",
+            vec!["Orders::Invoice::Read".to_string(), "@machine Orders::Invoice::{Generate,Send}".to_string()],
+        ))
+        .set_audience("orders");
+    let rbac_service = builder.build();
+
+    let human = User {
+        name: "alice".to_string(),
+        roles: vec!["Billing".to_string()],
+    };
+    let billing = MachineSubject::new("billing-worker", vec!["Billing".to_string()], vec!["orders".to_string()]);
+    assert!(rbac_service.has_permission(&human, Orders::Invoice::Read).is_ok());
+    assert!(rbac_service.has_permission(&human, Orders::Invoice::Generate).is_err());
+    assert!(rbac_service.has_permission(&billing, Orders::Invoice::Generate).is_ok());
+    assert!(RbacSession::new(&rbac_service, &billing).can(Orders::Invoice::Send));
+    assert!(rbac_service.has_permission_with_roles(&["Billing"], Orders::Invoice::Send).is_err());
+    assert_eq!(
+        rbac_service.explain(&billing, Orders::Invoice::Send).matched_pattern.as_deref(),
+        Some("@machine Orders::Invoice::{Generate,Send}")
+    );
+
+    let stranger = MachineSubject::new("reports", vec!["Billing".to_string()], vec!["reports".to_string()]);
+    assert!(matches!(
+        rbac_service.has_permission(&stranger, Orders::Invoice::Read),
+        Err(RbacError::AudienceMismatch { audience, .. }) if audience == "orders"
+    ));
+    assert!(RbacSession::new(&rbac_service, &stranger).require(Orders::Invoice::Read).is_err());
+}
+
+#[cfg(feature = "json")]
+#[test]
+fn test_claims() {
+    let rbac_service = setup_rbac();
+    let user = User {
+        name: "alice".to_string(),
+        roles: vec!["OrderManager".to_string()],
+    };
+
+    let claims = rbac_service.claims_for(&user);
+    assert_eq!(claims["roles"], serde_json::json!(["OrderManager"]));
+    assert_eq!(claims["policy_hash"], format!("{:016x}", rbac_service.policy_hash()));
+    assert!(rbac_service.check_claims(&claims, Orders::Order::Cancel).is_ok());
+    assert!(rbac_service.check_claims(&claims, Orders::Invoice::Send).is_err());
+
+    let claims = rbac_service.claims_with_permissions_for(&user);
+    assert_eq!(claims["permission_count"], rbac_service.get_all_permissions().len());
+    // Downstream service with the same permissions, but no roles
+    let mut builder = RbacService::builder();
+    builder.register_domains((Users, Templates, Orders));
+    let downstream = builder.build();
+    assert!(downstream.check_claims(&claims, Orders::Order::Cancel).is_ok());
+    assert!(downstream.check_claims(&claims, Orders::Invoice::Generate).is_ok());
+    assert!(downstream.check_claims(&claims, Orders::Invoice::Send).is_err());
+    assert!(downstream.check_claims(&claims, Users::User::Read).is_err());
+
+    let mut builder = RbacService::builder();
+    builder.register_domains((Orders,));
+    assert!(matches!(builder.build().check_claims(&claims, Orders::Order::Read), Err(RbacError::InvalidRoleData(_))));
+
+    // Catalog of the same size, but with other permissions
+    let mut builder = RbacService::builder();
+    builder.register_domains((Users, Templates, Orders));
+    builder.all_permissions.pop_first();
+    let info = PermissionInfo::of(&Shipping::Parcel::Read);
+    builder.all_permissions.insert(info.full_name.clone(), info);
+    let other = builder.build();
+    assert_eq!(other.get_all_permissions().len(), rbac_service.get_all_permissions().len());
+    assert!(matches!(other.check_claims(&claims, Orders::Order::Cancel), Err(RbacError::InvalidRoleData(_))));
+
+    // Claims keep guests off fallback roles and machine-only patterns to machines
+    let mut builder = RbacService::builder();
+    builder
+        .add_role(Role::new("Default", vec!["Orders::*".to_string()]))
+        .add_role(Role::new("Billing", vec!["@machine Orders::Invoice::Send".to_string()]))
+        .set_anonymous_roles(vec![]);
+    let rbac_service = builder.build();
+    let guest = Subject::anonymous();
+    assert!(rbac_service.has_permission(&guest, Orders::Order::Read).is_err());
+    let claims = rbac_service.claims_for(&guest);
+    assert_eq!((claims["roles"].clone(), claims["anonymous"].clone()), (serde_json::json!([]), serde_json::json!(true)));
+    assert!(rbac_service.check_claims(&claims, Orders::Order::Read).is_err());
+    assert!(rbac_service.check_claims(&rbac_service.claims_for(&Subject::new("bob", vec![])), Orders::Order::Read).is_ok());
+    let worker = MachineSubject::new("billing-worker", vec!["Billing".to_string()], vec![]);
+    assert!(rbac_service.check_claims(&rbac_service.claims_for(&worker), Orders::Invoice::Send).is_ok());
+    let billing = User {
+        name: "alice".to_string(),
+        roles: vec!["Billing".to_string()],
+    };
+    assert!(rbac_service.check_claims(&rbac_service.claims_for(&billing), Orders::Invoice::Send).is_err());
+
+    // Bitmap doesn't freeze gate and quota state of its issuing, they are applied by checking service
+    let mut builder = RbacService::builder();
+    builder
+        .add_role(Role::new("Billing", vec!["Orders::Invoice::*".to_string()]))
+        .add_quota("Orders::Invoice::Generate", Quota::parse("1/day").unwrap())
+        .gate_permissions("Orders::Invoice::Send", "invoices")
+        .register_domains((Users, Templates, Orders));
+    let issuer = builder.build();
+    assert!(issuer.has_permission(&billing, Orders::Invoice::Generate).is_ok());
+    assert!(issuer.has_permission(&billing, Orders::Invoice::Generate).is_err());
+    let claims = issuer.claims_with_permissions_for(&billing);
+    assert!(downstream.check_claims(&claims, Orders::Invoice::Generate).is_ok());
+    assert!(downstream.check_claims(&claims, Orders::Invoice::Send).is_ok());
+    assert!(matches!(issuer.check_claims(&claims, Orders::Invoice::Send), Err(RbacError::FeatureDisabled { .. })));
+}
+
+#[cfg(feature = "json")]
+#[test]
+fn test_rbac_verifier() {
+    use crate::RbacVerifier;
+
+    let rbac_service = setup_rbac();
+    let user = User {
+        name: "alice".to_string(),
+        roles: vec!["OrderManager".to_string()],
+    };
+    let catalog = rbac_service.catalog();
+
+    let verifier = RbacVerifier::from_claims(&rbac_service.claims_with_permissions_for(&user), &catalog).unwrap();
+    assert!(verifier.has_role("OrderManager"));
+    assert_eq!(verifier.policy_hash(), Some(format!("{:016x}", rbac_service.policy_hash()).as_str()));
+    assert!(verifier.has_permission(Orders::Order::Cancel).is_ok());
+    assert!(verifier.has_permission_str("Orders::Invoice::Generate").is_ok());
+    assert!(matches!(verifier.has_permission(Orders::Invoice::Send), Err(RbacError::PermissionDenied(_))));
+    assert!(verifier.has_permission(Users::User::Read).is_err());
+
+    // Roles only: nothing is granted without role map
+    let verifier = RbacVerifier::from_claims(&rbac_service.claims_for(&user), &catalog).unwrap();
+    assert!(verifier.has_role("OrderManager"));
+    assert!(matches!(verifier.has_permission(Orders::Order::Read), Err(RbacError::InvalidRoleData(_))));
+
+    let other: PermissionCatalog = catalog.permissions().skip(1).cloned().collect();
+    assert!(RbacVerifier::from_claims(&rbac_service.claims_with_permissions_for(&user), &other).is_err());
+    let mut renamed: Vec<PermissionInfo> = catalog.permissions().cloned().collect();
+    renamed[0].full_name.push_str("V2");
+    let renamed: PermissionCatalog = renamed.into_iter().collect();
+    assert_eq!(renamed.len(), catalog.len());
+    assert!(RbacVerifier::from_claims(&rbac_service.claims_with_permissions_for(&user), &renamed).is_err());
+    assert!(RbacVerifier::from_claims(&serde_json::json!({}), &catalog).is_err());
+}
@@ -0,0 +1,124 @@
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+use pin_project_lite::pin_project;
+use tower_layer::Layer;
+use tower_service::Service;
+
+use crate::{AtomicRoles, RbacError, RbacService, RbacSubject, RoleStorage};
+
+/// Framework-agnostic [tower](https://docs.rs/tower) layer, checking permissions before requests reach inner service.
+///
+/// Extractor maps request to subject and permission string (checked by [.has_permission_str()][RbacService#method.has_permission_str]),
+/// returning `None` for requests which don't need a check (e.g. public endpoints).
+/// Denied requests fail with [RbacError] converted into inner service error, so it works under hyper, tonic and axum
+/// (via `HandleErrorLayer`) alike, as long as their error type is `From<RbacError>` (e.g. `tower::BoxError`).
+/// Inner service has to be `Clone`: readiness it reserved for denied request is released by replacing it with fresh clone.
+pub struct RbacLayer<F, R: RoleStorage = AtomicRoles> {
+    rbac_service: Arc<RbacService<R>>,
+    extractor: F,
+}
+
+impl<F, R: RoleStorage> RbacLayer<F, R> {
+    pub fn new(rbac_service: Arc<RbacService<R>>, extractor: F) -> Self {
+        RbacLayer { rbac_service, extractor }
+    }
+}
+
+impl<F: Clone, R: RoleStorage> Clone for RbacLayer<F, R> {
+    fn clone(&self) -> Self {
+        RbacLayer {
+            rbac_service: self.rbac_service.clone(),
+            extractor: self.extractor.clone(),
+        }
+    }
+}
+
+impl<S, F: Clone, R: RoleStorage> Layer<S> for RbacLayer<F, R> {
+    type Service = RbacMiddleware<S, F, R>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RbacMiddleware {
+            inner,
+            rbac_service: self.rbac_service.clone(),
+            extractor: self.extractor.clone(),
+        }
+    }
+}
+
+/// Service produced by [RbacLayer]
+pub struct RbacMiddleware<S, F, R: RoleStorage = AtomicRoles> {
+    inner: S,
+    rbac_service: Arc<RbacService<R>>,
+    extractor: F,
+}
+
+impl<S: Clone, F: Clone, R: RoleStorage> Clone for RbacMiddleware<S, F, R> {
+    fn clone(&self) -> Self {
+        RbacMiddleware {
+            inner: self.inner.clone(),
+            rbac_service: self.rbac_service.clone(),
+            extractor: self.extractor.clone(),
+        }
+    }
+}
+
+impl<S, F, R, Request, Subject> Service<Request> for RbacMiddleware<S, F, R>
+where
+    S: Service<Request> + Clone,
+    S::Error: From<RbacError>,
+    F: Fn(&Request) -> Option<(Subject, String)>,
+    Subject: RbacSubject,
+    R: RoleStorage,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = ResponseFuture<S::Future, S::Error>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request) -> Self::Future {
+        if let Some((subject, permission)) = (self.extractor)(&request)
+            && let Err(e) = self.rbac_service.has_permission_str(&subject, &permission)
+        {
+            // Inner service was made ready for this request, dropping it releases what it reserved (e.g. concurrency limit permit)
+            let fresh = self.inner.clone();
+            drop(std::mem::replace(&mut self.inner, fresh));
+            return ResponseFuture::Denied { error: Some(e.into()) };
+        }
+        ResponseFuture::Inner {
+            future: self.inner.call(request),
+        }
+    }
+}
+
+pin_project! {
+    /// Future of [RbacMiddleware], either inner service response or denial
+    #[project = ResponseFutureProj]
+    pub enum ResponseFuture<Fut, E> {
+        Inner { #[pin] future: Fut },
+        Denied { error: Option<E> },
+    }
+}
+
+impl<Fut, T, E> Future for ResponseFuture<Fut, E>
+where
+    Fut: Future<Output = Result<T, E>>,
+{
+    type Output = Result<T, E>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        match self.project() {
+            ResponseFutureProj::Inner { future } => future.poll(cx),
+            ResponseFutureProj::Denied { error } => {
+                Poll::Ready(Err(error.take().expect("ResponseFuture polled after completion")))
+            }
+        }
+    }
+}
@@ -0,0 +1,144 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::{
+        PoisonError, RwLock,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use crate::{PermissionInfo, service::PermissionKey};
+
+/// Usage of single permission, collected when enabled by [.set_usage_stats()][crate::RbacServiceBuilder#method.set_usage_stats]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PermissionUsage {
+    /// Number of checks of permission
+    pub checks: u64,
+    /// Number of checks permission was granted on
+    pub allowed: u64,
+    /// Number of checks permission was granted on by fallback roles, as subject had no roles.
+    /// Spike of these usually means roles stopped being attached to subjects upstream.
+    pub fallback_allowed: u64,
+    /// Time of one of the last few checks: clock is read only on every [LAST_CHECKED_SAMPLE]th check of permission, starting with the first one
+    pub last_checked: Option<SystemTime>,
+}
+
+#[derive(Default)]
+struct Counter {
+    checks: AtomicU64,
+    allowed: AtomicU64,
+    fallback_allowed: AtomicU64,
+    /// Milliseconds since UNIX epoch, `0` if never checked
+    last_checked: AtomicU64,
+}
+
+/// Checks of permission between updates of its [PermissionUsage::last_checked]
+pub const LAST_CHECKED_SAMPLE: u64 = 64;
+
+/// Number of unregistered permissions getting own counters, checks of further ones aren't counted
+pub const MAX_UNREGISTERED_PERMISSIONS: usize = 1024;
+
+type Counters = HashMap<String, HashMap<String, HashMap<String, Counter>>>;
+
+#[derive(Default)]
+struct Permissions {
+    counters: Counters,
+    unregistered: usize,
+}
+
+/// Per-permission counters: domain → object → action. Registered permissions are known upfront,
+/// so their checks only take read lock and bump atomics, other permissions are added on first check
+/// (up to [MAX_UNREGISTERED_PERMISSIONS], as permission strings may come from outside).
+#[derive(Default)]
+pub(crate) struct UsageCounters(RwLock<Permissions>);
+
+impl UsageCounters {
+    pub(crate) fn new<'a>(registered: impl Iterator<Item = &'a PermissionInfo>) -> Self {
+        let mut counters = Counters::new();
+        for info in registered {
+            counters
+                .entry(info.domain.clone())
+                .or_default()
+                .entry(info.object_type.clone())
+                .or_default()
+                .entry(info.action.clone())
+                .or_default();
+        }
+        UsageCounters(RwLock::new(Permissions { counters, unregistered: 0 }))
+    }
+
+    pub(crate) fn record(&self, permission: PermissionKey, allowed: bool, fallback_used: bool) {
+        let PermissionKey { domain, object_type, action, .. } = permission;
+        let bump = |counter: &Counter| {
+            let checked = counter.checks.fetch_add(1, Ordering::Relaxed);
+            if allowed {
+                counter.allowed.fetch_add(1, Ordering::Relaxed);
+                if fallback_used {
+                    counter.fallback_allowed.fetch_add(1, Ordering::Relaxed);
+                }
+            }
+            if checked.is_multiple_of(LAST_CHECKED_SAMPLE) {
+                let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+                counter.last_checked.fetch_max(now.as_millis() as u64, Ordering::Relaxed);
+            }
+        };
+
+        let permissions = self.0.read().unwrap_or_else(PoisonError::into_inner);
+        if let Some(counter) = permissions.counters.get(domain).and_then(|objects| objects.get(object_type)).and_then(|actions| actions.get(action)) {
+            return bump(counter);
+        }
+        drop(permissions);
+
+        let mut permissions = self.0.write().unwrap_or_else(PoisonError::into_inner);
+        let Permissions { counters, unregistered } = &mut *permissions;
+        // Counted meanwhile by other thread
+        if let Some(counter) = counters.get(domain).and_then(|objects| objects.get(object_type)).and_then(|actions| actions.get(action)) {
+            return bump(counter);
+        }
+        // Checked before any map is added, so domains and object types of uncounted permissions don't pile up either
+        if *unregistered == MAX_UNREGISTERED_PERMISSIONS {
+            return;
+        }
+        *unregistered += 1;
+        bump(
+            counters
+                .entry(domain.to_string())
+                .or_default()
+                .entry(object_type.to_string())
+                .or_default()
+                .entry(action.to_string())
+                .or_default(),
+        );
+    }
+
+    /// Number of domains counters are kept for
+    #[cfg(test)]
+    pub(crate) fn domains(&self) -> usize {
+        self.0.read().unwrap_or_else(PoisonError::into_inner).counters.len()
+    }
+
+    pub(crate) fn snapshot(&self) -> BTreeMap<String, PermissionUsage> {
+        let permissions = self.0.read().unwrap_or_else(PoisonError::into_inner);
+        let mut stats = BTreeMap::new();
+        for (domain, objects) in permissions.counters.iter() {
+            for (object_type, actions) in objects {
+                for (action, counter) in actions {
+                    let last_checked = match counter.last_checked.load(Ordering::Relaxed) {
+                        0 => None,
+                        millis => Some(UNIX_EPOCH + Duration::from_millis(millis)),
+                    };
+                    stats.insert(
+                        format!("{}::{}::{}", domain, object_type, action),
+                        PermissionUsage {
+                            checks: counter.checks.load(Ordering::Relaxed),
+                            allowed: counter.allowed.load(Ordering::Relaxed),
+                            fallback_allowed: counter.fallback_allowed.load(Ordering::Relaxed),
+                            last_checked,
+                        },
+                    );
+                }
+            }
+        }
+        stats
+    }
+}
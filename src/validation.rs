@@ -0,0 +1,201 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::{
+    MACHINE_ONLY, PermissionCatalog, PermissionInfo, RbacError, RbacService, Role, RoleStorage, alias::PermissionAliases, split_pattern,
+    suggest::closest,
+};
+
+/// Checks pattern is one of `*`, `Domain::*`, `Domain::Object::*`, `Domain::Object::Action`, `Domain::Object::{Action,...}`
+/// (optionally marked as machine-only and followed by ` if condition`), which are the only forms roles compile
+pub(crate) fn is_valid_pattern(pattern: &str) -> bool {
+    let (_, pattern, _) = split_pattern(pattern);
+    // `-` appears in names converted by `#[rbac(string_case = "kebab")]`
+    let is_name = |part: &str| !part.is_empty() && part.chars().all(|c| c.is_alphanumeric() || c == '_' || c == '-');
+    let parts: Vec<&str> = pattern.split("::").collect();
+    match parts[..] {
+        ["*"] => true,
+        [domain, "*"] => is_name(domain),
+        [domain, object_type, "*"] => is_name(domain) && is_name(object_type),
+        [domain, object_type, actions] => {
+            let actions = actions.strip_prefix('{').and_then(|a| a.strip_suffix('}')).unwrap_or(actions);
+            is_name(domain) && is_name(object_type) && actions.split(',').all(is_name)
+        }
+        _ => false,
+    }
+}
+
+/// Errors of role: malformed patterns and conditions, and (when permissions are registered) references to unregistered permissions,
+/// which aren't aliases either
+pub(crate) fn role_errors(role: &Role, registry: &BTreeMap<String, PermissionInfo>, aliases: &PermissionAliases) -> Vec<RbacError> {
+    let mut errors = Vec::new();
+    if let Err(e) = role.validate_conditions() {
+        errors.push(e);
+    }
+    let (domains, objects): (BTreeSet<String>, BTreeSet<String>) = registry
+        .values()
+        .map(|info| (format!("{}::*", info.domain), format!("{}::{}::*", info.domain, info.object_type)))
+        .unzip();
+
+    for pattern in &role.permissions {
+        if !is_valid_pattern(pattern) {
+            errors.push(RbacError::InvalidPattern {
+                role: role.name.clone(),
+                pattern: pattern.clone(),
+            });
+            continue;
+        }
+        if registry.is_empty() {
+            continue;
+        }
+        let (_, pattern, _) = split_pattern(pattern);
+        let references: Vec<String> = match pattern.rsplit_once("::") {
+            None => Vec::new(),
+            Some((_, "*")) => vec![pattern.to_string()],
+            Some((object, actions)) => {
+                let actions = actions.strip_prefix('{').and_then(|a| a.strip_suffix('}')).unwrap_or(actions);
+                actions.split(',').map(|action| format!("{object}::{action}")).collect()
+            }
+        };
+        for reference in references {
+            let candidates = match reference.matches("::").count() {
+                1 => &domains,
+                _ if reference.ends_with("::*") => &objects,
+                _ => {
+                    if !registry.contains_key(&reference) && !aliases.is_alias(&reference) {
+                        errors.push(RbacError::UnknownPermission {
+                            suggestions: closest(&reference, registry.keys().map(String::as_str)),
+                            permission: reference,
+                        });
+                    }
+                    continue;
+                }
+            };
+            if !candidates.contains(&reference) {
+                errors.push(RbacError::UnknownPermission {
+                    suggestions: closest(&reference, candidates.iter().map(String::as_str)),
+                    permission: reference,
+                });
+            }
+        }
+    }
+    errors
+}
+
+/// Validity of pattern being typed, reported by [PatternValidator::check_partial]
+#[derive(Debug, Clone, PartialEq)]
+pub enum PatternState {
+    /// Complete pattern, which compiles and references registered permissions only
+    Valid,
+    /// Not a pattern yet, but beginning of one
+    Incomplete,
+    /// Neither pattern nor beginning of one, with the first error found
+    Invalid(RbacError),
+}
+
+/// Result of [PatternValidator::check_partial]
+#[derive(Debug, Clone, PartialEq)]
+pub struct PartialPattern {
+    pub state: PatternState,
+    /// Input completed up to the end of current segment (`"Orders::Ord"` → `"Orders::Order::"`, `"Orders::OrderItem::"`), sorted
+    pub completions: Vec<String>,
+}
+
+/// Validation-as-you-type of role patterns against permission catalog, for interactive role editors.
+/// Created by [.pattern_validator()][RbacService#method.pattern_validator] or from any catalog.
+#[derive(Debug, Clone)]
+pub struct PatternValidator {
+    registry: BTreeMap<String, PermissionInfo>,
+}
+
+impl PatternValidator {
+    pub fn new(catalog: &PermissionCatalog) -> Self {
+        PatternValidator {
+            registry: catalog.permissions().map(|info| (info.full_name.clone(), info.clone())).collect(),
+        }
+    }
+
+    /// Checks pattern typed so far, completing its current segment (domain, object, action or action of set).
+    /// Conditions (` if ...`) are validated, but not completed.
+    pub fn check_partial(&self, input: &str) -> PartialPattern {
+        let role = Role::new("", vec![input.to_string()]);
+        let errors = role_errors(&role, &self.registry, &PermissionAliases::default());
+        let (machine_only, pattern, condition) = split_pattern(input);
+        let completions = match condition {
+            Some(_) => Vec::new(),
+            None => {
+                let marker = if machine_only { MACHINE_ONLY } else { "" };
+                self.completions(pattern).into_iter().map(|completion| format!("{marker}{completion}")).collect()
+            }
+        };
+
+        let state = match errors.into_iter().next() {
+            None if !input.is_empty() => PatternState::Valid,
+            _ if !completions.is_empty() => PatternState::Incomplete,
+            Some(error) => PatternState::Invalid(error),
+            None => PatternState::Incomplete,
+        };
+        PartialPattern { state, completions }
+    }
+
+    fn completions(&self, pattern: &str) -> Vec<String> {
+        let parts: Vec<&str> = pattern.split("::").collect();
+        let mut completions: BTreeSet<String> = BTreeSet::new();
+        match parts[..] {
+            [partial] => {
+                if "*".starts_with(partial) {
+                    completions.insert("*".to_string());
+                }
+                completions.extend(self.registry.values().filter(|info| info.domain.starts_with(partial)).map(|info| format!("{}::", info.domain)));
+            }
+            [domain, partial] => {
+                if "*".starts_with(partial) {
+                    completions.insert(format!("{domain}::*"));
+                }
+                completions.extend(
+                    self.registry
+                        .values()
+                        .filter(|info| info.domain == domain && info.object_type.starts_with(partial))
+                        .map(|info| format!("{domain}::{}::", info.object_type)),
+                );
+            }
+            [domain, object_type, partial] => {
+                let actions = self.registry.values().filter(|info| info.domain == domain && info.object_type == object_type);
+                match partial.strip_prefix('{') {
+                    // Action set being typed: complete its last action with actions not in set yet
+                    Some(set) if !set.contains('}') => {
+                        let (chosen, last) = set.rsplit_once(',').map_or(("", set), |(chosen, last)| (chosen, last));
+                        let chosen: Vec<&str> = chosen.split(',').collect();
+                        let prefix = &pattern[..pattern.len() - last.len()];
+                        completions.extend(
+                            actions
+                                .filter(|info| info.action.starts_with(last) && !chosen.contains(&info.action.as_str()))
+                                .map(|info| format!("{prefix}{}", info.action)),
+                        );
+                    }
+                    Some(_) => {}
+                    None => {
+                        if "*".starts_with(partial) {
+                            completions.insert(format!("{domain}::{object_type}::*"));
+                        }
+                        completions.extend(
+                            actions
+                                .filter(|info| info.action.starts_with(partial))
+                                .map(|info| format!("{domain}::{object_type}::{}", info.action)),
+                        );
+                    }
+                }
+            }
+            _ => {}
+        }
+        // Input itself isn't completion
+        completions.remove(pattern);
+        completions.into_iter().collect()
+    }
+}
+
+impl<R: RoleStorage> RbacService<R> {
+    /// [PatternValidator] of registered permissions
+    pub fn pattern_validator(&self) -> PatternValidator {
+        PatternValidator::new(&self.catalog())
+    }
+}
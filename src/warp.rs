@@ -0,0 +1,49 @@
+use std::{
+    future::{Ready, ready},
+    sync::Arc,
+};
+
+use ::warp::{Rejection, Reply, http::StatusCode, reject::Reject};
+
+use crate::{Permission, RbacError, RbacService, RbacSubject, RoleStorage};
+
+/// Rejection of subject lacking permission, turned into `403 Forbidden` by [handle_rejection]
+#[derive(Debug)]
+pub struct Forbidden(pub RbacError);
+
+impl Reject for Forbidden {}
+
+/// Filter combinator checking permission of subject extracted by prior filter:
+///
+/// ```ignore
+/// let orders = subject_filter()
+///     .and_then(rbacrab::warp::with_permission(rbac_service.clone(), Orders::Order::Read))
+///     .map(|user: User| ...)
+///     .recover(rbacrab::warp::handle_rejection);
+/// ```
+///
+/// Subject is passed further on success, otherwise request is rejected with [Forbidden].
+pub fn with_permission<R, S, P>(
+    rbac_service: Arc<RbacService<R>>,
+    permission: P,
+) -> impl Fn(S) -> Ready<Result<S, Rejection>> + Clone + Send + Sync
+where
+    R: RoleStorage + Send + Sync,
+    S: RbacSubject + Send,
+    P: Permission + Send + Sync,
+{
+    move |subject: S| {
+        ready(match rbac_service.has_permission(&subject, permission.clone()) {
+            Ok(()) => Ok(subject),
+            Err(e) => Err(::warp::reject::custom(Forbidden(e))),
+        })
+    }
+}
+
+/// Recovers [Forbidden] rejections into `403 Forbidden` replies, passing other rejections through
+pub async fn handle_rejection(rejection: Rejection) -> Result<impl Reply, Rejection> {
+    match rejection.find::<Forbidden>() {
+        Some(Forbidden(e)) => Ok(::warp::reply::with_status(e.to_string(), StatusCode::FORBIDDEN)),
+        None => Err(rejection),
+    }
+}
@@ -0,0 +1,82 @@
+//! Matcher behind tiny C ABI, so it can be compiled to `wasm32-unknown-unknown` and enforce permissions inside untrusted plugin guests:
+//!
+//! ```text
+//! cargo rustc --release --target wasm32-unknown-unknown --features wasm --crate-type cdylib
+//! ```
+//!
+//! Host writes UTF-8 inputs into guest memory allocated by [rbac_alloc], calls [rbac_check] and frees inputs with [rbac_free].
+//! Evaluation is deterministic and side-effect free: no clock, randomness, I/O or state survives between calls.
+
+use crate::{ConditionInput, RbacError, RbacService, Role};
+
+/// [rbac_check] result: permission is granted
+pub const RBAC_ALLOW: i32 = 1;
+/// [rbac_check] result: permission is denied
+pub const RBAC_DENY: i32 = 0;
+/// [rbac_check] result: roles aren't valid JSON array of roles, or inputs aren't UTF-8
+pub const RBAC_INVALID_ROLES: i32 = -1;
+/// [rbac_check] result: permission isn't `Domain::Object::Action`
+pub const RBAC_INVALID_PERMISSION: i32 = -2;
+
+/// Checks if subject holding all given roles (JSON array of serialized [Role]s) has permission (`"Orders::Order::Read"`).
+/// Subject without roles is denied, there are no fallback roles.
+pub fn check_json(roles_json: &str, permission: &str) -> Result<bool, RbacError> {
+    let roles: Vec<Role> = serde_json::from_str(roles_json).map_err(|e| RbacError::InvalidRoleData(e.to_string()))?;
+    let names: Vec<String> = roles.iter().map(|role| role.name.clone()).collect();
+    let mut builder = RbacService::builder();
+    builder.set_fallback_roles(Vec::new()).try_load_roles(roles)?;
+    let rbac_service = builder.build();
+
+    let permission = rbac_service.parse_permission(permission)?;
+    if names.is_empty() {
+        return Ok(false);
+    }
+    match rbac_service.check_roles(None, &ConditionInput::default(), &names, permission) {
+        Ok(()) => Ok(true),
+        Err(RbacError::PermissionDenied(_)) => Ok(false),
+        Err(e) => Err(e),
+    }
+}
+
+/// Allocates `len` bytes of guest memory for inputs of [rbac_check]
+#[unsafe(no_mangle)]
+pub extern "C" fn rbac_alloc(len: usize) -> *mut u8 {
+    let mut buffer = Vec::<u8>::with_capacity(len);
+    let ptr = buffer.as_mut_ptr();
+    std::mem::forget(buffer);
+    ptr
+}
+
+/// Frees memory allocated by [rbac_alloc]
+///
+/// # Safety
+/// `ptr` must be returned by [rbac_alloc] called with the same `len`, and not freed yet.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn rbac_free(ptr: *mut u8, len: usize) {
+    // SAFETY: buffer was allocated by rbac_alloc with capacity `len`
+    drop(unsafe { Vec::from_raw_parts(ptr, 0, len) });
+}
+
+/// [check_json] of inputs written into guest memory, returning [RBAC_ALLOW], [RBAC_DENY] or negative error code
+///
+/// # Safety
+/// Pointers must point to `roles_len` and `permission_len` initialized bytes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn rbac_check(roles_ptr: *const u8, roles_len: usize, permission_ptr: *const u8, permission_len: usize) -> i32 {
+    // SAFETY: caller guarantees both buffers are initialized
+    let (roles, permission) = unsafe {
+        (
+            std::slice::from_raw_parts(roles_ptr, roles_len),
+            std::slice::from_raw_parts(permission_ptr, permission_len),
+        )
+    };
+    let (Ok(roles), Ok(permission)) = (std::str::from_utf8(roles), std::str::from_utf8(permission)) else {
+        return RBAC_INVALID_ROLES;
+    };
+    match check_json(roles, permission) {
+        Ok(true) => RBAC_ALLOW,
+        Ok(false) => RBAC_DENY,
+        Err(RbacError::InvalidPermission(_) | RbacError::UnknownPermission { .. }) => RBAC_INVALID_PERMISSION,
+        Err(_) => RBAC_INVALID_ROLES,
+    }
+}
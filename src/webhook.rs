@@ -0,0 +1,155 @@
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::Duration,
+};
+
+use serde_json::Value;
+use tokio::sync::mpsc::{self, Receiver, Sender, error::TrySendError};
+
+use crate::{
+    AuditSink, RbacDecision, RoleChangeEvent, RoleChangeSink,
+    publisher::{decision_event, role_change_event},
+};
+
+/// Delivery settings of [WebhookSink]
+#[derive(Debug, Clone)]
+pub struct WebhookOptions {
+    /// Maximum number of events per POST
+    pub batch_size: usize,
+    /// How long to wait for batch to fill up before sending what's collected
+    pub flush_interval: Duration,
+    /// Maximum number of queued events, further events are dropped until queue drains
+    pub queue_capacity: usize,
+    /// Retries of failed POST, after which batch is dropped
+    pub max_retries: u32,
+    /// Delay before first retry, doubled on every next one
+    pub retry_backoff: Duration,
+    /// Timeout of single POST
+    pub timeout: Duration,
+}
+
+impl Default for WebhookOptions {
+    fn default() -> Self {
+        WebhookOptions {
+            batch_size: 100,
+            flush_interval: Duration::from_secs(1),
+            queue_capacity: 10_000,
+            max_retries: 3,
+            retry_backoff: Duration::from_millis(200),
+            timeout: Duration::from_secs(10),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct WebhookStats {
+    delivered: AtomicU64,
+    dropped: AtomicU64,
+}
+
+/// Audit and role change sink POSTing events as JSON arrays to configured URL.
+///
+/// Events are queued without blocking checks and sent in batches by background task: decisions as
+/// `{"event": "decision", ...}` with [DecisionDto][crate::DecisionDto] fields, role changes as `{"event": "role_change", "kind": "updated", ...}`.
+/// Failed POSTs (including non-2xx responses) are retried with exponential backoff. When queue is full, new events are dropped
+/// rather than slowing checks down, see [.dropped()][WebhookSink#method.dropped].
+///
+/// Sink is cheap to clone, so the same queue may be registered both as audit and role change sink.
+/// Background task stops after sending queued events, once all clones are dropped.
+#[derive(Debug, Clone)]
+pub struct WebhookSink {
+    sender: Sender<Value>,
+    stats: Arc<WebhookStats>,
+}
+
+impl WebhookSink {
+    /// Starts background task sending events to `url`, must be called within tokio runtime
+    pub fn new(url: &str, options: WebhookOptions) -> Self {
+        Self::with_client(reqwest::Client::new(), url, options)
+    }
+
+    /// Like [WebhookSink::new], but sends with given client (e.g. with authentication headers or custom TLS)
+    pub fn with_client(client: reqwest::Client, url: &str, options: WebhookOptions) -> Self {
+        let (sender, receiver) = mpsc::channel(options.queue_capacity.max(1));
+        let stats = Arc::new(WebhookStats::default());
+        tokio::spawn(deliver(client, url.to_string(), options, receiver, stats.clone()));
+        WebhookSink { sender, stats }
+    }
+
+    /// Number of events accepted by webhook
+    pub fn delivered(&self) -> u64 {
+        self.stats.delivered.load(Ordering::Relaxed)
+    }
+
+    /// Number of events dropped because queue was full or POST failed after all retries
+    pub fn dropped(&self) -> u64 {
+        self.stats.dropped.load(Ordering::Relaxed)
+    }
+
+    fn enqueue(&self, event: Value) {
+        if let Err(TrySendError::Full(_) | TrySendError::Closed(_)) = self.sender.try_send(event) {
+            self.stats.dropped.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+impl AuditSink for WebhookSink {
+    fn on_decision(&self, decision: &RbacDecision) {
+        self.enqueue(decision_event(decision));
+    }
+}
+
+impl RoleChangeSink for WebhookSink {
+    fn on_role_change(&self, event: &RoleChangeEvent) {
+        self.enqueue(role_change_event(event));
+    }
+}
+
+/// Background task: collects batches from queue and POSTs them until queue is closed and drained
+async fn deliver(client: reqwest::Client, url: String, options: WebhookOptions, mut receiver: Receiver<Value>, stats: Arc<WebhookStats>) {
+    let batch_size = options.batch_size.max(1);
+    while let Some(first) = receiver.recv().await {
+        let mut batch = vec![first];
+        let deadline = tokio::time::Instant::now() + options.flush_interval;
+        while batch.len() < batch_size {
+            match tokio::time::timeout_at(deadline, receiver.recv()).await {
+                Ok(Some(event)) => batch.push(event),
+                Ok(None) | Err(_) => break,
+            }
+        }
+
+        let count = batch.len() as u64;
+        match post(&client, &url, &options, &Value::Array(batch)).await {
+            true => stats.delivered.fetch_add(count, Ordering::Relaxed),
+            false => stats.dropped.fetch_add(count, Ordering::Relaxed),
+        };
+    }
+}
+
+/// POSTs batch, retrying failures with exponential backoff. Returns whether webhook accepted it.
+async fn post(client: &reqwest::Client, url: &str, options: &WebhookOptions, batch: &Value) -> bool {
+    let body = batch.to_string();
+    let mut backoff = options.retry_backoff;
+    for attempt in 0..=options.max_retries {
+        if attempt > 0 {
+            tokio::time::sleep(backoff).await;
+            backoff *= 2;
+        }
+        let response = client
+            .post(url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .timeout(options.timeout)
+            .body(body.clone())
+            .send()
+            .await;
+        if let Ok(response) = response
+            && response.status().is_success()
+        {
+            return true;
+        }
+    }
+    false
+}